@@ -0,0 +1,418 @@
+//! Measures of a layout beyond beatable or not, from the solver's states.
+
+use std::{
+    collections::{HashMap, HashSet},
+    time::Instant,
+};
+
+use crate::{
+    rooms::{canonical_rotations, Room, Rotation},
+    solve::{first_state, step, target_mask, SolveState},
+    store::StateHasher,
+    verify::{out_of_budget, Operations, SearchStats, SolverConfig, Verdict},
+};
+
+/// How many ways there are to beat a layout, by length.
+#[derive(Debug, Clone)]
+pub struct SolutionCounts {
+    /// `Beatable` if there's a solution within the length, `Indeterminate`
+    /// if there isn't or the budget ran out before all lengths were counted
+    pub verdict: Verdict,
+    /// indexed by the number of operations, saturates at `u64::MAX`
+    pub by_length: Vec<u64>,
+    pub stats: SearchStats,
+}
+
+impl SolutionCounts {
+    pub fn total(&self) -> u64 {
+        self.by_length
+            .iter()
+            .fold(0, |total: u64, count| total.saturating_add(*count))
+    }
+}
+
+/// Counts the solutions with at most `max_length` operations. A solution
+/// ends with the operation that reaches the last target, so none is counted
+/// again with more operations after it, and like
+/// [`all_optimal_solutions`](crate::solve::all_optimal_solutions) operations
+/// that lead from the same state to the same state count once. A layout with
+/// one narrow path has few, one that can be beaten in many orders has a lot.
+pub fn count_solutions(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    config: &SolverConfig,
+    max_length: usize,
+) -> SolutionCounts {
+    let start_time = Instant::now();
+    let mut stats = SearchStats::default();
+    let mut counts = SolutionCounts {
+        verdict: Verdict::Indeterminate("no solution within the length"),
+        by_length: vec![0; max_length + 1],
+        stats: SearchStats::default(),
+    };
+    let start = match first_state(rooms, rotations, config, &mut stats) {
+        Ok((start, _)) => start,
+        Err(verdict) => {
+            counts.verdict = verdict;
+            return counts;
+        }
+    };
+    let targets = target_mask(rooms, config);
+    // the distinct states every state leads to, states show up on many
+    // levels so they're only expanded once
+    let mut successors: HashMap<SolveState, Vec<SolveState>, StateHasher> =
+        HashMap::with_hasher(config.hasher);
+    let mut level: HashMap<SolveState, u64, StateHasher> = HashMap::with_hasher(config.hasher);
+    level.insert(start, 1);
+    for length in 0..=max_length {
+        let mut next_level = HashMap::with_hasher(config.hasher);
+        for (state, paths) in level {
+            if state.reached == targets {
+                counts.by_length[length] = counts.by_length[length].saturating_add(paths);
+                continue;
+            }
+            if length == max_length {
+                continue;
+            }
+            if !successors.contains_key(&state) {
+                if let Some(verdict) = out_of_budget(config, &stats, start_time) {
+                    counts.verdict = verdict;
+                    return finish(counts, stats, successors.len(), start_time);
+                }
+                stats.expanded(length);
+                let mut next: Vec<SolveState> = Vec::new();
+                for operation in enum_iterator::all::<Operations>() {
+                    match step(&state, operation, targets, config, &mut stats) {
+                        Ok(Some(new_state)) if !next.contains(&new_state) => next.push(new_state),
+                        Ok(_) => {}
+                        Err(cycle) => {
+                            counts.verdict = Verdict::ChainCycle(cycle);
+                            return finish(counts, stats, successors.len(), start_time);
+                        }
+                    }
+                }
+                successors.insert(state, next);
+            }
+            for new_state in &successors[&state] {
+                let count = next_level.entry(*new_state).or_insert(0u64);
+                *count = count.saturating_add(paths);
+            }
+        }
+        stats.max_depth = length;
+        level = next_level;
+    }
+    if counts.total() > 0 {
+        counts.verdict = Verdict::Beatable;
+    }
+    finish(counts, stats, successors.len(), start_time)
+}
+
+fn finish(
+    mut counts: SolutionCounts,
+    mut stats: SearchStats,
+    states: usize,
+    start_time: Instant,
+) -> SolutionCounts {
+    stats.states_visited = states;
+    stats.elapsed = start_time.elapsed();
+    counts.stats = stats;
+    counts
+}
+
+/// The graph of every state reachable from the start, split into strongly
+/// connected components: states that can all be reached from each other.
+/// An edge between two components can't be undone, there's no way back to
+/// where it came from, like opening a gate that stays open.
+#[derive(Debug, Clone)]
+pub struct StateGraph {
+    /// why the graph couldn't be built completely, the budget running out
+    /// or a chain cycle, the rest is empty then
+    pub incomplete: Option<Verdict>,
+    pub states: usize,
+    pub edges: usize,
+    /// the size of every component, largest first
+    pub components: Vec<usize>,
+    /// edges between components
+    pub irreversible: usize,
+    /// how many of the irreversible edges open a gate
+    pub irreversible_gate_openings: usize,
+    /// components with no edge out, once in one the rest of the graph is
+    /// out of reach for good
+    pub sinks: usize,
+    pub stats: SearchStats,
+}
+
+/// Builds the [`StateGraph`] of the layout. The entrances reached so far
+/// aren't part of the states here, they only ever grow and would make every
+/// edge that reaches one irreversible.
+pub fn state_graph(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    config: &SolverConfig,
+) -> StateGraph {
+    let start_time = Instant::now();
+    let mut stats = SearchStats::default();
+    let mut graph = StateGraph {
+        incomplete: None,
+        states: 0,
+        edges: 0,
+        components: Vec::new(),
+        irreversible: 0,
+        irreversible_gate_openings: 0,
+        sinks: 0,
+        stats: SearchStats::default(),
+    };
+    let start = match first_state(rooms, rotations, config, &mut stats) {
+        Ok((start, _)) => start,
+        Err(verdict) => {
+            graph.incomplete = Some(verdict);
+            return graph;
+        }
+    };
+    let mut states = vec![start];
+    let mut ids: HashMap<SolveState, usize, StateHasher> = HashMap::with_hasher(config.hasher);
+    ids.insert(start, 0);
+    let mut adjacency: Vec<Vec<usize>> = Vec::new();
+    while adjacency.len() < states.len() {
+        if let Some(verdict) = out_of_budget(config, &stats, start_time) {
+            graph.incomplete = Some(verdict);
+            return finish_graph(graph, stats, states.len(), start_time);
+        }
+        stats.nodes_expanded += 1;
+        let state = states[adjacency.len()];
+        let mut next = Vec::new();
+        for operation in enum_iterator::all::<Operations>() {
+            // no targets, so nothing is ever marked as reached
+            let new_state = match step(&state, operation, 0, config, &mut stats) {
+                Ok(Some(new_state)) => new_state,
+                Ok(None) => continue,
+                Err(cycle) => {
+                    graph.incomplete = Some(Verdict::ChainCycle(cycle));
+                    return finish_graph(graph, stats, states.len(), start_time);
+                }
+            };
+            let id = *ids.entry(new_state).or_insert_with(|| {
+                states.push(new_state);
+                states.len() - 1
+            });
+            if !next.contains(&id) {
+                next.push(id);
+            }
+        }
+        adjacency.push(next);
+    }
+    let component = strongly_connected_components(&adjacency);
+    let mut sizes = vec![0; component.iter().max().map_or(0, |max| max + 1)];
+    let mut has_exit = vec![false; sizes.len()];
+    for (from, next) in adjacency.iter().enumerate() {
+        sizes[component[from]] += 1;
+        graph.edges += next.len();
+        for to in next {
+            if component[*to] != component[from] {
+                graph.irreversible += 1;
+                has_exit[component[from]] = true;
+                if states[*to].gates() != states[from].gates() {
+                    graph.irreversible_gate_openings += 1;
+                }
+            }
+        }
+    }
+    graph.sinks = has_exit.iter().filter(|exit| !**exit).count();
+    sizes.sort_unstable_by(|a, b| b.cmp(a));
+    graph.components = sizes;
+    finish_graph(graph, stats, states.len(), start_time)
+}
+
+fn finish_graph(
+    mut graph: StateGraph,
+    mut stats: SearchStats,
+    states: usize,
+    start_time: Instant,
+) -> StateGraph {
+    graph.states = states;
+    stats.states_visited = states;
+    stats.elapsed = start_time.elapsed();
+    graph.stats = stats;
+    graph
+}
+
+/// How big the space of (board, position, gates) states of a layout is, to
+/// size encodings and tables for it.
+#[derive(Debug, Clone)]
+pub struct StateSpace {
+    /// why not every state could be explored, the budget running out or a
+    /// chain cycle, the rest only counts what was explored until then
+    pub incomplete: Option<Verdict>,
+    /// the states by the fewest operations they're away from the start, the
+    /// last index is the max depth
+    pub by_depth: Vec<usize>,
+    /// distinct arrangements of the rooms and their rotations, boards that
+    /// only differ in ways that don't matter like
+    /// [`are_equivalent`](crate::rooms::are_equivalent) ones count once
+    pub boards: usize,
+    /// distinct combinations of open gates
+    pub gate_sets: usize,
+    /// the states by how many different states they lead to with one
+    /// operation
+    pub by_branching: Vec<usize>,
+    pub stats: SearchStats,
+}
+
+impl StateSpace {
+    pub fn states(&self) -> usize {
+        self.by_depth.iter().sum()
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.by_depth.len().saturating_sub(1)
+    }
+
+    /// the operations going to a different state per state, on average
+    pub fn mean_branching(&self) -> f64 {
+        let expanded: usize = self.by_branching.iter().sum();
+        let edges: usize = self
+            .by_branching
+            .iter()
+            .enumerate()
+            .map(|(branching, count)| branching * count)
+            .sum();
+        edges as f64 / expanded.max(1) as f64
+    }
+}
+
+/// Explores every state of the layout breadth first and counts them, with
+/// the entrances reached left out of the states like in [`state_graph`].
+pub fn state_space(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    config: &SolverConfig,
+) -> StateSpace {
+    let start_time = Instant::now();
+    let mut stats = SearchStats::default();
+    let mut space = StateSpace {
+        incomplete: None,
+        by_depth: Vec::new(),
+        boards: 0,
+        gate_sets: 0,
+        by_branching: Vec::new(),
+        stats: SearchStats::default(),
+    };
+    let start = match first_state(rooms, rotations, config, &mut stats) {
+        Ok((start, _)) => start,
+        Err(verdict) => {
+            space.incomplete = Some(verdict);
+            return space;
+        }
+    };
+    let mut seen: HashSet<SolveState, StateHasher> = HashSet::with_hasher(config.hasher);
+    let mut boards = HashSet::with_hasher(config.hasher);
+    let mut gate_sets = HashSet::new();
+    seen.insert(start);
+    let mut level = vec![start];
+    'levels: while !level.is_empty() {
+        space.by_depth.push(level.len());
+        let mut next_level = Vec::new();
+        for state in level {
+            boards.insert((
+                *state.rooms(),
+                canonical_rotations(state.rooms(), state.rotations()),
+            ));
+            gate_sets.insert(state.gates());
+            if let Some(verdict) = out_of_budget(config, &stats, start_time) {
+                space.incomplete = Some(verdict);
+                break 'levels;
+            }
+            stats.expanded(space.by_depth.len() - 1);
+            let mut next = Vec::new();
+            for operation in enum_iterator::all::<Operations>() {
+                let new_state = match step(&state, operation, 0, config, &mut stats) {
+                    Ok(Some(new_state)) => new_state,
+                    Ok(None) => continue,
+                    Err(cycle) => {
+                        space.incomplete = Some(Verdict::ChainCycle(cycle));
+                        break 'levels;
+                    }
+                };
+                if new_state == state || next.contains(&new_state) {
+                    continue;
+                }
+                next.push(new_state);
+                if seen.insert(new_state) {
+                    next_level.push(new_state);
+                } else {
+                    stats.transposition_hits += 1;
+                }
+            }
+            if space.by_branching.len() <= next.len() {
+                space.by_branching.resize(next.len() + 1, 0);
+            }
+            space.by_branching[next.len()] += 1;
+        }
+        level = next_level;
+    }
+    space.boards = boards.len();
+    space.gate_sets = gate_sets.len();
+    stats.max_depth = space.max_depth();
+    stats.states_visited = seen.len();
+    stats.elapsed = start_time.elapsed();
+    space.stats = stats;
+    space
+}
+
+/// Tarjan's algorithm without recursion, the graphs are far too deep for the
+/// stack. Returns the component of every node.
+fn strongly_connected_components(adjacency: &[Vec<usize>]) -> Vec<usize> {
+    const UNVISITED: usize = usize::MAX;
+    let n = adjacency.len();
+    let mut index = vec![UNVISITED; n];
+    let mut low = vec![0; n];
+    let mut on_stack = vec![false; n];
+    let mut component = vec![UNVISITED; n];
+    let mut stack = Vec::new();
+    let mut next_index = 0;
+    let mut components = 0;
+    for root in 0..n {
+        if index[root] != UNVISITED {
+            continue;
+        }
+        // nodes being visited with how many of their edges are done
+        let mut calls = vec![(root, 0)];
+        index[root] = next_index;
+        low[root] = next_index;
+        next_index += 1;
+        stack.push(root);
+        on_stack[root] = true;
+        while let Some((node, edge)) = calls.last_mut() {
+            let node = *node;
+            if let Some(&next) = adjacency[node].get(*edge) {
+                *edge += 1;
+                if index[next] == UNVISITED {
+                    index[next] = next_index;
+                    low[next] = next_index;
+                    next_index += 1;
+                    stack.push(next);
+                    on_stack[next] = true;
+                    calls.push((next, 0));
+                } else if on_stack[next] {
+                    low[node] = low[node].min(index[next]);
+                }
+                continue;
+            }
+            calls.pop();
+            if let Some((parent, _)) = calls.last() {
+                low[*parent] = low[*parent].min(low[node]);
+            }
+            if low[node] == index[node] {
+                while let Some(member) = stack.pop() {
+                    on_stack[member] = false;
+                    component[member] = components;
+                    if member == node {
+                        break;
+                    }
+                }
+                components += 1;
+            }
+        }
+    }
+    component
+}
@@ -0,0 +1,126 @@
+//! Newline delimited JSON for pipelines: one layout per line in, one result
+//! per line out in the same order, written as soon as the layout is done so
+//! memory stays the same however many lines go through.
+//!
+//! A line is an object with the layout as its share `code` or, unturned, in
+//! `notation` like `STR SV ET/LMF BOS AC/FS SSH -`. `solve` searches for a
+//! shortest solution instead of only verifying, and the overrides of the
+//! [`rpc`](crate::rpc) params work here too. An `id` is copied to the result
+//! as it is. A line that can't be read gets a result with an `error` instead
+//! of a verdict and the batch goes on.
+
+use std::io::{self, BufRead, Write};
+
+use crate::{
+    json::Json,
+    partial::parse_partial_layout,
+    rooms::{Layout, Room, Rotation, NO_ROTATIONS},
+    rpc::{config_with_overrides, operations_json, stats_json, verdict_fields},
+    share::{layout_code, parse_layout_code},
+    solve::solve_rotated_rooms,
+    verify::{verify_rotated_rooms, SolverConfig},
+};
+
+/// How many lines a batch went through.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BatchSummary {
+    pub layouts: usize,
+    /// lines that got an error instead of a verdict
+    pub errors: usize,
+}
+
+/// Reads layouts from `input` until it ends and writes a result for every
+/// one to `output`, verifying them with `config` or solving them if `solve`
+/// unless a line says otherwise. Empty lines are skipped.
+pub fn run_batch(
+    input: impl BufRead,
+    output: &mut impl Write,
+    config: &SolverConfig,
+    solve: bool,
+) -> io::Result<BatchSummary> {
+    let mut summary = BatchSummary::default();
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let result = batch_result(&line, config, solve);
+        if result.get("error").is_some() {
+            summary.errors += 1;
+        } else {
+            summary.layouts += 1;
+        }
+        writeln!(output, "{result}")?;
+        output.flush()?;
+    }
+    Ok(summary)
+}
+
+/// the result line for an input line
+pub fn batch_result(line: &str, config: &SolverConfig, solve: bool) -> Json {
+    let request = Json::parse(line);
+    let id = request
+        .as_ref()
+        .ok()
+        .and_then(|request| request.get("id").cloned());
+    let mut fields = match request.and_then(|request| check(&request, config, solve)) {
+        Ok(fields) => fields,
+        Err(e) => vec![("error".to_string(), e.into())],
+    };
+    if let Some(id) = id {
+        fields.insert(0, ("id".to_string(), id));
+    }
+    Json::Object(fields)
+}
+
+fn check(
+    request: &Json,
+    config: &SolverConfig,
+    solve: bool,
+) -> Result<Vec<(String, Json)>, String> {
+    let config = config_with_overrides(config, request)?;
+    let (rooms, rotations) = layout(request)?;
+    let solve = match request.get("solve") {
+        Some(solve) => solve.as_bool().ok_or("invalid solve")?,
+        None => solve,
+    };
+    let mut fields = vec![("code".to_string(), layout_code(&rooms, &rotations).into())];
+    if solve {
+        let result = solve_rotated_rooms(&rooms, &rotations, &config);
+        fields.extend(verdict_fields(&result.verdict));
+        if let Some(solution) = result.solution {
+            fields.push((
+                "start_panel".to_string(),
+                format!("{:?}", solution.start_panel).into(),
+            ));
+            fields.push((
+                "operations".to_string(),
+                operations_json(&solution.operations),
+            ));
+        }
+        fields.push(("stats".to_string(), stats_json(&result.stats)));
+    } else {
+        let verification = verify_rotated_rooms(&rooms, &rotations, &config);
+        fields.extend(verdict_fields(&verification.verdict));
+        fields.push(("stats".to_string(), stats_json(&verification.stats)));
+    }
+    Ok(fields)
+}
+
+fn layout(request: &Json) -> Result<([Room; 9], [Rotation; 9]), String> {
+    if let Some(code) = request.get("code") {
+        return parse_layout_code(code.as_str().ok_or("invalid code")?);
+    }
+    let notation = request
+        .get("notation")
+        .ok_or("missing code or notation")?
+        .as_str()
+        .ok_or("invalid notation")?;
+    let rooms = parse_partial_layout(notation)?
+        .iter()
+        .copied()
+        .collect::<Option<Vec<Room>>>()
+        .and_then(|rooms| rooms.try_into().ok())
+        .ok_or_else(|| format!("unknown tiles in {notation}"))?;
+    Layout::new(rooms, NO_ROTATIONS).map(Layout::into_parts)
+}
@@ -0,0 +1,183 @@
+use crate::rooms::{
+    do_move, Direction, DoorRules, Entrance, OpenedGates, Room, Rotation, BOTH_WAY_DOORS,
+    NO_ROTATIONS,
+};
+
+/// Returned when walking through doors leads back to an entrance that was
+/// already passed in the same walk, which would otherwise never terminate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChainCycle {
+    pub entrance: Entrance,
+    pub tile: u8,
+}
+
+impl std::fmt::Display for ChainCycle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "door loop at {:?} on tile {}", self.entrance, self.tile)
+    }
+}
+
+/// Walks through the rooms starting at a door, yielding every entrance that
+/// is passed together with the tile it is on, alternating between the
+/// entrance a room is entered through and the one it is left through.
+///
+/// The walk stops at a dead end, a closed gate or the edge of the board. If
+/// the doors loop back onto themselves it stops as well, [`ChainWalker::cycle_found`]
+/// tells these cases apart.
+#[derive(Debug, Clone)]
+pub struct ChainWalker<'a> {
+    rooms: &'a [Room; 9],
+    rotations: &'a [Rotation; 9],
+    doors: &'a DoorRules,
+    gates: OpenedGates,
+    start: (u8, Direction),
+    next: Option<(Entrance, u8)>,
+    entering: bool,
+    // every entrance can be passed at most once, seeing one again means the
+    // doors loop back onto themselves
+    visited: u32,
+    cycle: Option<ChainCycle>,
+}
+
+impl<'a> ChainWalker<'a> {
+    /// starts walking through the room on `tile` from its door in
+    /// `direction`, as if having just come in through it
+    pub fn new(rooms: &'a [Room; 9], gates: OpenedGates, tile: u8, direction: Direction) -> Self {
+        let mut walker = ChainWalker {
+            rooms,
+            rotations: &NO_ROTATIONS,
+            doors: &BOTH_WAY_DOORS,
+            gates,
+            start: (tile, direction),
+            next: None,
+            entering: true,
+            visited: 0,
+            cycle: None,
+        };
+        walker.next = walker.entrance_at(tile, direction);
+        walker
+    }
+
+    /// walks a board with turned rooms, `direction` is still the direction
+    /// on the board, not in the turned room
+    pub fn with_rotations(mut self, rotations: &'a [Rotation; 9]) -> Self {
+        self.rotations = rotations;
+        self.next = self.entrance_at(self.start.0, self.start.1);
+        self
+    }
+
+    /// walks a board with one-way doors or doors on other sides
+    pub fn with_door_rules(mut self, doors: &'a DoorRules) -> Self {
+        self.doors = doors;
+        self.next = self.entrance_at(self.start.0, self.start.1);
+        self
+    }
+
+    fn entrance_at(&self, tile: u8, direction: Direction) -> Option<(Entrance, u8)> {
+        let tile_index = tile as usize;
+        self.doors
+            .on_board(
+                self.rooms[tile_index],
+                self.rotations[tile_index],
+                direction,
+            )
+            .map(|entrance| (entrance, tile))
+    }
+
+    /// the loop the walk ran into, if that is why it ended
+    pub fn cycle_found(&self) -> Option<ChainCycle> {
+        self.cycle
+    }
+}
+
+impl Iterator for ChainWalker<'_> {
+    type Item = (Entrance, u8);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (entrance, tile) = self.next.take()?;
+        let bit = 1 << entrance as u32;
+        if self.visited & bit != 0 {
+            self.cycle = Some(ChainCycle { entrance, tile });
+            return None;
+        }
+        self.visited |= bit;
+        self.next = if self.entering {
+            entrance.traverse_room(self.gates).map(|exit| (exit, tile))
+        } else if self.doors.can_exit(entrance) {
+            let direction = self.rotations[tile as usize].rotate(self.doors.direction(entrance));
+            do_move(tile, direction)
+                .and_then(|(tile, direction)| self.entrance_at(tile, direction))
+                .filter(|(entrance, _)| self.doors.can_enter(*entrance))
+        } else {
+            None
+        };
+        self.entering = !self.entering;
+        Some((entrance, tile))
+    }
+}
+
+/// where walks from the player standing at a door start: through the room
+/// the player is in, then through the neighboring room behind the door if
+/// the door can be passed
+pub(crate) fn walk_starts(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    doors: &DoorRules,
+    tile: u8,
+    direction: Direction,
+) -> impl Iterator<Item = (u8, Direction)> {
+    let entrance_at = |tile: u8, direction: Direction| {
+        doors.on_board(rooms[tile as usize], rotations[tile as usize], direction)
+    };
+    let passable = entrance_at(tile, direction).is_none_or(|entrance| doors.can_exit(entrance));
+    let behind = do_move(tile, direction).filter(|(other_tile, other_dir)| {
+        passable
+            && entrance_at(*other_tile, *other_dir)
+                .is_some_and(|entrance| doors.can_enter(entrance))
+    });
+    [Some((tile, direction)), behind].into_iter().flatten()
+}
+
+/// like [`follow_chain`], but if nothing is found also walks through the door
+/// the player is standing in front of, in the neighboring room
+pub(crate) fn follow_chain_both<T>(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    doors: &DoorRules,
+    gates: OpenedGates,
+    tile: u8,
+    direction: Direction,
+    check: &mut impl FnMut(Entrance, u8) -> Option<T>,
+) -> Result<Option<T>, ChainCycle> {
+    for (tile, direction) in walk_starts(rooms, rotations, doors, tile, direction) {
+        if let Some(val) = follow_chain(rooms, rotations, doors, gates, tile, direction, check)? {
+            return Ok(Some(val));
+        }
+    }
+    Ok(None)
+}
+
+/// calls `check` for every entrance of the walk started at `tile` and
+/// `direction`, returning the first value it produces
+pub(crate) fn follow_chain<T>(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    doors: &DoorRules,
+    gates: OpenedGates,
+    tile: u8,
+    direction: Direction,
+    check: &mut impl FnMut(Entrance, u8) -> Option<T>,
+) -> Result<Option<T>, ChainCycle> {
+    let mut walker = ChainWalker::new(rooms, gates, tile, direction)
+        .with_rotations(rotations)
+        .with_door_rules(doors);
+    for (entrance, tile) in walker.by_ref() {
+        if let Some(val) = check(entrance, tile) {
+            return Ok(Some(val));
+        }
+    }
+    match walker.cycle_found() {
+        Some(cycle) => Err(cycle),
+        None => Ok(None),
+    }
+}
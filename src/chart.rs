@@ -0,0 +1,112 @@
+//! SVG charts of what enumerate finds, drawn by hand like the QR codes.
+
+use std::collections::BTreeMap;
+
+use crate::{enumerate::Heatmap, rooms::ALL_ROOMS};
+
+const FONT: &str = "font-family=\"sans-serif\" font-size=\"12\"";
+
+/// A bar per solution length with the number of layouts whose shortest
+/// solution has that many operations, `lengths` maps length to count.
+pub fn length_histogram_svg(lengths: &BTreeMap<usize, usize>) -> String {
+    let (bar, gap, height, margin) = (16, 4, 200, 40);
+    let (Some(first), Some(last)) = (lengths.keys().next(), lengths.keys().next_back()) else {
+        return empty_svg("no beatable layouts");
+    };
+    let max = lengths.values().copied().max().unwrap_or(1).max(1);
+    let bars = last - first + 1;
+    let width = 2 * margin + bars * (bar + gap);
+    let full_height = height + 2 * margin;
+    let mut body = String::new();
+    for (i, length) in (*first..=*last).enumerate() {
+        let count = lengths.get(&length).copied().unwrap_or(0);
+        let bar_height = count * height / max;
+        let x = margin + i * (bar + gap);
+        body.push_str(&format!(
+            "<rect x=\"{x}\" y=\"{}\" width=\"{bar}\" height=\"{bar_height}\" fill=\"#4477aa\"><title>{length} operations: {count} layouts</title></rect>",
+            margin + height - bar_height
+        ));
+        body.push_str(&format!(
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\" {FONT}>{length}</text>",
+            x + bar / 2,
+            margin + height + 14
+        ));
+    }
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {width} {full_height}\">\
+         <rect width=\"100%\" height=\"100%\" fill=\"#fff\"/>\
+         <text x=\"{margin}\" y=\"{}\" {FONT}>layouts by shortest solution, most: {max}</text>\
+         {body}\
+         <text x=\"{}\" y=\"{}\" text-anchor=\"middle\" {FONT}>operations</text></svg>\n",
+        margin - 16,
+        width / 2,
+        full_height - 8
+    )
+}
+
+/// A grid with a row per room and a column per tile, shaded by the share of
+/// the layouts with the room on that tile that are beatable.
+pub fn solvable_by_position_svg(beatable: &Heatmap, all: &Heatmap) -> String {
+    let (cell, left, top) = (40, 48, 48);
+    let width = left + 9 * cell + 8;
+    let height = top + ALL_ROOMS.len() * cell + 8;
+    let mut body = String::new();
+    for tile in 0..9 {
+        body.push_str(&format!(
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\" {FONT}>{tile}</text>",
+            left + tile * cell + cell / 2,
+            top - 8
+        ));
+    }
+    for (row, room) in ALL_ROOMS.iter().enumerate() {
+        let y = top + row * cell;
+        body.push_str(&format!(
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"end\" {FONT}>{}</text>",
+            left - 6,
+            y + cell / 2 + 4,
+            room.code()
+        ));
+        for tile in 0..9 {
+            let x = left + tile * cell;
+            let total = all.count(*room, tile);
+            if total == 0 {
+                body.push_str(&format!(
+                    "<rect x=\"{x}\" y=\"{y}\" width=\"{cell}\" height=\"{cell}\" fill=\"#eee\"/>"
+                ));
+                continue;
+            }
+            let share = beatable.count(*room, tile) as f64 / total as f64;
+            // from white to dark green
+            let shade = |full: f64| (255.0 - share * (255.0 - full)).round() as u8;
+            body.push_str(&format!(
+                "<rect x=\"{x}\" y=\"{y}\" width=\"{cell}\" height=\"{cell}\" fill=\"#{:02x}{:02x}{:02x}\"><title>{} on tile {tile}: {:.1}% beatable</title></rect>",
+                shade(0x1b as f64),
+                shade(0x78 as f64),
+                shade(0x37 as f64),
+                room.code(),
+                share * 100.0
+            ));
+            body.push_str(&format!(
+                "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\" fill=\"{}\" {FONT}>{:.0}</text>",
+                x + cell / 2,
+                y + cell / 2 + 4,
+                if share > 0.5 { "#fff" } else { "#000" },
+                share * 100.0
+            ));
+        }
+    }
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {width} {height}\">\
+         <rect width=\"100%\" height=\"100%\" fill=\"#fff\"/>\
+         <text x=\"8\" y=\"16\" {FONT}>% beatable by room and tile</text>\
+         {body}</svg>\n"
+    )
+}
+
+fn empty_svg(text: &str) -> String {
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 200 40\">\
+         <rect width=\"100%\" height=\"100%\" fill=\"#fff\"/>\
+         <text x=\"8\" y=\"24\" {FONT}>{text}</text></svg>\n"
+    )
+}
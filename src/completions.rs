@@ -0,0 +1,271 @@
+//! Shell completion scripts, made from the usage text so every command and
+//! option the usage lists can be completed without keeping a second list.
+//!
+//! The usage is read by its layout: a command or an option is a line
+//! starting with four spaces and then its name, an option's value follows
+//! the name after one space and the description after two or more.
+//! `<path>` values complete files, `<dir>` values directories and values
+//! like `<text|csv>` their choices.
+
+use std::fmt::Write;
+
+use enum_iterator::{all, Sequence};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Sequence)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    pub fn name(self) -> &'static str {
+        match self {
+            Shell::Bash => "bash",
+            Shell::Zsh => "zsh",
+            Shell::Fish => "fish",
+        }
+    }
+}
+
+pub fn parse_shell(name: &str) -> Result<Shell, String> {
+    all::<Shell>()
+        .find(|shell| shell.name() == name)
+        .ok_or_else(|| format!("unknown shell: {name}"))
+}
+
+/// What can be completed after an option.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OptionValue<'a> {
+    /// the option is a flag
+    None,
+    Path,
+    Dir,
+    Choices(Vec<&'a str>),
+    /// anything, nothing to complete
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CliOption<'a> {
+    /// with the dashes, like `--seed`
+    pub name: &'a str,
+    pub value: OptionValue<'a>,
+}
+
+/// The commands and options of a usage text.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CliSpec<'a> {
+    pub commands: Vec<&'a str>,
+    pub options: Vec<CliOption<'a>>,
+}
+
+impl<'a> CliSpec<'a> {
+    /// Reads the `commands:` and `options:` sections of `usage`.
+    pub fn from_usage(usage: &'a str) -> Self {
+        let mut spec = CliSpec::default();
+        let mut section = "";
+        for line in usage.lines() {
+            if let Some(name) = line.strip_suffix(':').filter(|name| !name.starts_with(' ')) {
+                section = name;
+                continue;
+            }
+            // continued descriptions are indented further
+            let Some(entry) = line
+                .strip_prefix("    ")
+                .filter(|entry| !entry.starts_with(' ') && !entry.is_empty())
+            else {
+                continue;
+            };
+            let (name, rest) = entry.split_once(' ').unwrap_or((entry, ""));
+            match section {
+                // analyze has a line for each of its subcommands
+                "commands" if spec.commands.contains(&name) => {}
+                "commands" => spec.commands.push(name),
+                "options" if name.starts_with("--") => {
+                    let value = if rest.is_empty() || rest.starts_with(' ') {
+                        OptionValue::None
+                    } else {
+                        let value = rest.split("  ").next().unwrap_or(rest);
+                        parse_value(value)
+                    };
+                    spec.options.push(CliOption { name, value });
+                }
+                _ => {}
+            }
+        }
+        spec
+    }
+
+    fn options_with<'s>(
+        &'s self,
+        pick: impl Fn(&OptionValue<'a>) -> bool + 's,
+    ) -> impl Iterator<Item = &'a str> + 's {
+        self.options
+            .iter()
+            .filter(move |option| pick(&option.value))
+            .map(|option| option.name)
+    }
+}
+
+fn parse_value(value: &str) -> OptionValue<'_> {
+    match value {
+        "<path>" => OptionValue::Path,
+        "<dir>" => OptionValue::Dir,
+        _ => match value.strip_prefix('<').and_then(|v| v.strip_suffix('>')) {
+            Some(choices) if choices.contains('|') && !choices.contains(['<', '>']) => {
+                OptionValue::Choices(choices.split('|').collect())
+            }
+            _ => OptionValue::Other,
+        },
+    }
+}
+
+/// The script that makes `shell` complete the commands and options in
+/// `spec` for `program`. A `completions` command completes the shell names.
+pub fn completion_script(shell: Shell, program: &str, spec: &CliSpec) -> String {
+    match shell {
+        Shell::Bash => bash_script(program, spec),
+        Shell::Zsh => zsh_script(program, spec),
+        Shell::Fish => fish_script(program, spec),
+    }
+}
+
+fn shell_names() -> String {
+    all::<Shell>()
+        .map(Shell::name)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn function_name(program: &str) -> String {
+    format!("_{}", program.replace('-', "_"))
+}
+
+fn bash_script(program: &str, spec: &CliSpec) -> String {
+    let function = function_name(program);
+    let mut out = String::new();
+    writeln!(out, "{function}() {{").unwrap();
+    out.push_str("    local cur=\"${COMP_WORDS[COMP_CWORD]}\"\n");
+    out.push_str("    local prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n");
+    out.push_str("    case \"$prev\" in\n");
+    for option in &spec.options {
+        if let OptionValue::Choices(choices) = &option.value {
+            writeln!(
+                out,
+                "        {})\n            COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n            return ;;",
+                option.name,
+                choices.join(" ")
+            )
+            .unwrap();
+        }
+    }
+    let mut case = |names: Vec<&str>, action: &str| {
+        if !names.is_empty() {
+            writeln!(out, "        {})\n            {action}", names.join("|")).unwrap();
+            out.push_str("            return ;;\n");
+        }
+    };
+    case(
+        spec.options_with(|value| *value == OptionValue::Path)
+            .collect(),
+        "COMPREPLY=($(compgen -f -- \"$cur\"))",
+    );
+    case(
+        spec.options_with(|value| *value == OptionValue::Dir)
+            .collect(),
+        "COMPREPLY=($(compgen -d -- \"$cur\"))",
+    );
+    case(
+        spec.options_with(|value| *value == OptionValue::Other)
+            .collect(),
+        "COMPREPLY=()",
+    );
+    writeln!(
+        out,
+        "        completions)\n            [[ $COMP_CWORD -eq 2 ]] && COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n            return ;;",
+        shell_names()
+    )
+    .unwrap();
+    out.push_str("    esac\n");
+    out.push_str("    if [[ $COMP_CWORD -eq 1 && $cur != -* ]]; then\n");
+    writeln!(
+        out,
+        "        COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))",
+        spec.commands.join(" ")
+    )
+    .unwrap();
+    out.push_str("    else\n");
+    let options: Vec<&str> = spec.options.iter().map(|option| option.name).collect();
+    writeln!(
+        out,
+        "        COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))",
+        options.join(" ")
+    )
+    .unwrap();
+    out.push_str("    fi\n}\n");
+    writeln!(out, "complete -F {function} {program}").unwrap();
+    out
+}
+
+fn zsh_script(program: &str, spec: &CliSpec) -> String {
+    let function = function_name(program);
+    let mut out = String::new();
+    writeln!(out, "#compdef {program}\n").unwrap();
+    writeln!(out, "{function}() {{").unwrap();
+    out.push_str("    if (( CURRENT == 3 )) && [[ $words[2] == completions ]]; then\n");
+    writeln!(out, "        _values shell {}", shell_names()).unwrap();
+    out.push_str("        return\n    fi\n");
+    out.push_str("    _arguments \\\n");
+    writeln!(out, "        '1::command:({})' \\", spec.commands.join(" ")).unwrap();
+    for (index, option) in spec.options.iter().enumerate() {
+        // the message is shown while typing the value, the option's name
+        // is safe to quote where the usage's placeholder might not be
+        let message = option.name.trim_start_matches('-');
+        let action = match &option.value {
+            OptionValue::None => String::new(),
+            OptionValue::Path => format!(":{message}:_files"),
+            OptionValue::Dir => format!(":{message}:_files -/"),
+            OptionValue::Choices(choices) => format!(":{message}:({})", choices.join(" ")),
+            OptionValue::Other => format!(":{message}: "),
+        };
+        let end = if index + 1 == spec.options.len() {
+            ""
+        } else {
+            " \\"
+        };
+        writeln!(out, "        '*{}{action}'{end}", option.name).unwrap();
+    }
+    out.push_str("}\n\n");
+    writeln!(out, "{function} \"$@\"").unwrap();
+    out
+}
+
+fn fish_script(program: &str, spec: &CliSpec) -> String {
+    let mut out = String::new();
+    writeln!(out, "complete -c {program} -f").unwrap();
+    writeln!(
+        out,
+        "complete -c {program} -n __fish_use_subcommand -a '{}'",
+        spec.commands.join(" ")
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "complete -c {program} -n '__fish_seen_subcommand_from completions' -a '{}'",
+        shell_names()
+    )
+    .unwrap();
+    for option in &spec.options {
+        let long = option.name.trim_start_matches('-');
+        let value = match &option.value {
+            OptionValue::None => String::new(),
+            OptionValue::Path => " -r -F".to_string(),
+            OptionValue::Dir => " -x -a '(__fish_complete_directories)'".to_string(),
+            OptionValue::Choices(choices) => format!(" -x -a '{}'", choices.join(" ")),
+            OptionValue::Other => " -x".to_string(),
+        };
+        writeln!(out, "complete -c {program} -l {long}{value}").unwrap();
+    }
+    out
+}
@@ -0,0 +1,147 @@
+//! Files that can be zstd compressed: the layout database, the search tree
+//! dumps and other big outputs. Compressed files are recognized by their
+//! magic number when read, and written for paths ending in `.zst`. The
+//! compression itself is done by the `zstd` program on the `PATH` with the
+//! `zstd` feature, without it compressed files are an error.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+/// the first bytes of every zstd frame
+pub const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+pub fn is_zstd(bytes: &[u8]) -> bool {
+    bytes.starts_with(&ZSTD_MAGIC)
+}
+
+/// The contents of the file, decompressed if it's compressed.
+pub fn read_file(path: impl AsRef<Path>) -> io::Result<Vec<u8>> {
+    let bytes = std::fs::read(path)?;
+    if !is_zstd(&bytes) {
+        return Ok(bytes);
+    }
+    decompress(&bytes)
+}
+
+/// [`read_file`] for text
+pub fn read_to_string(path: impl AsRef<Path>) -> io::Result<String> {
+    String::from_utf8(read_file(path)?)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "not UTF-8 text"))
+}
+
+/// A file being written, compressed on the way if it was created with a
+/// `.zst` path. [`finish`](Self::finish) has to be called to know it was
+/// all written.
+pub enum FileWriter {
+    Plain(BufWriter<File>),
+    #[cfg(feature = "zstd")]
+    Zstd(std::process::Child),
+}
+
+impl FileWriter {
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        if path.extension().is_some_and(|extension| extension == "zst") {
+            return compressor(path);
+        }
+        Ok(FileWriter::Plain(BufWriter::new(File::create(path)?)))
+    }
+
+    /// flushes everything and waits for the compression to be done
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            FileWriter::Plain(mut out) => out.flush(),
+            #[cfg(feature = "zstd")]
+            FileWriter::Zstd(mut child) => {
+                // closing stdin ends the input
+                drop(child.stdin.take());
+                let status = child.wait()?;
+                if !status.success() {
+                    return Err(io::Error::other(format!("zstd failed: {status}")));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Write for FileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            FileWriter::Plain(out) => out.write(buf),
+            #[cfg(feature = "zstd")]
+            FileWriter::Zstd(child) => child.stdin.as_mut().expect("open until finish").write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            FileWriter::Plain(out) => out.flush(),
+            #[cfg(feature = "zstd")]
+            FileWriter::Zstd(child) => child.stdin.as_mut().expect("open until finish").flush(),
+        }
+    }
+}
+
+#[cfg(feature = "zstd")]
+fn compressor(path: &Path) -> io::Result<FileWriter> {
+    use std::process::{Command, Stdio};
+
+    let out = File::create(path)?;
+    let child = Command::new("zstd")
+        .args(["-q", "-c"])
+        .stdin(Stdio::piped())
+        .stdout(out)
+        .spawn()
+        .map_err(|e| io::Error::new(e.kind(), format!("can't run zstd: {e}")))?;
+    Ok(FileWriter::Zstd(child))
+}
+
+#[cfg(not(feature = "zstd"))]
+fn compressor(_: &Path) -> io::Result<FileWriter> {
+    Err(unsupported())
+}
+
+#[cfg(feature = "zstd")]
+fn decompress(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    use std::process::{Command, Stdio};
+
+    let mut child = Command::new("zstd")
+        .args(["-q", "-d", "-c"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| io::Error::new(e.kind(), format!("can't run zstd: {e}")))?;
+    // written from another thread, zstd stops reading while its output
+    // isn't read
+    let mut stdin = child.stdin.take().expect("piped");
+    let output = std::thread::scope(|scope| {
+        let writer = scope.spawn(move || stdin.write_all(bytes));
+        let output = child.wait_with_output();
+        writer.join().expect("writing to zstd panicked")?;
+        output
+    })?;
+    if !output.status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("zstd failed: {}", output.status),
+        ));
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn decompress(_: &[u8]) -> io::Result<Vec<u8>> {
+    Err(unsupported())
+}
+
+#[cfg(not(feature = "zstd"))]
+fn unsupported() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "zstd compressed files need the zstd feature",
+    )
+}
@@ -0,0 +1,86 @@
+//! Layouts whose verdicts are known, to catch changes to the rules that
+//! change a verdict without meaning to. The layouts are in
+//! `data/corpus.txt`, which is built into the library.
+
+use crate::{
+    partial::parse_partial_layout,
+    rooms::Room,
+    solve::solve_rooms,
+    verify::{verify_rooms, SolverConfig, Verdict},
+};
+
+const CORPUS: &str = include_str!("../data/corpus.txt");
+
+/// What the default options should find for a layout of the corpus.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expected {
+    Beatable {
+        min_length: usize,
+    },
+    /// with the reason of [`Verdict::NotBeatable`]
+    NotBeatable(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorpusEntry {
+    pub rooms: [Room; 9],
+    pub expected: Expected,
+}
+
+impl CorpusEntry {
+    /// Verifies the layout, and solves it if it's beatable, `Err` with what
+    /// came out instead if that's not what's expected. Options that change
+    /// verdicts, like another win condition, make entries fail.
+    pub fn check(&self, config: &SolverConfig) -> Result<(), String> {
+        let verdict = verify_rooms(&self.rooms, config).verdict;
+        match (&self.expected, verdict) {
+            (Expected::Beatable { min_length }, Verdict::Beatable) => {
+                match solve_rooms(&self.rooms, config).solution {
+                    Some(solution) if solution.operations.len() == *min_length => Ok(()),
+                    Some(solution) => Err(format!(
+                        "shortest solution has {} operations instead of {min_length}",
+                        solution.operations.len()
+                    )),
+                    None => Err("the verifier found it beatable, the solver didn't".to_string()),
+                }
+            }
+            (Expected::NotBeatable(expected), Verdict::NotBeatable(reason))
+                if reason == expected =>
+            {
+                Ok(())
+            }
+            (_, verdict) => Err(format!("verdict is {verdict:?}")),
+        }
+    }
+}
+
+/// Every layout of the corpus, in the order of the file.
+pub fn iter() -> impl Iterator<Item = CorpusEntry> {
+    CORPUS.lines().enumerate().filter_map(|(i, line)| {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        // the corpus is built in, so a broken line is a bug
+        Some(parse_entry(line).unwrap_or_else(|e| panic!("corpus line {}: {e}", i + 1)))
+    })
+}
+
+fn parse_entry(line: &str) -> Result<CorpusEntry, String> {
+    let (notation, expected) = line.split_once('|').ok_or("missing |")?;
+    let partial = parse_partial_layout(notation)?;
+    let mut rooms = [Room::Empty; 9];
+    for (room, known) in rooms.iter_mut().zip(partial) {
+        *room = known.ok_or("unknown tile")?;
+    }
+    let expected = match expected.trim().split_once(' ') {
+        Some(("beatable", length)) => Expected::Beatable {
+            min_length: length
+                .parse()
+                .map_err(|_| format!("invalid length: {length}"))?,
+        },
+        Some(("not-beatable", reason)) => Expected::NotBeatable(reason.to_string()),
+        _ => return Err(format!("invalid verdict: {}", expected.trim())),
+    };
+    Ok(CorpusEntry { rooms, expected })
+}
@@ -0,0 +1,233 @@
+//! Real time instead of operation counts: what each operation takes, and a
+//! solver for the route that takes the least time overall.
+
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    time::Instant,
+};
+
+use crate::{
+    rooms::{ControlPanel, Room, Rotation},
+    solve::{first_state, step, target_mask, Solution, SolveResult, SolveState},
+    store::StateHasher,
+    verify::{out_of_budget, Operations, SearchStats, SolverConfig, Verdict},
+};
+
+const PANELS: usize = 4;
+
+/// Seconds each operation takes, by the control panel it's done from, or
+/// for walking to a panel by the panel walked to. How far the walk is isn't
+/// taken into account. The defaults are rough guesses, measure your own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CostModel {
+    /// indexed by [`ControlPanel`]
+    pub reach: [f64; PANELS],
+    pub slide: [f64; PANELS],
+    pub turn: [f64; PANELS],
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        CostModel {
+            reach: [6.0; PANELS],
+            slide: [2.5; PANELS],
+            turn: [2.0; PANELS],
+        }
+    }
+}
+
+impl CostModel {
+    /// the seconds `operation` takes with the player at `panel`
+    pub fn cost(&self, panel: ControlPanel, operation: Operations) -> f64 {
+        match operation {
+            Operations::Reach(target) => self.reach[target as usize],
+            Operations::Move(..) => self.slide[panel as usize],
+            Operations::Rotate(..) => self.turn[panel as usize],
+        }
+    }
+
+    /// the seconds all operations of the solution take together
+    pub fn route_cost(&self, solution: &Solution) -> f64 {
+        self.route_time(solution).total()
+    }
+
+    /// the seconds the solution takes, split up by kind of operation
+    pub fn route_time(&self, solution: &Solution) -> RouteTime {
+        let mut panel = solution.start_panel;
+        let mut time = RouteTime::default();
+        for operation in &solution.operations {
+            let cost = self.cost(panel, *operation);
+            match operation {
+                Operations::Reach(reached) => {
+                    time.walking += cost;
+                    panel = *reached;
+                }
+                Operations::Move(..) => time.sliding += cost,
+                Operations::Rotate(..) => time.turning += cost,
+            }
+        }
+        time
+    }
+
+    /// in whole milliseconds, for the search to compare exactly
+    fn millis(&self, panel: ControlPanel, operation: Operations) -> u64 {
+        (self.cost(panel, operation).max(0.0) * 1000.0).round() as u64
+    }
+}
+
+/// How long a route takes by a [`CostModel`], in seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RouteTime {
+    pub walking: f64,
+    pub sliding: f64,
+    pub turning: f64,
+}
+
+impl RouteTime {
+    pub fn total(&self) -> f64 {
+        self.walking + self.sliding + self.turning
+    }
+}
+
+impl std::fmt::Display for RouteTime {
+    /// like `1:02.5 (walking 22.5s, sliding 40.0s, turning 0.0s)`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // rounded first so 59.96 doesn't come out as 0:60.0
+        let total = (self.total() * 10.0).round() / 10.0;
+        write!(
+            f,
+            "{}:{:04.1} (walking {:.1}s, sliding {:.1}s, turning {:.1}s)",
+            (total / 60.0).floor(),
+            total % 60.0,
+            self.walking,
+            self.sliding,
+            self.turning
+        )
+    }
+}
+
+/// Searches for the route that takes the least time by `costs`, with
+/// Dijkstra's algorithm over the same states as
+/// [`solve_rotated_rooms`](crate::solve::solve_rotated_rooms). The strategy
+/// in `config` is ignored.
+pub fn solve_weighted(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    config: &SolverConfig,
+    costs: &CostModel,
+) -> SolveResult {
+    let start_time = Instant::now();
+    let mut stats = SearchStats::default();
+    let (verdict, solution) =
+        weighted_search(rooms, rotations, config, costs, &mut stats, start_time);
+    stats.elapsed = start_time.elapsed();
+    SolveResult {
+        verdict,
+        solution,
+        stats,
+    }
+}
+
+struct Node {
+    state: SolveState,
+    parent: Option<(usize, Operations)>,
+    cost: u64,
+    depth: usize,
+}
+
+fn weighted_search(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    config: &SolverConfig,
+    costs: &CostModel,
+    stats: &mut SearchStats,
+    start_time: Instant,
+) -> (Verdict, Option<Solution>) {
+    let (start, start_panel) = match first_state(rooms, rotations, config, stats) {
+        Ok(start) => start,
+        Err(verdict) => return (verdict, None),
+    };
+    let targets = target_mask(rooms, config);
+    // a cheaper way to a state that was already found adds another node for
+    // it, `best` has the cheapest and the others are skipped when popped
+    let mut best: HashMap<SolveState, usize, StateHasher> = HashMap::with_hasher(config.hasher);
+    best.insert(start, 0);
+    let mut nodes = vec![Node {
+        state: start,
+        parent: None,
+        cost: 0,
+        depth: 0,
+    }];
+    let mut heap = BinaryHeap::from([Reverse((0, 0))]);
+
+    while let Some(Reverse((cost, id))) = heap.pop() {
+        let state = nodes[id].state;
+        if best[&state] != id {
+            continue;
+        }
+        if state.reached == targets {
+            stats.states_visited = best.len();
+            let operations = path(&nodes, id);
+            return (
+                Verdict::Beatable,
+                Some(Solution {
+                    start_panel,
+                    operations,
+                }),
+            );
+        }
+        if let Some(verdict) = out_of_budget(config, stats, start_time) {
+            stats.states_visited = best.len();
+            return (verdict, None);
+        }
+        let depth = nodes[id].depth;
+        stats.expanded(depth);
+        let Some(panel) = panel_at(&state) else {
+            return (Verdict::Indeterminate("player not at a panel"), None);
+        };
+        for operation in enum_iterator::all::<Operations>() {
+            let new_state = match step(&state, operation, targets, config, stats) {
+                Ok(Some(new_state)) => new_state,
+                Ok(None) => continue,
+                Err(cycle) => return (Verdict::ChainCycle(cycle), None),
+            };
+            let new_cost = cost + costs.millis(panel, operation);
+            if best
+                .get(&new_state)
+                .is_some_and(|known| nodes[*known].cost <= new_cost)
+            {
+                stats.transposition_hits += 1;
+                continue;
+            }
+            let new_id = nodes.len();
+            best.insert(new_state, new_id);
+            nodes.push(Node {
+                state: new_state,
+                parent: Some((id, operation)),
+                cost: new_cost,
+                depth: depth + 1,
+            });
+            heap.push(Reverse((new_cost, new_id)));
+        }
+    }
+    stats.states_visited = best.len();
+    (Verdict::NotBeatable("unreachable entrances"), None)
+}
+
+fn path(nodes: &[Node], mut id: usize) -> Vec<Operations> {
+    let mut operations = Vec::new();
+    while let Some((parent, operation)) = nodes[id].parent {
+        operations.push(operation);
+        id = parent;
+    }
+    operations.reverse();
+    operations
+}
+
+/// the panel in the room the player stands in, which is always one for the
+/// states the search gets to
+fn panel_at(state: &SolveState) -> Option<ControlPanel> {
+    let room = state.rooms()[state.tile() as usize];
+    enum_iterator::all::<ControlPanel>().find(|panel| panel.room() == room)
+}
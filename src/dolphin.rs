@@ -0,0 +1,190 @@
+//! Reads the Sky Keep state out of a running Dolphin, the way Dolphin Memory
+//! Engine does: Dolphin keeps the emulated console's main memory in a shared
+//! memory file, so it can be read through `/proc/<pid>/mem` on Linux.
+//!
+//! Where the game keeps the puzzle differs between game versions and isn't
+//! built in, it's passed in as [`DolphinAddresses`].
+
+use std::{
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom},
+};
+
+use crate::{
+    patch::{parse_room_ids, rooms_from_patch, RoomIds, DEFAULT_ROOM_IDS},
+    rooms::{validate_rooms, Direction, OpenedGates, Room},
+};
+
+/// the size of the Wii's MEM1, which is where the game's data lives
+const MEM1_SIZE: u64 = 0x200_0000;
+/// where MEM1 starts in the console's address space
+const MEM1_START: u32 = 0x8000_0000;
+
+/// Where the game keeps what the tracker needs, as console addresses like
+/// `0x80xxxxxx`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DolphinAddresses {
+    /// 9 bytes, the room on each tile in board order
+    pub layout: u32,
+    /// a byte with the open gates in the bits of [`OpenedGates`]
+    pub gates: u32,
+    /// a byte with the tile the player is on
+    pub player_tile: u32,
+    /// a byte with the side of that tile the player stands at, 0 to 3 for
+    /// up, left, down and right
+    pub player_direction: u32,
+    /// the room each byte value in the layout stands for, see
+    /// [`patch`](crate::patch)
+    pub room_ids: RoomIds,
+}
+
+impl DolphinAddresses {
+    /// Reads `layout=0x..,gates=0x..,player_tile=0x..,player_direction=0x..`
+    /// with an optional
+    /// `room_ids=STR SV ET ...` for games that number rooms differently.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut layout = None;
+        let mut gates = None;
+        let mut player_tile = None;
+        let mut player_direction = None;
+        let mut room_ids = DEFAULT_ROOM_IDS;
+        for field in text.split(',') {
+            let (key, value) = field
+                .split_once('=')
+                .ok_or_else(|| format!("expected <name>=<value>, got {field}"))?;
+            let address = || {
+                u32::from_str_radix(value.trim().trim_start_matches("0x"), 16)
+                    .ok()
+                    .filter(|address| (MEM1_START..MEM1_START + MEM1_SIZE as u32).contains(address))
+                    .ok_or_else(|| format!("invalid address: {value}"))
+            };
+            match key.trim() {
+                "layout" => layout = Some(address()?),
+                "gates" => gates = Some(address()?),
+                "player_tile" => player_tile = Some(address()?),
+                "player_direction" => player_direction = Some(address()?),
+                "room_ids" => room_ids = parse_room_ids(value)?,
+                _ => return Err(format!("unknown address: {key}")),
+            }
+        }
+        Ok(DolphinAddresses {
+            layout: layout.ok_or("missing layout address")?,
+            gates: gates.ok_or("missing gates address")?,
+            player_tile: player_tile.ok_or("missing player_tile address")?,
+            player_direction: player_direction.ok_or("missing player_direction address")?,
+            room_ids,
+        })
+    }
+}
+
+/// What was read from the game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GameState {
+    pub rooms: [Room; 9],
+    pub gates: OpenedGates,
+    pub player_tile: u8,
+    pub player_direction: Direction,
+}
+
+/// A Dolphin process to read the emulated memory of.
+pub struct DolphinMemory {
+    mem: File,
+    /// where MEM1 is mapped in Dolphin's address space
+    mem1: u64,
+}
+
+impl DolphinMemory {
+    /// Attaches to the first running Dolphin that has a game started.
+    pub fn attach() -> io::Result<Self> {
+        for entry in fs::read_dir("/proc")? {
+            let Ok(pid) = entry?.file_name().to_string_lossy().parse::<u32>() else {
+                continue;
+            };
+            let Ok(comm) = fs::read_to_string(format!("/proc/{pid}/comm")) else {
+                continue;
+            };
+            if !comm.to_lowercase().contains("dolphin") {
+                continue;
+            }
+            let Some(mem1) = find_mem1(pid)? else {
+                continue;
+            };
+            let mem = File::open(format!("/proc/{pid}/mem"))?;
+            return Ok(DolphinMemory { mem, mem1 });
+        }
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "no Dolphin with a running game found",
+        ))
+    }
+
+    fn read(&mut self, address: u32, buf: &mut [u8]) -> io::Result<()> {
+        let offset = address.wrapping_sub(MEM1_START) as u64;
+        if offset + buf.len() as u64 > MEM1_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("address {address:#x} is outside of MEM1"),
+            ));
+        }
+        self.mem.seek(SeekFrom::Start(self.mem1 + offset))?;
+        self.mem.read_exact(buf)
+    }
+
+    pub fn read_state(&mut self, addresses: &DolphinAddresses) -> io::Result<GameState> {
+        let invalid = |what: String| io::Error::new(io::ErrorKind::InvalidData, what);
+        let mut layout = [0; 9];
+        self.read(addresses.layout, &mut layout)?;
+        let rooms = rooms_from_patch(&layout, &addresses.room_ids).map_err(invalid)?;
+        // also what reading while the game rewrites the layout looks like
+        validate_rooms(&rooms).map_err(invalid)?;
+        let mut byte = [0];
+        self.read(addresses.gates, &mut byte)?;
+        let gates = OpenedGates::from_bits_truncate(byte[0]);
+        self.read(addresses.player_tile, &mut byte)?;
+        if byte[0] >= 9 {
+            return Err(invalid(format!("invalid player tile {}", byte[0])));
+        }
+        let player_tile = byte[0];
+        self.read(addresses.player_direction, &mut byte)?;
+        let player_direction = enum_iterator::all::<Direction>()
+            .nth(byte[0] as usize)
+            .ok_or_else(|| invalid(format!("invalid player direction {}", byte[0])))?;
+        Ok(GameState {
+            rooms,
+            gates,
+            player_tile,
+            player_direction,
+        })
+    }
+}
+
+/// the start of the shared memory mapping holding MEM1, if the game is
+/// running
+fn find_mem1(pid: u32) -> io::Result<Option<u64>> {
+    let maps = match fs::read_to_string(format!("/proc/{pid}/maps")) {
+        Ok(maps) => maps,
+        // the process is gone or isn't ours
+        Err(_) => return Ok(None),
+    };
+    for line in maps.lines() {
+        // start-end perms offset dev inode path
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [range, _, offset, _, _, path, ..] = fields[..] else {
+            continue;
+        };
+        if !path.contains("dolphin-emu") || u64::from_str_radix(offset, 16) != Ok(0) {
+            continue;
+        }
+        let Some((start, end)) = range.split_once('-') else {
+            continue;
+        };
+        let (Ok(start), Ok(end)) = (u64::from_str_radix(start, 16), u64::from_str_radix(end, 16))
+        else {
+            continue;
+        };
+        if end - start == MEM1_SIZE {
+            return Ok(Some(start));
+        }
+    }
+    Ok(None)
+}
@@ -0,0 +1,501 @@
+use crate::{
+    partial::parse_partial_layout,
+    rooms::{
+        do_move, validate_rooms, Direction, DoorRules, Entrance, OpenedGates, Room, Rotation,
+        ALL_ROOMS, NO_ROTATIONS,
+    },
+    solve::{solve_rotated_rooms, Solution},
+    verify::{
+        verify_rotated_rooms, EntryPoint, SearchStrategy, SolverConfig, Verdict, Verification,
+    },
+};
+
+/// Every distinct arrangement of `rooms` on the board, in lexicographic order
+/// of the room declaration order. Duplicate rooms (like several empty tiles)
+/// don't produce duplicate layouts.
+pub fn all_layouts(mut rooms: [Room; 9]) -> impl Iterator<Item = [Room; 9]> {
+    rooms.sort_by_key(|r| *r as u8);
+    layouts_from(rooms)
+}
+
+/// `rooms` and the layouts that come after it in the order of
+/// [`all_layouts`]
+pub fn layouts_from(rooms: [Room; 9]) -> impl Iterator<Item = [Room; 9]> {
+    let mut next = Some(rooms);
+    std::iter::from_fn(move || {
+        let current = next?;
+        let mut following = current;
+        next = next_permutation(&mut following).then_some(following);
+        Some(current)
+    })
+}
+
+/// the number of layouts of [`ALL_ROOMS`], 9!
+pub const LAYOUT_COUNT: u32 = 362_880;
+
+/// Where a layout of [`ALL_ROOMS`] comes in [`all_layouts`], from 0 to
+/// [`LAYOUT_COUNT`] - 1, computed from its Lehmer code. Panics if a room is
+/// missing or there twice.
+pub fn layout_to_index(rooms: &[Room; 9]) -> u32 {
+    let mut left = [true; ALL_ROOMS.len()];
+    let mut index = 0;
+    for (tile, room) in rooms.iter().enumerate() {
+        let room = *room as usize;
+        assert!(left[room], "{:?} is on the board twice", rooms[tile]);
+        left[room] = false;
+        // how many of the rooms still to place would come first
+        let smaller = left[..room].iter().filter(|left| **left).count() as u32;
+        index = index * (9 - tile as u32) + smaller;
+    }
+    index
+}
+
+/// The layout of [`ALL_ROOMS`] at `index` in [`all_layouts`], the inverse of
+/// [`layout_to_index`]. Panics if the index isn't below [`LAYOUT_COUNT`].
+pub fn index_to_layout(index: u32) -> [Room; 9] {
+    assert!(index < LAYOUT_COUNT, "no layout {index}");
+    let mut left: Vec<Room> = ALL_ROOMS.to_vec();
+    left.sort_by_key(|room| *room as u8);
+    // the Lehmer code digits, the last tile's first
+    let mut digits = [0; 9];
+    let mut rest = index;
+    for (radix, digit) in (1..=9).zip(digits.iter_mut().rev()) {
+        *digit = rest % radix;
+        rest /= radix;
+    }
+    digits.map(|digit| left.remove(digit as usize))
+}
+
+/// rearranges the rooms into the next permutation in lexicographic order,
+/// `false` if they already were in the last one
+pub(crate) fn next_permutation(rooms: &mut [Room]) -> bool {
+    let key = |rooms: &[Room], i: usize| rooms[i] as u8;
+    let Some(pivot) = (0..rooms.len().saturating_sub(1))
+        .rev()
+        .find(|&i| key(rooms, i) < key(rooms, i + 1))
+    else {
+        return false;
+    };
+    let swap = (pivot + 1..rooms.len())
+        .rev()
+        .find(|&i| key(rooms, i) > key(rooms, pivot))
+        .unwrap();
+    rooms.swap(pivot, swap);
+    rooms[pivot + 1..].reverse();
+    true
+}
+
+/// The verifier and the solver came to different conclusions about a layout,
+/// one of them has a bug.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Disagreement {
+    pub rooms: [Room; 9],
+    pub verifier: Verdict,
+    pub solver: Verdict,
+}
+
+impl std::fmt::Display for Disagreement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "verifier says {:?}, solver says {:?}: {:?}",
+            self.verifier, self.solver, self.rooms
+        )
+    }
+}
+
+/// Runs both the depth first verifier and the breadth first solver on the
+/// layout, returning the verifier's result and a [`Disagreement`] if the
+/// solver doesn't agree. Searches cut short by the budget never disagree.
+pub fn cross_check(
+    rooms: &[Room; 9],
+    config: &SolverConfig,
+) -> (Verification, Option<Disagreement>) {
+    let verification = verify_rotated_rooms(rooms, &NO_ROTATIONS, config);
+    let verifier = &verification.verdict;
+    let solver = solve_rotated_rooms(rooms, &NO_ROTATIONS, config).verdict;
+    let indeterminate = |verdict: &Verdict| matches!(verdict, Verdict::Indeterminate(_));
+    if *verifier == solver || indeterminate(verifier) || indeterminate(&solver) {
+        return (verification, None);
+    }
+    let disagreement = Disagreement {
+        rooms: *rooms,
+        verifier: verifier.clone(),
+        solver,
+    };
+    (verification, Some(disagreement))
+}
+
+/// The beatable layout whose shortest solution is the longest, the first one
+/// found if several tie. Layouts are verified first and only the beatable
+/// ones solved, `on_record` is called every time a longer one turns up.
+/// Layouts whose search is cut short by the budget are skipped.
+pub fn hardest_layout(
+    layouts: impl IntoIterator<Item = [Room; 9]>,
+    config: &SolverConfig,
+    mut on_record: impl FnMut(&[Room; 9], &Solution),
+) -> Option<([Room; 9], Solution)> {
+    // best first solutions aren't necessarily the shortest
+    let config = SolverConfig {
+        strategy: SearchStrategy::BreadthFirst,
+        ..config.clone()
+    };
+    let mut hardest: Option<([Room; 9], Solution)> = None;
+    for rooms in layouts {
+        if verify_rotated_rooms(&rooms, &NO_ROTATIONS, &config).verdict != Verdict::Beatable {
+            continue;
+        }
+        let Some(solution) = solve_rotated_rooms(&rooms, &NO_ROTATIONS, &config).solution else {
+            continue;
+        };
+        let longer = hardest
+            .as_ref()
+            .is_none_or(|(_, best)| solution.operations.len() > best.operations.len());
+        if longer {
+            on_record(&rooms, &solution);
+            hardest = Some((rooms, solution));
+        }
+    }
+    hardest
+}
+
+/// Every door on the edge of the board facing outwards, with the doors on
+/// the sides `doors` puts them, which is where the player could be dropped
+/// in by entrance randomization.
+pub fn boundary_entry_points(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    doors: &DoorRules,
+) -> Vec<EntryPoint> {
+    (0..9u8)
+        .flat_map(|tile| enum_iterator::all::<Direction>().map(move |direction| (tile, direction)))
+        .filter(|(tile, direction)| do_move(*tile, *direction).is_none())
+        .filter(|(tile, direction)| {
+            let tile = *tile as usize;
+            doors
+                .on_board(rooms[tile], rotations[tile], *direction)
+                .is_some()
+        })
+        .map(|(tile, direction)| EntryPoint { tile, direction })
+        .collect()
+}
+
+/// Verifies the layout once for every entry point on its edge, the entry
+/// point in `config` is ignored.
+pub fn verify_entry_points(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    config: &SolverConfig,
+) -> Vec<(EntryPoint, Verification)> {
+    boundary_entry_points(rooms, rotations, &config.door_rules)
+        .into_iter()
+        .map(|entry| {
+            let config = SolverConfig {
+                entry,
+                ..config.clone()
+            };
+            (entry, verify_rotated_rooms(rooms, rotations, &config))
+        })
+        .collect()
+}
+
+/// How often each room was on each tile, counted over a set of layouts.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Heatmap {
+    /// indexed by room, then tile
+    counts: [[usize; 9]; 9],
+    layouts: usize,
+}
+
+impl Heatmap {
+    pub fn add(&mut self, rooms: &[Room; 9]) {
+        for (tile, room) in rooms.iter().enumerate() {
+            self.counts[*room as usize][tile] += 1;
+        }
+        self.layouts += 1;
+    }
+
+    pub fn count(&self, room: Room, tile: usize) -> usize {
+        self.counts[room as usize][tile]
+    }
+
+    /// the number of layouts added
+    pub fn layouts(&self) -> usize {
+        self.layouts
+    }
+
+    /// one row per room with the count for every tile
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("room,tile0,tile1,tile2,tile3,tile4,tile5,tile6,tile7,tile8\n");
+        for room in ALL_ROOMS {
+            let counts: Vec<String> = (0..9)
+                .map(|tile| self.count(room, tile).to_string())
+                .collect();
+            csv.push_str(&format!("{},{}\n", room.code(), counts.join(",")));
+        }
+        csv
+    }
+}
+
+impl std::fmt::Display for Heatmap {
+    /// the share of layouts with the room on the tile, in percent
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:<4}", "")?;
+        for tile in 0..9 {
+            write!(f, "{tile:>6}")?;
+        }
+        writeln!(f)?;
+        for room in ALL_ROOMS {
+            write!(f, "{:<4}", room.code())?;
+            for tile in 0..9 {
+                let share = self.count(room, tile) as f64 * 100.0 / self.layouts.max(1) as f64;
+                write!(f, "{share:>6.1}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// The layouts an enumeration found beatable, to pick from without
+/// verifying anything.
+///
+/// Besides the enumeration's CSV they can be kept in a binary file, see
+/// [`to_bytes`](Self::to_bytes), which says what it was made for so it can
+/// be refused when the room logic changed since.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SolvableLayouts {
+    layouts: Vec<[Room; 9]>,
+}
+
+/// the first bytes of a binary layout database
+pub const DB_MAGIC: [u8; 8] = *b"SKPZLDB\0";
+
+/// bumped whenever the layout of the binary database changes
+pub const DB_VERSION: u16 = 1;
+
+/// magic, version, width, height, room logic hash and layout count
+const DB_HEADER_SIZE: usize = 8 + 2 + 1 + 1 + 8 + 4;
+
+/// A fingerprint of the room logic the layouts were verified with: the
+/// rooms, their doors, where the doors lead through the room for every set
+/// of opened gates, the control panels and the gates they open. Changing
+/// any of it changes the hash, which makes older databases unusable.
+pub fn room_logic_hash() -> u64 {
+    // FNV-1a, which stays the same between builds and platforms
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut add = |bytes: &[u8]| {
+        for byte in bytes {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    };
+    for room in enum_iterator::all::<Room>() {
+        add(&[room as u8]);
+        add(room.code().as_bytes());
+    }
+    for entrance in enum_iterator::all::<Entrance>() {
+        let (room, direction) = entrance.to_room_direction();
+        add(&[entrance as u8, room as u8, direction as u8]);
+        add(&[
+            entrance.has_control_panel() as u8,
+            entrance.open_gate().map_or(0, |gates| gates.bits()),
+        ]);
+        for bits in 0..=OpenedGates::all().bits() {
+            let through = entrance.traverse_room(OpenedGates::from_bits_retain(bits));
+            add(&[through.map_or(u8::MAX, |exit| exit as u8)]);
+        }
+    }
+    hash
+}
+
+impl SolvableLayouts {
+    pub fn new(layouts: Vec<[Room; 9]>) -> Self {
+        SolvableLayouts { layouts }
+    }
+
+    /// Reads either the binary database or the enumeration's CSV.
+    pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.starts_with(&DB_MAGIC) {
+            return Self::from_bytes(bytes);
+        }
+        let text = std::str::from_utf8(bytes)
+            .map_err(|_| "neither a layout database nor a CSV".to_string())?;
+        Self::from_csv(text)
+    }
+
+    /// The binary database, all numbers little endian: [`DB_MAGIC`], the
+    /// [`DB_VERSION`] as u16, the width and height of the board as u8, the
+    /// [`room_logic_hash`] as u64, the number of layouts as u32 and then
+    /// every layout as 9 bytes of `Room as u8`, row by row.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(DB_HEADER_SIZE + self.layouts.len() * 9);
+        bytes.extend_from_slice(&DB_MAGIC);
+        bytes.extend_from_slice(&DB_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&[3, 3]);
+        bytes.extend_from_slice(&room_logic_hash().to_le_bytes());
+        let count = u32::try_from(self.layouts.len()).expect("fewer layouts than 9!");
+        bytes.extend_from_slice(&count.to_le_bytes());
+        for rooms in &self.layouts {
+            bytes.extend(rooms.iter().map(|room| *room as u8));
+        }
+        bytes
+    }
+
+    /// Reads what [`to_bytes`](Self::to_bytes) wrote, refusing databases of
+    /// another format version, board size or room logic.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if !bytes.starts_with(&DB_MAGIC) {
+            return Err("not a layout database".to_string());
+        }
+        if bytes.len() < DB_HEADER_SIZE {
+            return Err("layout database header is cut off".to_string());
+        }
+        let version = u16::from_le_bytes([bytes[8], bytes[9]]);
+        if version != DB_VERSION {
+            return Err(format!(
+                "layout database has format version {version}, this version reads {DB_VERSION}"
+            ));
+        }
+        let (width, height) = (bytes[10], bytes[11]);
+        if (width, height) != (3, 3) {
+            return Err(format!(
+                "layout database is for a {width}x{height} board, the puzzle is 3x3"
+            ));
+        }
+        let hash = u64::from_le_bytes(bytes[12..20].try_into().expect("8 bytes"));
+        if hash != room_logic_hash() {
+            return Err(format!(
+                "layout database was made with other room logic (hash {hash:016x}, now {:016x}), \
+                 it has to be enumerated again",
+                room_logic_hash()
+            ));
+        }
+        let count = u32::from_le_bytes(bytes[20..24].try_into().expect("4 bytes")) as usize;
+        let data = &bytes[DB_HEADER_SIZE..];
+        if data.len() != count * 9 {
+            return Err(format!(
+                "layout database should have {count} layouts but has {} bytes of them",
+                data.len()
+            ));
+        }
+        let rooms: Vec<Room> = enum_iterator::all::<Room>().collect();
+        let layouts = data
+            .chunks_exact(9)
+            .enumerate()
+            .map(|(index, layout)| {
+                let layout: Vec<Room> = layout
+                    .iter()
+                    .map(|byte| rooms.get(*byte as usize).copied())
+                    .collect::<Option<_>>()
+                    .ok_or_else(|| format!("layout {index}: unknown room"))?;
+                let layout: [Room; 9] = layout.try_into().expect("9 rooms");
+                validate_rooms(&layout).map_err(|e| format!("layout {index}: {e}"))?;
+                Ok(layout)
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(SolvableLayouts { layouts })
+    }
+
+    /// Reads the CSV `enumerate --format csv` writes and keeps the rows with
+    /// `solvable` true. The layouts are only beatable for the options the
+    /// enumeration was run with.
+    pub fn from_csv(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().enumerate();
+        match lines.next() {
+            Some((_, header)) if header.starts_with("notation,solvable,") => {}
+            _ => return Err("expected the CSV of enumerate --format csv".to_string()),
+        }
+        let mut layouts = Vec::new();
+        for (index, line) in lines {
+            let mut fields = line.split(',');
+            let (Some(notation), Some(solvable)) = (fields.next(), fields.next()) else {
+                return Err(format!("line {}: expected notation,solvable", index + 1));
+            };
+            if solvable != "true" {
+                continue;
+            }
+            let rooms = parse_partial_layout(notation)
+                .ok()
+                .and_then(|partial| partial.iter().copied().collect::<Option<Vec<Room>>>())
+                .and_then(|rooms| rooms.try_into().ok())
+                .filter(|rooms| validate_rooms(rooms).is_ok())
+                .ok_or_else(|| format!("line {}: invalid layout {notation}", index + 1))?;
+            layouts.push(rooms);
+        }
+        Ok(SolvableLayouts { layouts })
+    }
+
+    pub fn len(&self) -> usize {
+        self.layouts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layouts.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&[Room; 9]> {
+        self.layouts.get(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rooms::BOTH_WAY_DOORS;
+
+    #[test]
+    fn layout_indices_round_trip() {
+        let mut sorted = ALL_ROOMS;
+        sorted.sort_by_key(|room| *room as u8);
+        assert_eq!(index_to_layout(0), sorted);
+        let mut reversed = sorted;
+        reversed.reverse();
+        assert_eq!(index_to_layout(LAYOUT_COUNT - 1), reversed);
+        for (index, rooms) in all_layouts(ALL_ROOMS).take(1000).enumerate() {
+            assert_eq!(index_to_layout(index as u32), rooms);
+        }
+        for index in (0..LAYOUT_COUNT).step_by(997) {
+            assert_eq!(layout_to_index(&index_to_layout(index)), index);
+        }
+    }
+
+    #[test]
+    fn layout_database_round_trips() {
+        let db = SolvableLayouts::new(vec![index_to_layout(0), index_to_layout(12345)]);
+        let bytes = db.to_bytes();
+        assert_eq!(bytes.len(), DB_HEADER_SIZE + 2 * 9);
+        assert_eq!(SolvableLayouts::from_bytes(&bytes), Ok(db.clone()));
+        assert_eq!(SolvableLayouts::parse(&bytes), Ok(db));
+
+        let mut other_version = bytes.clone();
+        other_version[8] ^= 1;
+        assert!(SolvableLayouts::from_bytes(&other_version).is_err());
+        let mut other_logic = bytes.clone();
+        other_logic[12] ^= 1;
+        assert!(SolvableLayouts::from_bytes(&other_logic).is_err());
+        assert!(SolvableLayouts::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let mut twice = bytes;
+        twice[DB_HEADER_SIZE + 1] = twice[DB_HEADER_SIZE];
+        assert!(SolvableLayouts::from_bytes(&twice).is_err());
+    }
+
+    #[test]
+    fn entry_points_follow_moved_doors() {
+        let mut rooms = [Room::Empty; 9];
+        rooms[0] = Room::Sandship;
+        let entry = |tile, direction| EntryPoint { tile, direction };
+        assert_eq!(
+            boundary_entry_points(&rooms, &NO_ROTATIONS, &BOTH_WAY_DOORS),
+            [entry(0, Direction::Left)]
+        );
+        let mut doors = BOTH_WAY_DOORS;
+        doors.set_direction(Entrance::SandshipLeft, Direction::Up);
+        assert_eq!(
+            boundary_entry_points(&rooms, &NO_ROTATIONS, &doors),
+            [entry(0, Direction::Up)]
+        );
+        doors.set_direction(Entrance::SandshipLeft, Direction::Right);
+        assert!(boundary_entry_points(&rooms, &NO_ROTATIONS, &doors).is_empty());
+    }
+}
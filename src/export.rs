@@ -0,0 +1,189 @@
+//! Solutions written out for runners and their tools, with what every
+//! operation does on the board instead of the solver's slot numbers.
+
+use crate::{
+    json::Json,
+    locale::Locale,
+    rooms::{ControlPanel, Direction, Room, Rotation},
+    share::layout_code,
+    solve::{apply, Solution, SolveState},
+    verify::{Operations, SolverConfig},
+};
+
+/// An operation of a solution, done from `panel`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RouteStep {
+    pub panel: ControlPanel,
+    pub operation: Operations,
+    pub action: Action,
+}
+
+/// What an operation does on the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// walking over to the panel
+    Walk(ControlPanel),
+    /// the room moves from tile `from` into the empty tile `to`, which is
+    /// next to it in `direction`
+    Slide {
+        room: Room,
+        from: u8,
+        to: u8,
+        direction: Direction,
+    },
+    /// the room on `tile` is turned clockwise
+    Turn { room: Room, tile: u8 },
+}
+
+/// Plays the solution from the start and records what each operation did,
+/// `Err` if one of them can't be done.
+pub fn route_steps(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    solution: &Solution,
+    config: &SolverConfig,
+) -> Result<Vec<RouteStep>, String> {
+    let mut state = SolveState::start(rooms, rotations, config)
+        .map_err(|verdict| format!("can't start the puzzle: {verdict:?}"))?;
+    let mut panel = solution.start_panel;
+    let mut steps = Vec::new();
+    for operation in &solution.operations {
+        let next = apply(&state, *operation, config)
+            .map_err(|e| format!("{operation:?} can't be done: {e}"))?;
+        let changed = |tile: &usize| {
+            state.rooms()[*tile] != next.rooms()[*tile]
+                || state.rotations()[*tile] != next.rotations()[*tile]
+        };
+        let changed: Vec<usize> = (0..9).filter(changed).collect();
+        let action = match (operation, &changed[..]) {
+            (Operations::Reach(target), _) => Action::Walk(*target),
+            (Operations::Move(..), [a, b]) => {
+                let (from, to) = if next.rooms()[*a] == Room::Empty {
+                    (*a, *b)
+                } else {
+                    (*b, *a)
+                };
+                let direction = match to as isize - from as isize {
+                    -3 => Direction::Up,
+                    3 => Direction::Down,
+                    -1 => Direction::Left,
+                    _ => Direction::Right,
+                };
+                Action::Slide {
+                    room: next.rooms()[to],
+                    from: from as u8,
+                    to: to as u8,
+                    direction,
+                }
+            }
+            (Operations::Rotate(..), [tile]) => Action::Turn {
+                room: next.rooms()[*tile],
+                tile: *tile as u8,
+            },
+            _ => return Err(format!("{operation:?} didn't change the board as expected")),
+        };
+        steps.push(RouteStep {
+            panel,
+            operation: *operation,
+            action,
+        });
+        if let Operations::Reach(target) = operation {
+            panel = *target;
+        }
+        state = next;
+    }
+    Ok(steps)
+}
+
+/// Plain text for LiveSplit's run notes, a paragraph per panel with a line
+/// per operation done there, in the words of `locale`.
+pub fn livesplit_notes(start_panel: ControlPanel, steps: &[RouteStep], locale: &Locale) -> String {
+    let mut notes = locale.phrase("notes.start", &[("room", locale.room(start_panel.room()))]);
+    notes.push('\n');
+    for step in steps {
+        let line = match step.action {
+            Action::Walk(panel) => format!(
+                "\n{}",
+                locale.phrase("notes.walk", &[("room", locale.room(panel.room()))])
+            ),
+            Action::Slide {
+                room, direction, ..
+            } => {
+                let direction = locale.direction(direction);
+                locale.phrase(
+                    "notes.slide",
+                    &[("room", locale.room(room)), ("direction", direction)],
+                )
+            }
+            Action::Turn { room, .. } => {
+                locale.phrase("notes.turn", &[("room", locale.room(room))])
+            }
+        };
+        notes.push_str(&line);
+        notes.push('\n');
+    }
+    notes
+}
+
+/// A script for TAS tools to turn into inputs, as JSON: the layout's share
+/// `code`, the `start_panel` and a step per operation with the `panel` it's
+/// done from and the `action`. A `walk` has the panel walked `to`, a
+/// `slide` the `room`, the tiles it moves `from` and `to` and the
+/// `direction` it moves in, a `turn` the `room` and its `tile`. Timing is
+/// left to the tool.
+pub fn tas_script(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    start_panel: ControlPanel,
+    steps: &[RouteStep],
+) -> Json {
+    let steps = steps
+        .iter()
+        .enumerate()
+        .map(|(i, step)| {
+            let mut fields = vec![
+                ("step", (i + 1).into()),
+                ("panel", format!("{:?}", step.panel).into()),
+                ("operation", format!("{:?}", step.operation).into()),
+            ];
+            match step.action {
+                Action::Walk(panel) => {
+                    fields.push(("action", "walk".into()));
+                    fields.push(("to", format!("{panel:?}").into()));
+                }
+                Action::Slide {
+                    room,
+                    from,
+                    to,
+                    direction,
+                } => {
+                    fields.push(("action", "slide".into()));
+                    fields.push(("room", format!("{room:?}").into()));
+                    fields.push(("from", (from as usize).into()));
+                    fields.push(("to", (to as usize).into()));
+                    fields.push(("direction", direction_name(direction).into()));
+                }
+                Action::Turn { room, tile } => {
+                    fields.push(("action", "turn".into()));
+                    fields.push(("room", format!("{room:?}").into()));
+                    fields.push(("tile", (tile as usize).into()));
+                }
+            }
+            Json::object(fields)
+        })
+        .collect();
+    Json::object([
+        ("code", layout_code(rooms, rotations).into()),
+        ("start_panel", format!("{start_panel:?}").into()),
+        ("steps", Json::Array(steps)),
+    ])
+}
+
+fn direction_name(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Up => "up",
+        Direction::Left => "left",
+        Direction::Down => "down",
+        Direction::Right => "right",
+    }
+}
@@ -0,0 +1,353 @@
+use std::ops::RangeInclusive;
+
+use crate::{
+    cost::{solve_weighted, CostModel},
+    enumerate::{boundary_entry_points, SolvableLayouts},
+    rooms::{
+        do_move, ControlPanel, Direction, DoorRules, Entrance, OpenedGates, Room, Rotation,
+        ALL_ROOMS, NO_ROTATIONS,
+    },
+    session::Session,
+    solve::{all_optimal_solutions, solve_rotated_rooms, GateOrder, Solution},
+    verify::{verify_rotated_rooms, EntryPoint, Operations, SearchStrategy, SolverConfig, Verdict},
+};
+
+/// The random choices made while generating a layout, so the same layout
+/// can be made with any RNG from `rand` or reproduced with Python's.
+pub trait LayoutRng {
+    /// uniform in `0..n`
+    fn below(&mut self, n: usize) -> usize;
+    fn shuffle<T>(&mut self, items: &mut [T]);
+}
+
+impl<R: rand::Rng> LayoutRng for R {
+    fn below(&mut self, n: usize) -> usize {
+        self.gen_range(0..n)
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        rand::seq::SliceRandom::shuffle(items, self);
+    }
+}
+
+/// Shuffles every room onto the board, leaving out random rooms to get more
+/// than one empty tile and turning every room randomly if `rotatable`.
+pub fn random_layout(
+    rng: &mut impl LayoutRng,
+    empty_tiles: usize,
+    rotatable: bool,
+) -> ([Room; 9], [Rotation; 9]) {
+    let mut rooms = ALL_ROOMS;
+    for _ in 1..empty_tiles {
+        let filled: Vec<usize> = (0..9).filter(|tile| rooms[*tile] != Room::Empty).collect();
+        rooms[filled[rng.below(filled.len())]] = Room::Empty;
+    }
+    rng.shuffle(&mut rooms);
+    let mut rotations = NO_ROTATIONS;
+    if rotatable {
+        let all_rotations: Vec<Rotation> = enum_iterator::all::<Rotation>().collect();
+        for rotation in rotations.iter_mut() {
+            *rotation = all_rotations[rng.below(all_rotations.len())];
+        }
+    }
+    (rooms, rotations)
+}
+
+/// One of the doors on the edge of the layout to enter the board through,
+/// `None` if there's no door on the edge at all.
+pub fn random_entry_point(
+    rng: &mut impl LayoutRng,
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    door_rules: &DoorRules,
+) -> Option<EntryPoint> {
+    let doors = boundary_entry_points(rooms, rotations, door_rules);
+    (!doors.is_empty()).then(|| doors[rng.below(doors.len())])
+}
+
+/// One of the 12 outward facing sides of the edge tiles, door or not, for
+/// picking the entrance before there's a layout.
+pub fn random_edge(rng: &mut impl LayoutRng) -> EntryPoint {
+    let edges: Vec<EntryPoint> = (0..9u8)
+        .flat_map(|tile| enum_iterator::all::<Direction>().map(move |direction| (tile, direction)))
+        .filter(|(tile, direction)| do_move(*tile, *direction).is_none())
+        .map(|(tile, direction)| EntryPoint { tile, direction })
+        .collect();
+    edges[rng.below(edges.len())]
+}
+
+/// Every gate open or closed with the same chance, like with randomizer
+/// settings that open some of them from the start.
+pub fn random_gates(rng: &mut impl LayoutRng) -> OpenedGates {
+    OpenedGates::all()
+        .iter()
+        .filter(|_| rng.below(2) == 1)
+        .collect()
+}
+
+/// Moves every room's doors to random sides of it, each room keeping as
+/// many doors as it has. Unlike turning the room the doors don't keep their
+/// angle to each other, doors on sides next to each other can end up on
+/// opposite sides.
+pub fn shuffle_doors(rng: &mut impl LayoutRng, doors: &mut DoorRules) {
+    for room in enum_iterator::all::<Room>() {
+        let mut directions: Vec<Direction> = enum_iterator::all().collect();
+        rng.shuffle(&mut directions);
+        let entrances = enum_iterator::all::<Entrance>()
+            .filter(|entrance| entrance.to_room_direction().0 == room);
+        for (entrance, direction) in entrances.zip(directions) {
+            doors.set_direction(entrance, direction);
+        }
+    }
+}
+
+/// One of `layouts` with the same chance each, `None` if there are none.
+/// Uniform over the beatable layouts without verifying a single one, if the
+/// enumeration they come from covered all of them.
+pub fn sample_solvable(rng: &mut impl LayoutRng, layouts: &SolvableLayouts) -> Option<[Room; 9]> {
+    if layouts.is_empty() {
+        return None;
+    }
+    layouts.get(rng.below(layouts.len())).copied()
+}
+
+/// A layout found by [`random_solvable_layout`].
+#[derive(Debug, Clone)]
+pub struct RejectionSample {
+    pub rooms: [Room; 9],
+    pub rotations: [Rotation; 9],
+    /// how many layouts were drawn, including this one
+    pub tries: usize,
+}
+
+/// Draws layouts like [`random_layout`] until one is beatable, which makes
+/// every beatable layout equally likely since every layout is equally likely
+/// to be drawn. Layouts whose verification runs out of budget are drawn
+/// again too, so it's only uniform without `max_nodes` or `timeout`. 58608 of
+/// the 362880 layouts with one empty tile are beatable from the default entry
+/// point, about 16.2%, so it takes 6.2 tries on average. Gives up after
+/// `max_tries`.
+pub fn random_solvable_layout(
+    rng: &mut impl LayoutRng,
+    empty_tiles: usize,
+    rotatable: bool,
+    config: &SolverConfig,
+    max_tries: usize,
+) -> Option<RejectionSample> {
+    random_constrained_layout(
+        rng,
+        empty_tiles,
+        rotatable,
+        &LayoutConstraints::default(),
+        config,
+        max_tries,
+    )
+}
+
+/// What a beatable layout has to take for
+/// [`random_constrained_layout`] to keep it, every one for all solutions.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LayoutConstraints {
+    /// panels rooms have to be slid or turned from, for seeds that show off
+    /// the ones that are rarely needed
+    pub panels: Vec<ControlPanel>,
+    /// the fewest rooms to slide, to keep race seeds from being over in a
+    /// handful of slides
+    pub min_slides: usize,
+    /// rules every shortest solution opens the gates by, for themed seeds
+    /// like ones that save the Fire Sanctuary gate for last
+    pub gate_order: Vec<GateOrder>,
+}
+
+impl LayoutConstraints {
+    /// if every beatable layout is kept
+    pub fn is_empty(&self) -> bool {
+        *self == LayoutConstraints::default()
+    }
+
+    /// If a beatable layout meets all of them, the cheap ones are checked
+    /// first. Not if a search runs out of budget.
+    pub fn accepts(
+        &self,
+        rooms: &[Room; 9],
+        rotations: &[Rotation; 9],
+        config: &SolverConfig,
+    ) -> bool {
+        self.panels
+            .iter()
+            .all(|panel| needs_panel(rooms, rotations, *panel, config))
+            && (self.min_slides == 0
+                || min_slides(rooms, rotations, config).is_some_and(|s| s >= self.min_slides))
+            && (self.gate_order.is_empty()
+                || forces_gate_order(rooms, rotations, &self.gate_order, config))
+    }
+}
+
+/// Like [`random_solvable_layout`], only keeping the layouts that meet
+/// `constraints`. Every such layout is equally likely.
+pub fn random_constrained_layout(
+    rng: &mut impl LayoutRng,
+    empty_tiles: usize,
+    rotatable: bool,
+    constraints: &LayoutConstraints,
+    config: &SolverConfig,
+    max_tries: usize,
+) -> Option<RejectionSample> {
+    let mut session = Session::new(config.clone());
+    for tries in 1..=max_tries {
+        let (rooms, rotations) = random_layout(rng, empty_tiles, rotatable);
+        if session.verify(&rooms, &rotations).verdict == Verdict::Beatable
+            && constraints.accepts(&rooms, &rotations, config)
+        {
+            return Some(RejectionSample {
+                rooms,
+                rotations,
+                tries,
+            });
+        }
+    }
+    None
+}
+
+/// If a beatable layout can't be beaten without sliding or turning rooms
+/// from `panel`, found out by verifying it again with the panel locked. Not
+/// if that runs out of budget.
+pub fn needs_panel(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    panel: ControlPanel,
+    config: &SolverConfig,
+) -> bool {
+    let mut locked = config.clone();
+    locked.locked_panels.push(panel);
+    matches!(
+        verify_rotated_rooms(rooms, rotations, &locked).verdict,
+        Verdict::NotBeatable(_)
+    )
+}
+
+/// The fewest rooms a solution slides, walking to panels and turning rooms
+/// don't count. `None` if the layout isn't beatable or the search runs out
+/// of budget.
+pub fn min_slides(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    config: &SolverConfig,
+) -> Option<usize> {
+    let mut costs = CostModel::default();
+    costs.reach.fill(0.0);
+    costs.slide.fill(1.0);
+    costs.turn.fill(0.0);
+    let solution = solve_weighted(rooms, rotations, config, &costs).solution?;
+    let slides = solution
+        .operations
+        .iter()
+        .filter(|operation| matches!(operation, Operations::Move(..)))
+        .count();
+    Some(slides)
+}
+
+/// If every shortest solution opens the gates in an order all of `rules`
+/// hold for. Not if there are more than [`MAX_OPTIMAL_SOLUTIONS`] of them to
+/// check or the search runs out of budget.
+pub fn forces_gate_order(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    rules: &[GateOrder],
+    config: &SolverConfig,
+) -> bool {
+    // solutions that only differ in the irrelevant gates are told apart
+    let config = SolverConfig {
+        keep_irrelevant_gates: true,
+        ..config.clone()
+    };
+    let optimal = all_optimal_solutions(rooms, rotations, &config, MAX_OPTIMAL_SOLUTIONS);
+    optimal.verdict == Verdict::Beatable
+        && !optimal.truncated
+        && optimal.solutions.iter().all(|solution| {
+            let order = solution.gate_order(rooms, rotations, &config);
+            rules.iter().all(|rule| rule.holds(&order))
+        })
+}
+
+/// how many shortest solutions [`forces_gate_order`] checks at most
+pub const MAX_OPTIMAL_SOLUTIONS: usize = 10_000;
+
+/// A layout made by [`guided_layout`] with its shortest solution.
+#[derive(Debug, Clone)]
+pub struct GuidedLayout {
+    pub rooms: [Room; 9],
+    pub rotations: [Rotation; 9],
+    pub solution: Solution,
+    /// how many swaps were tried before the layout was found
+    pub steps: usize,
+}
+
+/// Looks for a layout whose shortest solution has a length in `lengths` by
+/// starting from a random layout and swapping random pairs of tiles, keeping
+/// every swap that doesn't take the length further away from the range.
+/// Gives up after `max_steps` swaps.
+pub fn guided_layout(
+    rng: &mut impl LayoutRng,
+    empty_tiles: usize,
+    rotatable: bool,
+    lengths: RangeInclusive<usize>,
+    config: &SolverConfig,
+    max_steps: usize,
+) -> Option<GuidedLayout> {
+    // best first solutions aren't necessarily the shortest
+    let config = SolverConfig {
+        strategy: SearchStrategy::BreadthFirst,
+        ..config.clone()
+    };
+    let (mut rooms, mut rotations) = random_layout(rng, empty_tiles, rotatable);
+    let mut current = evaluate(&rooms, &rotations, &lengths, &config);
+    for steps in 0..=max_steps {
+        if let (0, Some(solution)) = &current {
+            return Some(GuidedLayout {
+                rooms,
+                rotations,
+                solution: solution.clone(),
+                steps,
+            });
+        }
+        let a = rng.below(9);
+        let b = (a + 1 + rng.below(8)) % 9;
+        let mut new_rooms = rooms;
+        let mut new_rotations = rotations;
+        new_rooms.swap(a, b);
+        new_rotations.swap(a, b);
+        if new_rooms == rooms && new_rotations == rotations {
+            continue;
+        }
+        let candidate = evaluate(&new_rooms, &new_rotations, &lengths, &config);
+        if candidate.0 <= current.0 {
+            (rooms, rotations, current) = (new_rooms, new_rotations, candidate);
+        }
+    }
+    None
+}
+
+/// how far the shortest solution's length is from the range, `usize::MAX`
+/// if there's none
+fn evaluate(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    lengths: &RangeInclusive<usize>,
+    config: &SolverConfig,
+) -> (usize, Option<Solution>) {
+    // the verifier rules out most layouts much faster than the solver
+    if verify_rotated_rooms(rooms, rotations, config).verdict != Verdict::Beatable {
+        return (usize::MAX, None);
+    }
+    let Some(solution) = solve_rotated_rooms(rooms, rotations, config).solution else {
+        return (usize::MAX, None);
+    };
+    let len = solution.operations.len();
+    let distance = if len < *lengths.start() {
+        lengths.start() - len
+    } else {
+        len.saturating_sub(*lengths.end())
+    };
+    (distance, Some(solution))
+}
@@ -0,0 +1,116 @@
+use crate::rooms::{
+    do_move, DoorRules, Entrance, OpenedGates, Room, Rotation, BOTH_WAY_DOORS, ENTRANCE_COUNT,
+    NO_ROTATIONS,
+};
+
+fn bit(entrance: Entrance) -> u32 {
+    1 << entrance as u32
+}
+
+fn entrances_in(mask: u32) -> impl Iterator<Item = Entrance> {
+    enum_iterator::all::<Entrance>().filter(move |e| mask & bit(*e) != 0)
+}
+
+/// The doors of a board that isn't being slid around, with a fixed set of
+/// opened gates. Edges are directed: walking through a room can need a gate
+/// in one direction but not the other, doorways between two rooms connect
+/// both ways unless they are one-way doors.
+#[derive(Debug, Clone)]
+pub struct DoorGraph {
+    tiles: [Option<u8>; ENTRANCE_COUNT],
+    adjacent: [u32; ENTRANCE_COUNT],
+}
+
+impl DoorGraph {
+    pub fn new(rooms: &[Room; 9], gates: OpenedGates) -> Self {
+        Self::with_rotations(rooms, &NO_ROTATIONS, gates)
+    }
+
+    pub fn with_rotations(
+        rooms: &[Room; 9],
+        rotations: &[Rotation; 9],
+        gates: OpenedGates,
+    ) -> Self {
+        Self::with_door_rules(rooms, rotations, &BOTH_WAY_DOORS, gates)
+    }
+
+    pub fn with_door_rules(
+        rooms: &[Room; 9],
+        rotations: &[Rotation; 9],
+        doors: &DoorRules,
+        gates: OpenedGates,
+    ) -> Self {
+        let mut tiles = [None; ENTRANCE_COUNT];
+        let mut adjacent = [0; ENTRANCE_COUNT];
+        for entrance in enum_iterator::all::<Entrance>() {
+            let room = entrance.to_room_direction().0;
+            let Some(tile) = rooms.iter().position(|r| *r == room) else {
+                continue;
+            };
+            let direction = rotations[tile].rotate(doors.direction(entrance));
+            let tile = tile as u8;
+            tiles[entrance as usize] = Some(tile);
+            if let Some(exit) = entrance.traverse_room(gates) {
+                adjacent[entrance as usize] |= bit(exit);
+            }
+            if let Some(other) = do_move(tile, direction).and_then(|(other_tile, other_dir)| {
+                doors.on_board(
+                    rooms[other_tile as usize],
+                    rotations[other_tile as usize],
+                    other_dir,
+                )
+            }) {
+                if doors.can_exit(entrance) && doors.can_enter(other) {
+                    adjacent[entrance as usize] |= bit(other);
+                }
+            }
+        }
+        DoorGraph { tiles, adjacent }
+    }
+
+    /// the tile the entrance is on, `None` if its room isn't on the board
+    pub fn tile(&self, entrance: Entrance) -> Option<u8> {
+        self.tiles[entrance as usize]
+    }
+
+    /// entrances that can be walked to from `entrance` directly, either
+    /// through its room or through the doorway into the neighboring room
+    pub fn neighbors(&self, entrance: Entrance) -> impl Iterator<Item = Entrance> {
+        entrances_in(self.adjacent[entrance as usize])
+    }
+
+    pub fn is_adjacent(&self, from: Entrance, to: Entrance) -> bool {
+        self.adjacent[from as usize] & bit(to) != 0
+    }
+
+    pub fn edges(&self) -> impl Iterator<Item = (Entrance, Entrance)> + '_ {
+        enum_iterator::all::<Entrance>()
+            .flat_map(|from| self.neighbors(from).map(move |to| (from, to)))
+    }
+
+    /// every entrance that can be walked to from `entrance`, including itself
+    pub fn reachable_from(&self, entrance: Entrance) -> Vec<Entrance> {
+        entrances_in(self.reachable_mask(entrance)).collect()
+    }
+
+    pub fn can_reach(&self, from: Entrance, to: Entrance) -> bool {
+        self.reachable_mask(from) & bit(to) != 0
+    }
+
+    fn reachable_mask(&self, entrance: Entrance) -> u32 {
+        if self.tile(entrance).is_none() {
+            return 0;
+        }
+        let mut reached = bit(entrance);
+        let mut todo = vec![entrance];
+        while let Some(current) = todo.pop() {
+            for next in self.neighbors(current) {
+                if reached & bit(next) == 0 {
+                    reached |= bit(next);
+                    todo.push(next);
+                }
+            }
+        }
+        reached
+    }
+}
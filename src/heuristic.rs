@@ -0,0 +1,197 @@
+//! Estimates of how many operations a state is from beating the layout, for
+//! [`SearchStrategy::AStar`] and iterative deepening, which becomes IDA*
+//! with one.
+//!
+//! Both find a shortest solution only with an estimate that's never more
+//! than the operations actually left. None of the built in ones promise
+//! that, a single operation can reach several entrances and open several
+//! gates at once, they trade it for expanding fewer states.
+//!
+//! [`SearchStrategy::AStar`]: crate::verify::SearchStrategy::AStar
+
+use std::fmt;
+
+use crate::{
+    rooms::{ControlPanel, Entrance, Room, Rotation},
+    solve::{solve_rotated_rooms, target_mask, SolveState},
+    verify::{SearchStrategy, SolverConfig},
+};
+
+/// How far a state is from beating the layout, in operations. Set one as
+/// [`SolverConfig::heuristic`].
+pub trait Heuristic: fmt::Debug + Send + Sync {
+    /// `unreached` are the entrances the win condition still needs, a state
+    /// is only beaten once it's empty
+    fn estimate(&self, state: &SolveState, unreached: Unreached) -> u32;
+}
+
+/// The entrances the win condition still needs from a state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Unreached(u32);
+
+impl Unreached {
+    pub fn len(&self) -> u32 {
+        self.0.count_ones()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn contains(&self, entrance: Entrance) -> bool {
+        self.0 & 1 << entrance as u32 != 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Entrance> + '_ {
+        enum_iterator::all::<Entrance>().filter(|entrance| self.contains(*entrance))
+    }
+
+    /// if a door of `room` is still needed
+    pub fn in_room(&self, room: Room) -> bool {
+        self.iter()
+            .any(|entrance| entrance.to_room_direction().0 == room)
+    }
+}
+
+/// the estimate of the config's heuristic, 0 without one
+pub(crate) fn estimate(config: &SolverConfig, state: &SolveState, targets: u32) -> u32 {
+    let Some(heuristic) = &config.heuristic else {
+        return 0;
+    };
+    heuristic.estimate(state, Unreached(targets & !state.reached))
+}
+
+/// One operation per entrance still to reach.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnreachedEntrances;
+
+impl Heuristic for UnreachedEntrances {
+    fn estimate(&self, _: &SolveState, unreached: Unreached) -> u32 {
+        unreached.len()
+    }
+}
+
+/// One operation per closed gate in a room with an entrance still to reach.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnopenedGates;
+
+impl Heuristic for UnopenedGates {
+    fn estimate(&self, state: &SolveState, unreached: Unreached) -> u32 {
+        enum_iterator::all::<Entrance>()
+            .filter(|entrance| {
+                entrance.open_gate().is_some_and(|gate| {
+                    !state.gates().contains(gate)
+                        && unreached.in_room(entrance.to_room_direction().0)
+                })
+            })
+            .count() as u32
+    }
+}
+
+/// How many tiles the nearest empty tile is away from being next to the
+/// nearest control panel in a room with an entrance still to reach, one
+/// slide each. Like the others it can be more than the operations left, the
+/// entrances may well be reachable without sliding anything there.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EmptyDistance;
+
+impl Heuristic for EmptyDistance {
+    fn estimate(&self, state: &SolveState, unreached: Unreached) -> u32 {
+        let tiles = |wanted: &dyn Fn(Room) -> bool| -> Vec<(u32, u32)> {
+            (0..9u32)
+                .filter(|tile| wanted(state.rooms()[*tile as usize]))
+                .map(|tile| (tile % 3, tile / 3))
+                .collect()
+        };
+        let empty = tiles(&|room| room == Room::Empty);
+        let needed = tiles(&|room| {
+            enum_iterator::all::<ControlPanel>()
+                .any(|panel| panel.room() == room && unreached.in_room(room))
+        });
+        empty
+            .iter()
+            .flat_map(|a| {
+                needed
+                    .iter()
+                    .map(move |b| a.0.abs_diff(b.0) + a.1.abs_diff(b.1))
+            })
+            .min()
+            .map_or(0, |distance| distance.saturating_sub(1))
+    }
+}
+
+/// A layout a heuristic estimated more operations for at the start than its
+/// shortest solution takes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Overestimate {
+    pub rooms: [Room; 9],
+    pub rotations: [Rotation; 9],
+    pub estimate: u32,
+    pub optimal: usize,
+}
+
+/// How the estimates of a heuristic at the start of layouts compare to
+/// their shortest solutions.
+#[derive(Debug, Clone, Default)]
+pub struct HeuristicReport {
+    /// the layouts a shortest solution was found for
+    pub layouts: usize,
+    /// the layouts that weren't beatable or ran out of budget, left out
+    pub skipped: usize,
+    pub overestimates: Vec<Overestimate>,
+    pub total_estimate: u64,
+    pub total_optimal: u64,
+}
+
+impl HeuristicReport {
+    /// if it never estimated more than the shortest solution
+    pub fn admissible(&self) -> bool {
+        self.overestimates.is_empty()
+    }
+
+    /// how many operations the estimate is below the shortest solution on
+    /// average, negative if it's above
+    pub fn mean_slack(&self) -> f64 {
+        (self.total_optimal as f64 - self.total_estimate as f64) / self.layouts.max(1) as f64
+    }
+}
+
+/// Compares the estimate at the start of every layout to the length of a
+/// shortest solution, found breadth first without a heuristic.
+pub fn evaluate_heuristic(
+    heuristic: &dyn Heuristic,
+    layouts: impl IntoIterator<Item = ([Room; 9], [Rotation; 9])>,
+    config: &SolverConfig,
+) -> HeuristicReport {
+    let config = SolverConfig {
+        strategy: SearchStrategy::BreadthFirst,
+        heuristic: None,
+        ..config.clone()
+    };
+    let mut report = HeuristicReport::default();
+    for (rooms, rotations) in layouts {
+        let result = solve_rotated_rooms(&rooms, &rotations, &config);
+        let (Some(solution), Ok(start)) = (
+            result.solution,
+            SolveState::start(&rooms, &rotations, &config),
+        ) else {
+            report.skipped += 1;
+            continue;
+        };
+        let targets = target_mask(&rooms, &config);
+        let estimate = heuristic.estimate(&start, Unreached(targets & !start.reached));
+        let optimal = solution.operations.len();
+        report.layouts += 1;
+        report.total_estimate += estimate as u64;
+        report.total_optimal += optimal as u64;
+        if estimate as usize > optimal {
+            report.overestimates.push(Overestimate {
+                rooms,
+                rotations,
+                estimate,
+                optimal,
+            });
+        }
+    }
+    report
+}
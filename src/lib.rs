@@ -0,0 +1,115 @@
+//! Verifies and solves the Sky Keep puzzle of Skyward Sword and its
+//! randomizer variants.
+//!
+//! Code that depends on this crate should use the [`prelude`]: what's in
+//! there keeps its names and signatures until the next minor version while
+//! the version is 0.x, and the next major one after that. The modules are
+//! public too, for the command line tool and for trying things out, but
+//! change whenever the tool needs them to, as do the glob exports of them
+//! next to the prelude.
+
+pub mod analysis;
+pub mod batch;
+pub mod chain;
+pub mod chart;
+pub mod completions;
+pub mod compress;
+// not glob exported, `corpus::iter` reads better than a bare `iter`
+pub mod corpus;
+pub mod cost;
+#[cfg(all(feature = "dolphin", target_os = "linux"))]
+pub mod dolphin;
+pub mod enumerate;
+pub mod export;
+#[cfg(feature = "gen")]
+pub mod generate;
+pub mod graph;
+pub mod heuristic;
+pub mod json;
+pub mod locale;
+pub mod notation;
+pub mod partial;
+pub mod patch;
+pub mod puzzle_file;
+#[cfg(feature = "gen")]
+pub mod python_random;
+pub mod qr;
+pub mod reachability;
+pub mod render;
+pub mod repair;
+pub mod rooms;
+pub mod rpc;
+pub mod session;
+pub mod share;
+pub mod simulator;
+pub mod solve;
+pub mod spoiler;
+pub mod stages;
+pub mod store;
+pub mod verify;
+pub mod webhook;
+pub mod websocket;
+
+pub use analysis::*;
+pub use batch::*;
+pub use chain::*;
+pub use chart::*;
+pub use completions::*;
+pub use compress::*;
+pub use cost::*;
+#[cfg(all(feature = "dolphin", target_os = "linux"))]
+pub use dolphin::*;
+pub use enumerate::*;
+pub use export::*;
+#[cfg(feature = "gen")]
+pub use generate::*;
+pub use graph::*;
+pub use heuristic::*;
+pub use json::*;
+pub use locale::*;
+pub use notation::*;
+pub use partial::*;
+pub use patch::*;
+pub use puzzle_file::*;
+#[cfg(feature = "gen")]
+pub use python_random::*;
+pub use qr::*;
+pub use reachability::*;
+pub use render::*;
+pub use repair::*;
+pub use rooms::*;
+pub use rpc::*;
+pub use session::*;
+pub use share::*;
+pub use simulator::*;
+pub use solve::*;
+pub use spoiler::*;
+pub use stages::*;
+pub use store::*;
+pub use verify::*;
+pub use webhook::*;
+pub use websocket::*;
+
+/// The stable part of the API, see the [crate] docs: layouts, the
+/// config, verifying, solving and playing the puzzle one operation at a time.
+pub mod prelude {
+    pub use crate::{
+        chain::ChainCycle,
+        notation::parse_operations,
+        patch::{patch_bytes, RoomIds, DEFAULT_ROOM_IDS},
+        rooms::{
+            are_equivalent, ControlPanel, Direction, Entrance, Layout, OpenedGates, Room, Rotation,
+            ALL_ROOMS, NO_ROTATIONS,
+        },
+        session::Session,
+        share::{layout_code, parse_layout_code},
+        solve::{
+            apply, solve_async, solve_rotated_rooms, MoveError, Solution, SolveFuture, SolveResult,
+            SolveState,
+        },
+        verify::{
+            verify_rotated_rooms, EmptySlot, EntryPoint, Operations, SearchStats, SolverConfig,
+            Verdict, Verification, WinCondition,
+        },
+    };
+}
@@ -0,0 +1,98 @@
+//! Translations for the text meant for players, the room names and the
+//! phrases of the run notes.
+
+use std::collections::HashMap;
+
+use enum_iterator::all;
+
+use crate::{
+    json::Json,
+    rooms::{Direction, Room},
+};
+
+/// The English text every locale starts from, `{room}` and `{direction}` are
+/// filled in where the phrase is used.
+const ENGLISH: &[(&str, &str)] = &[
+    ("direction.up", "up"),
+    ("direction.left", "left"),
+    ("direction.down", "down"),
+    ("direction.right", "right"),
+    ("notes.start", "{room} panel"),
+    ("notes.walk", "go to the {room} panel"),
+    ("notes.slide", "- slide {room} {direction}"),
+    ("notes.turn", "- turn {room}"),
+];
+
+/// Text by key, like `room.earth-temple` or `notes.slide`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Locale {
+    strings: HashMap<String, String>,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        let mut strings: HashMap<String, String> = ENGLISH
+            .iter()
+            .map(|(key, text)| (key.to_string(), text.to_string()))
+            .collect();
+        for room in all::<Room>() {
+            strings.insert(room_key(room), room.name().to_string());
+        }
+        Locale { strings }
+    }
+}
+
+impl Locale {
+    /// A JSON object of keys to their translation, keys that are left out
+    /// stay English so a translation can be done bit by bit.
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        let Json::Object(fields) = Json::parse(text)? else {
+            return Err("a locale has to be a JSON object".to_string());
+        };
+        let mut locale = Locale::default();
+        for (key, value) in fields {
+            if !locale.strings.contains_key(&key) {
+                return Err(format!("unknown locale key: {key}"));
+            }
+            let text = value
+                .as_str()
+                .ok_or_else(|| format!("{key} has to be a string"))?;
+            locale.strings.insert(key, text.to_string());
+        }
+        Ok(locale)
+    }
+
+    /// the text for `key`, the key itself if there's none
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        self.strings.get(key).map_or(key, String::as_str)
+    }
+
+    pub fn room(&self, room: Room) -> &str {
+        self.strings
+            .get(&room_key(room))
+            .map_or(room.name(), String::as_str)
+    }
+
+    pub fn direction(&self, direction: Direction) -> &str {
+        let key = match direction {
+            Direction::Up => "direction.up",
+            Direction::Left => "direction.left",
+            Direction::Down => "direction.down",
+            Direction::Right => "direction.right",
+        };
+        self.get(key)
+    }
+
+    /// the phrase for `key` with its `{name}` placeholders replaced
+    pub fn phrase(&self, key: &str, args: &[(&str, &str)]) -> String {
+        args.iter()
+            .fold(self.get(key).to_string(), |text, (name, value)| {
+                text.replace(&format!("{{{name}}}"), value)
+            })
+    }
+}
+
+/// `room.` and the name in lowercase with dashes, like `room.earth-temple`
+fn room_key(room: Room) -> String {
+    format!("room.{}", room.name().to_lowercase().replace(' ', "-"))
+}
@@ -1,506 +1,1747 @@
-use std::collections::{hash_map::Entry, HashMap, HashSet};
+use std::{
+    collections::BTreeMap,
+    io::{IsTerminal, Write},
+    ops::RangeInclusive,
+    sync::Arc,
+    time::Instant,
+};
 
-use enum_iterator::Sequence;
-use rand::{seq::SliceRandom, SeedableRng};
+use rand::SeedableRng;
+use skykeep_puzzle::{
+    all_optimal_solutions, ascii_board, check_markers, check_partial_layout, completion_script,
+    compress, corpus, count_solutions, cross_check, evaluate_heuristic, export_search_graphml,
+    export_search_tree, guided_layout, hardest_layout, index_to_layout, layout_code,
+    layout_notation, layouts_from, length_histogram_svg, livesplit_notes, nearest_beatable,
+    parse_control_panel, parse_cost_model, parse_deepening, parse_direction, parse_door_access,
+    parse_entrance, parse_gate, parse_gate_order, parse_hasher, parse_heuristic, parse_layout_code,
+    parse_partial_layout, parse_random_probes, parse_room_ids, parse_shell, parse_spoiler_log,
+    parse_strategy, parse_timeout, parse_win_condition, patch_bytes, patch_hex, post_json,
+    random_constrained_layout, random_edge, random_entry_point, random_gates, random_layout,
+    random_solvable_layout, route_steps, run_batch, sample_solvable, sample_solvable_fraction,
+    scout_ranking, shuffle_doors, solvable_by_position_svg, solvable_fraction, solve_rooms,
+    solve_rotated_rooms, solve_weighted, state_graph, state_space, tas_script, verify_entry_points,
+    verify_rooms, verify_rotated_rooms, AsciiRenderer, Board, BoardRenderer, Broadcaster, CliSpec,
+    CostModel, EmptySlot, EntryPoint, FileWriter, Heatmap, Heuristic, Json, LayoutConstraints,
+    LayoutRng, Locale, PartialLayout, PuzzleFile, PythonRandom, QrCode, ReachabilityMatrix, Room,
+    RoomIds, Rotation, RpcServer, SearchStats, Shell, Solution, Solvability, SolvableLayouts,
+    SolveResult, SolverConfig, StageIds, SvgRenderer, UnicodeRenderer, Verdict, Verification,
+    VisitedStore, WinCondition, DEFAULT_ROOM_IDS, LAYOUT_COUNT, NO_ROTATIONS,
+};
 
-bitflags::bitflags! {
-    #[derive(Debug, Clone, Copy)]
-    pub struct OpenedGates : u8 {
-        const STARTING = 1 << 0;
-        const EARTH_TEMPLE = 1 << 1;
-        const MINI_BOSS = 1 << 2;
-        const FIRE_SANCTUARY = 1 << 3;
-    }
+const USAGE: &str = "\
+usage: skykeep-puzzle [command] [options]
+       skykeep-puzzle --rpc [options]
+
+commands:
+    random                      verify a random layout (default)
+    enumerate                   verify every layout and count the results
+    extreme                     search every layout for the beatable one with
+                                the longest shortest solution
+    verify                      verify the layout from --file, --code,
+                                --spoiler or --partial
+    verify-corpus               check that the layouts with known verdicts
+                                still get them
+    batch                       verify every layout read from stdin as
+                                newline delimited JSON, like {\"code\": ...},
+                                and write one JSON result per line
+    track                       follow the puzzle in a running Dolphin and
+                                print hints, needs the dolphin feature
+    completions <shell>         print the completion script for bash, zsh or
+                                fish
+    analyze heuristic <name>    compare the estimates of a --heuristic at the
+                                start of --samples random beatable layouts
+                                (default 100) to their shortest solutions
+    analyze state-space <code>  count every reachable state of the layout
+                                with this share code by depth and by how
+                                many states it leads to, and the boards and
+                                gate combinations among them
+
+options:
+    --rpc                       answer JSON-RPC requests on stdin, one per
+                                line, with the other options as defaults
+    --websocket <addr>          --rpc, track: send the board and a hint to
+                                every WebSocket client on this address, like
+                                127.0.0.1:9001, whenever it changes
+    --dolphin-addresses layout=<addr>,gates=<addr>,player_tile=<addr>,player_direction=<addr>
+                                track: where the game keeps the puzzle
+    --file <path>               read the layout and options from a YAML file,
+                                has to be the first option, the ones after it
+                                override the ones in the file
+    --code <code>               verify the layout with this share code
+    --spoiler <path>            verify the Sky Keep layout in a spoiler log of
+                                the randomizer
+    --partial <notation>        verify a layout with unknown tiles, like
+                                \"STR ? ET/? BOS ?/FS SSH -\", for every way
+                                to fill them in
+    --probability               with --partial, the share of the ways to fill
+                                in the unknown tiles that are beatable
+    --samples <count>           with --probability, only check this many
+                                random ways, seeded by --seed. analyze: the
+                                layouts to look at
+    --scout                     with --partial, rank the unknown tiles by how
+                                much seeing them tells about being beatable
+    --qr <path>                 also write the share code as a QR code SVG
+    --patch                     print the bytes the randomizer patches into
+                                the game for the layout, as hex
+    --room-ids <rooms>          the game's number for each room for --patch,
+                                like \"STR SV ET LMF BOS AC FS SSH -\" for
+                                0 to 8 (the default)
+    --stages <path>             with --patch, also print where the room on
+                                each tile is in the game files, from a JSON
+                                object like {\"earth-temple\": {\"stage\":
+                                ..., \"room\": 0, \"layer\": 0}}
+    --charts <dir>              enumerate: write SVG charts of how beatable
+                                each room is on each tile and, with --solve,
+                                of the shortest solution lengths to this
+                                directory
+    --write-db <path>           enumerate: write the beatable layouts as a
+                                binary layout database for --layout-db,
+                                zstd compressed if the path ends in .zst
+    --webhook <url>             enumerate, extreme: POST a JSON summary to this
+                                URL when the run finishes or fails, https
+                                needs the curl feature
+    --cross-check               enumerate: also solve every layout and report
+                                where solver and verifier disagree
+    --limit <count>             enumerate, extreme: stop after this many layouts
+    --start <index>             enumerate, extreme: start at the layout with
+                                this index instead of the first, to split up
+                                or resume a run
+    --format <text|csv>         print one csv row per layout instead of text
+    --heatmap                   enumerate: print how often each room is on each
+                                tile in the beatable layouts instead
+    --all-entries               verify from every door on the edge of the board
+                                instead of only the entry point
+    --repair <swaps>            if the layout isn't beatable, look for the
+                                beatable one the fewest room swaps away
+    --reachability              print which entrances can be reached when
+                                entering with each combination of open gates
+    --box-drawing               draw the board with boxes and the doors of
+                                every room, ≡ for a door behind a closed gate
+    --svg <path>                write a picture of the board as SVG
+    --no-color                  don't color the board, also off when the output
+                                isn't a terminal or NO_COLOR is set
+    --state-graph               build the graph of every reachable state and
+                                print its strongly connected components and
+                                the transitions that can't be undone
+    --state-space               verify, random: analyze state-space for the
+                                layout instead of verifying it
+    --stats                     print how much searching it took, with the
+                                time spent where built with `profile`
+    --solve                     search for a shortest solution and print it,
+                                enumerate: fills in min_length
+    --route <notation>          verify, random: check that a route like
+                                \"STR: U L M:ET R\" solves the layout
+    --all-optimal <max>         with --solve, print up to this many shortest
+                                solutions instead of one, each going through
+                                different states
+    --count-paths <length>      count the solutions with at most this many
+                                operations, by length
+    --fastest                   with --solve, search for the route that takes
+                                the least time instead of the fewest
+                                operations
+    --costs <spec>              seconds per operation for --fastest, like
+                                slide=3,turn=2,reach=6,reach@mini-boss=8
+    --estimate-time             with --solve, print about how long the
+                                solution takes in real time, by --costs
+    --notes <path>              with --solve, write the solution as run notes
+                                for LiveSplit, grouped by panel
+    --locale <path>             write --notes in another language, from a JSON
+                                object of text by key like
+                                {\"room.sandship\": \"...\"}
+    --tas <path>                with --solve, write the solution as a JSON
+                                script of panels and slide directions for
+                                TAS tools
+    --search-tree <path>        with --solve, write every edge of the search
+                                tree to this file as JSON lines
+    --graphml <path>            with --solve, write the states and edges the
+                                search found to this file as GraphML
+                                (--search-tree and --graphml are zstd
+                                compressed for paths ending in .zst)
+    --max-nodes <count>         give up after expanding this many nodes
+    --threads <count>           solve breadth first on this many threads
+    --timeout <seconds>         give up after this much time
+    --empty-tiles <count>       leave out rooms to get more empty tiles (1-2)
+    --difficulty <min>-<max>    random: swap rooms around until the shortest
+                                solution has between min and max operations
+    --steps <count>             give up --difficulty after this many swaps,
+                                --solvable-only, --needs-panel,
+                                --min-slides and --gate-order after this
+                                many layouts (default 1000)
+    --solvable-only             random: draw layouts until one is beatable,
+                                every beatable layout is equally likely
+    --needs-panel <panel>       random: like --solvable-only, drawing until
+                                no solution gets by without sliding or
+                                turning rooms from this panel, like
+                                mini-boss, can be repeated
+    --min-slides <count>        random: like --solvable-only, drawing until
+                                every solution slides rooms at least this
+                                many times
+    --gate-order <rule>         random: like --solvable-only, drawing until
+                                every shortest solution opens the gates
+                                following the rule, like
+                                fire-sanctuary:last, starting:first or
+                                earth-temple:before:mini-boss, can be
+                                repeated
+    --layout-db <path>          random: pick one of the beatable layouts in
+                                this database from enumerate --write-db or
+                                CSV from enumerate --format csv, every one
+                                equally likely, can be zstd compressed
+    --seed <seed>               seed for the random layout, a random one if
+                                not given
+    --python-seed <seed>        shuffle with python's random.Random(seed) like
+                                the randomizer does instead of a random seed
+    --random-entry              random: enter through a random door on the
+                                edge of the board instead of the entry point
+    --rotatable-rooms           rooms start turned and can be turned
+    --toggling-gates <gate,...> gates that flip instead of staying open
+    --initial-gates <gate,...>  gates that are already open when entering
+    --random-gates              random: open a random set of gates from the
+                                start instead of --initial-gates
+    --one-way-door <entrance>:<enter-only|exit-only|both>
+                                restrict which way a door can be passed
+    --door-direction <entrance>:<up|down|left|right>
+                                move a door to another side of its room,
+                                swapping with the door there, like the
+                                doors line random layouts print
+    --shuffle-doors             random: move every room's doors to random
+                                sides first, keeping how many it has
+    --win-condition <all-entrances|all-panels|all-entrances-and-panels|sandship|all-markers>
+                                what has to be reachable
+    --marker <entrance>         something to collect behind this entrance, for
+                                --win-condition all-markers, can be repeated
+    --sandship-only             same as --win-condition sandship --solve, for
+                                the shortest route to the Sandship
+    --strategy <breadth-first|best-first|depth-first|iterative-deepening|a-star>
+                                the order --solve expands states in
+    --heuristic <unreached-entrances|unopened-gates|empty-distance>
+                                the estimate a-star and iterative deepening
+                                go by. None of these is admissible, they can
+                                estimate more operations than are left, so
+                                the solution isn't necessarily the shortest
+                                with one
+    --deepening <start>+<step>|<start>x<factor>
+                                the depth bounds iterative deepening tries,
+                                the solution can be up to the bound long
+                                instead of the shortest unless it's 1+1
+                                (default 1+1)
+    --random-probes <count>:<nodes>
+                                before --solve searches, try this many short
+                                depth first searches in a random order that
+                                give up after expanding nodes states
+    --keep-irrelevant-gates     keep gates that can't change any walk in the
+                                solver's states, to cross-check leaving them
+                                out
+    --hasher <fx|sip>           the hash function for the states kept in
+                                memory (default fx)
+    --chain-cache               remember the walks around every position the
+                                search gets to, --stats shows the hits
+    --ordered-store             keep the verifier's visited states sorted, for
+                                debugging
+    --disk-store <dir>          keep the verifier's visited states in a file
+                                in this directory instead of memory
+    --bloom-filter <states>:<false-positive-rate>
+                                keep the verifier's visited states in a Bloom
+                                filter, not beatable verdicts become unreliable
+";
+
+enum Command {
+    Random,
+    Enumerate,
+    Extreme,
+    Verify,
+    VerifyCorpus,
+    Batch,
+    #[cfg(all(feature = "dolphin", target_os = "linux"))]
+    Track,
+    Completions(Shell),
+    AnalyzeHeuristic(String, Arc<dyn Heuristic>),
+    AnalyzeStateSpace,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Sequence)]
-pub enum Direction {
-    Up,
-    Left,
-    Down,
-    Right,
+#[derive(PartialEq)]
+enum Format {
+    Text,
+    Csv,
 }
 
-impl Direction {
-    pub fn opposite(&self) -> Direction {
-        match self {
-            Direction::Up => Direction::Down,
-            Direction::Left => Direction::Right,
-            Direction::Down => Direction::Up,
-            Direction::Right => Direction::Left,
+struct Args {
+    command: Command,
+    layout: Option<([Room; 9], [Rotation; 9])>,
+    config: SolverConfig,
+    empty_tiles: usize,
+    solve: bool,
+    cross_check: bool,
+    limit: Option<usize>,
+    start: u32,
+    format: Format,
+    qr: Option<String>,
+    patch: bool,
+    room_ids: RoomIds,
+    stages: Option<StageIds>,
+    seed: Option<u64>,
+    python_seed: Option<u64>,
+    random_entry: bool,
+    random_gates: bool,
+    shuffle_doors: bool,
+    solvable_only: bool,
+    constraints: LayoutConstraints,
+    layout_db: Option<String>,
+    stats: bool,
+    color: bool,
+    box_drawing: bool,
+    svg: Option<String>,
+    all_entries: bool,
+    repair: Option<usize>,
+    heatmap: bool,
+    reachability: bool,
+    state_graph: bool,
+    state_space: bool,
+    difficulty: Option<RangeInclusive<usize>>,
+    steps: usize,
+    search_tree: Option<String>,
+    graphml: Option<String>,
+    fastest: bool,
+    all_optimal: Option<usize>,
+    route: Option<Solution>,
+    count_paths: Option<usize>,
+    estimate_time: bool,
+    notes: Option<String>,
+    locale: Locale,
+    tas: Option<String>,
+    costs: CostModel,
+    rpc: bool,
+    websocket: Option<String>,
+    webhook: Option<String>,
+    charts: Option<String>,
+    write_db: Option<String>,
+    partial: Option<PartialLayout>,
+    probability: bool,
+    samples: Option<usize>,
+    scout: bool,
+    #[cfg(all(feature = "dolphin", target_os = "linux"))]
+    dolphin_addresses: Option<String>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut command = Command::Random;
+    let mut layout = None;
+    let mut config = SolverConfig::default();
+    let mut empty_tiles = 1;
+    let mut solve = false;
+    let mut cross_check = false;
+    let mut limit = None;
+    let mut start = 0;
+    let mut format = Format::Text;
+    let mut qr = None;
+    let mut patch = false;
+    let mut room_ids = DEFAULT_ROOM_IDS;
+    let mut stages = None;
+    let mut seed = None;
+    let mut python_seed = None;
+    let mut random_entry = false;
+    let mut random_gates = false;
+    let mut shuffle_doors = false;
+    let mut solvable_only = false;
+    let mut constraints = LayoutConstraints::default();
+    let mut layout_db = None;
+    let mut stats = false;
+    let mut box_drawing = false;
+    let mut svg = None;
+    // colors only for a terminal, not when the output is piped somewhere
+    let mut color = std::io::stdout().is_terminal()
+        && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty());
+    let mut all_entries = false;
+    let mut heatmap = false;
+    let mut reachability = false;
+    let mut state_graph = false;
+    let mut state_space = false;
+    let mut difficulty = None;
+    let mut steps = 1000;
+    let mut search_tree = None;
+    let mut graphml = None;
+    let mut fastest = false;
+    let mut all_optimal = None;
+    let mut route = None;
+    let mut count_paths = None;
+    let mut estimate_time = false;
+    let mut notes = None;
+    let mut locale = Locale::default();
+    let mut tas = None;
+    let mut costs = CostModel::default();
+    let mut rpc = false;
+    let mut websocket = None;
+    let mut webhook = None;
+    let mut charts = None;
+    let mut write_db = None;
+    let mut partial = None;
+    let mut probability = false;
+    let mut samples = None;
+    let mut scout = false;
+    #[cfg(all(feature = "dolphin", target_os = "linux"))]
+    let mut dolphin_addresses = None;
+    let mut repair = None;
+    let mut args = std::env::args().skip(1).peekable();
+    if let Some(name) = args.next_if(|arg| !arg.starts_with("--")) {
+        command = match name.as_str() {
+            "random" => Command::Random,
+            "enumerate" => Command::Enumerate,
+            "extreme" => Command::Extreme,
+            "verify" => Command::Verify,
+            "verify-corpus" => Command::VerifyCorpus,
+            "batch" => Command::Batch,
+            #[cfg(all(feature = "dolphin", target_os = "linux"))]
+            "track" => Command::Track,
+            "completions" => {
+                let shell = args
+                    .next()
+                    .ok_or_else(|| "completions needs a shell".to_string())?;
+                Command::Completions(parse_shell(&shell)?)
+            }
+            "analyze" => match (args.next().as_deref(), args.next()) {
+                (Some("heuristic"), Some(name)) => {
+                    let heuristic = parse_heuristic(&name)?;
+                    Command::AnalyzeHeuristic(name, heuristic)
+                }
+                (Some("state-space"), Some(code)) => {
+                    layout = Some(parse_layout_code(&code)?);
+                    Command::AnalyzeStateSpace
+                }
+                _ => {
+                    return Err(
+                        "expected analyze heuristic <name> or analyze state-space <code>"
+                            .to_string(),
+                    )
+                }
+            },
+            _ => return Err(format!("unknown command: {name}")),
+        };
+    }
+    let mut first_option = true;
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("missing value for {arg}"))
+        };
+        match arg.as_str() {
+            // the file sets every option, it would undo the ones before it
+            "--file" if !first_option => {
+                return Err("--file has to be the first option".to_string())
+            }
+            "--file" => {
+                let path = value()?;
+                let text = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("can't read {path}: {e}"))?;
+                let file = PuzzleFile::parse(&text).map_err(|e| format!("{path}: {e}"))?;
+                layout = Some((file.rooms, file.rotations));
+                config = file.config;
+            }
+            "--code" => layout = Some(parse_layout_code(&value()?)?),
+            "--spoiler" => {
+                let path = value()?;
+                let text = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("can't read {path}: {e}"))?;
+                let rooms = parse_spoiler_log(&text).map_err(|e| format!("{path}: {e}"))?;
+                layout = Some((rooms, NO_ROTATIONS));
+            }
+            "--partial" => partial = Some(parse_partial_layout(&value()?)?),
+            "--probability" => probability = true,
+            "--scout" => scout = true,
+            #[cfg(all(feature = "dolphin", target_os = "linux"))]
+            "--dolphin-addresses" => dolphin_addresses = Some(value()?),
+            "--samples" => {
+                let value = value()?;
+                samples = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("invalid sample count: {value}"))?,
+                );
+            }
+            "--qr" => qr = Some(value()?),
+            "--patch" => patch = true,
+            "--room-ids" => room_ids = parse_room_ids(&value()?)?,
+            "--stages" => {
+                let path = value()?;
+                let text = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("can't read {path}: {e}"))?;
+                stages = Some(StageIds::from_json(&text).map_err(|e| format!("{path}: {e}"))?);
+            }
+            "--max-nodes" => {
+                let value = value()?;
+                config.max_nodes = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("invalid node count: {value}"))?,
+                );
+            }
+            "--threads" => {
+                let value = value()?;
+                config.threads = value
+                    .parse()
+                    .map_err(|_| format!("invalid thread count: {value}"))?;
+            }
+            "--timeout" => config.timeout = Some(parse_timeout(&value()?)?),
+            "--rotatable-rooms" => config.rotatable_rooms = true,
+            "--random-entry" => random_entry = true,
+            "--random-gates" => random_gates = true,
+            "--shuffle-doors" => shuffle_doors = true,
+            "--solvable-only" => solvable_only = true,
+            "--needs-panel" => constraints.panels.push(parse_control_panel(&value()?)?),
+            "--gate-order" => constraints.gate_order.push(parse_gate_order(&value()?)?),
+            "--min-slides" => {
+                let value = value()?;
+                constraints.min_slides = value
+                    .parse()
+                    .map_err(|_| format!("invalid slide count: {value}"))?;
+            }
+            "--layout-db" => layout_db = Some(value()?),
+            "--solve" => solve = true,
+            "--search-tree" => search_tree = Some(value()?),
+            "--graphml" => graphml = Some(value()?),
+            "--fastest" => fastest = true,
+            "--count-paths" => {
+                let value = value()?;
+                count_paths = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("invalid length: {value}"))?,
+                );
+            }
+            "--all-optimal" => {
+                let value = value()?;
+                all_optimal = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("invalid solution count: {value}"))?,
+                );
+            }
+            "--route" => route = Some(value()?.parse()?),
+            "--estimate-time" => estimate_time = true,
+            "--notes" => notes = Some(value()?),
+            "--locale" => {
+                let path = value()?;
+                let text = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("can't read {path}: {e}"))?;
+                locale = Locale::from_json(&text).map_err(|e| format!("{path}: {e}"))?;
+            }
+            "--tas" => tas = Some(value()?),
+            "--costs" => costs = parse_cost_model(&value()?)?,
+            "--rpc" => rpc = true,
+            "--websocket" => websocket = Some(value()?),
+            "--webhook" => webhook = Some(value()?),
+            "--charts" => charts = Some(value()?),
+            "--write-db" => write_db = Some(value()?),
+            "--cross-check" => cross_check = true,
+            "--stats" => stats = true,
+            "--no-color" => color = false,
+            "--box-drawing" => box_drawing = true,
+            "--svg" => svg = Some(value()?),
+            "--all-entries" => all_entries = true,
+            "--heatmap" => heatmap = true,
+            "--reachability" => reachability = true,
+            "--state-graph" => state_graph = true,
+            "--state-space" => state_space = true,
+            "--repair" => {
+                let value = value()?;
+                repair = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("invalid swap count: {value}"))?,
+                );
+            }
+            "--format" => {
+                format = match value()?.as_str() {
+                    "text" => Format::Text,
+                    "csv" => Format::Csv,
+                    other => return Err(format!("unknown format: {other}")),
+                };
+            }
+            "--start" => {
+                let value = value()?;
+                start = value
+                    .parse()
+                    .ok()
+                    .filter(|start| *start < LAYOUT_COUNT)
+                    .ok_or_else(|| format!("invalid start: {value}"))?;
+            }
+            "--limit" => {
+                let value = value()?;
+                limit = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("invalid limit: {value}"))?,
+                );
+            }
+            "--toggling-gates" => {
+                for name in value()?.split(',') {
+                    config.toggling_gates |= parse_gate(name)?;
+                }
+            }
+            "--initial-gates" => {
+                for name in value()?.split(',') {
+                    config.initial_gates |= parse_gate(name)?;
+                }
+            }
+            "--door-direction" => {
+                let value = value()?;
+                let (entrance, direction) = value
+                    .split_once(':')
+                    .ok_or_else(|| format!("expected <entrance>:<direction>, got {value}"))?;
+                config
+                    .door_rules
+                    .set_direction(parse_entrance(entrance)?, parse_direction(direction)?);
+            }
+            "--one-way-door" => {
+                let value = value()?;
+                let (entrance, access) = value
+                    .split_once(':')
+                    .ok_or_else(|| format!("expected <entrance>:<access>, got {value}"))?;
+                config
+                    .door_rules
+                    .set_access(parse_entrance(entrance)?, parse_door_access(access)?);
+            }
+            "--sandship-only" => {
+                config.win_condition = WinCondition::Sandship;
+                solve = true;
+            }
+            "--win-condition" => config.win_condition = parse_win_condition(&value()?)?,
+            "--marker" => {
+                config.markers.insert(parse_entrance(&value()?)?);
+            }
+            "--strategy" => config.strategy = parse_strategy(&value()?)?,
+            "--deepening" => config.deepening = parse_deepening(&value()?)?,
+            "--heuristic" => config.heuristic = Some(parse_heuristic(&value()?)?),
+            "--random-probes" => config.probes = parse_random_probes(&value()?)?,
+            "--hasher" => config.hasher = parse_hasher(&value()?)?,
+            "--chain-cache" => config.chain_cache = true,
+            "--keep-irrelevant-gates" => config.keep_irrelevant_gates = true,
+            "--ordered-store" => config.visited_store = VisitedStore::Ordered,
+            "--disk-store" => config.visited_store = VisitedStore::Disk(value()?.into()),
+            "--bloom-filter" => {
+                let value = value()?;
+                let parsed = value.split_once(':').and_then(|(states, rate)| {
+                    Some(VisitedStore::Bloom {
+                        expected_states: states.parse().ok()?,
+                        false_positive_rate: rate.parse().ok()?,
+                    })
+                });
+                config.visited_store =
+                    parsed.ok_or_else(|| format!("invalid bloom filter: {value}"))?;
+            }
+            "--seed" => {
+                let value = value()?;
+                seed = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("invalid seed: {value}"))?,
+                );
+            }
+            "--python-seed" => {
+                let value = value()?;
+                python_seed = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("invalid seed: {value}"))?,
+                );
+            }
+            "--empty-tiles" => {
+                let value = value()?;
+                empty_tiles = value
+                    .parse()
+                    .ok()
+                    .filter(|count| (1..=enum_iterator::cardinality::<EmptySlot>()).contains(count))
+                    .ok_or_else(|| format!("invalid empty tile count: {value}"))?;
+            }
+            "--difficulty" => {
+                let value = value()?;
+                let parsed = value
+                    .split_once('-')
+                    .and_then(|(min, max)| Some(min.parse().ok()?..=max.parse().ok()?));
+                difficulty = Some(
+                    parsed
+                        .filter(|lengths| !lengths.is_empty())
+                        .ok_or_else(|| format!("invalid difficulty: {value}"))?,
+                );
+            }
+            "--steps" => {
+                let value = value()?;
+                steps = value
+                    .parse()
+                    .map_err(|_| format!("invalid step count: {value}"))?;
+            }
+            _ => return Err(format!("unknown argument: {arg}")),
         }
+        first_option = false;
+    }
+    check_markers(&config)?;
+    if matches!(command, Command::Verify) && layout.is_none() && partial.is_none() {
+        return Err(
+            "verify needs a layout from --file, --code, --spoiler or --partial".to_string(),
+        );
     }
+    if (solvable_only || !constraints.is_empty() || layout_db.is_some())
+        && (difficulty.is_some() || random_entry || random_gates)
+    {
+        return Err(
+            "--solvable-only, --needs-panel, --min-slides, --gate-order and --layout-db only \
+             pick the layout, they can't be combined with --difficulty, --random-entry or \
+             --random-gates"
+                .to_string(),
+        );
+    }
+    if !constraints.is_empty() && layout_db.is_some() {
+        return Err(
+            "--needs-panel, --min-slides and --gate-order draw layouts, they can't pick one \
+             from --layout-db"
+                .to_string(),
+        );
+    }
+    if shuffle_doors && (layout_db.is_some() || random_entry) {
+        return Err(
+            "--layout-db and --random-entry go by the doors of the game, they can't be \
+             combined with --shuffle-doors"
+                .to_string(),
+        );
+    }
+    if search_tree.is_some() && graphml.is_some() {
+        return Err("--search-tree and --graphml need a search each, use one".to_string());
+    }
+    Ok(Args {
+        command,
+        layout,
+        config,
+        empty_tiles,
+        solve,
+        cross_check,
+        limit,
+        start,
+        format,
+        qr,
+        patch,
+        room_ids,
+        stages,
+        seed,
+        python_seed,
+        random_entry,
+        random_gates,
+        shuffle_doors,
+        solvable_only,
+        constraints,
+        layout_db,
+        stats,
+        color,
+        box_drawing,
+        svg,
+        all_entries,
+        heatmap,
+        reachability,
+        state_graph,
+        state_space,
+        difficulty,
+        steps,
+        search_tree,
+        graphml,
+        fastest,
+        all_optimal,
+        route,
+        count_paths,
+        estimate_time,
+        notes,
+        locale,
+        tas,
+        costs,
+        rpc,
+        websocket,
+        webhook,
+        charts,
+        write_db,
+        partial,
+        probability,
+        samples,
+        scout,
+        #[cfg(all(feature = "dolphin", target_os = "linux"))]
+        dolphin_addresses,
+        repair,
+    })
+}
 
-    pub fn tile_move(&self) -> isize {
-        match self {
-            Direction::Up => -3,
-            Direction::Left => -1,
-            Direction::Down => 3,
-            Direction::Right => 1,
+fn main() {
+    let mut args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{e}");
+            eprint!("{USAGE}");
+            std::process::exit(2);
+        }
+    };
+    if args.rpc {
+        run_rpc(&args);
+        return;
+    }
+    if let Some(url) = args.webhook.clone() {
+        // report crashes too, after printing them as usual
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            default_hook(info);
+            notify(
+                &url,
+                Json::object([
+                    ("status", "failed".into()),
+                    ("error", info.to_string().into()),
+                ]),
+            );
+        }));
+    }
+    match args.command {
+        Command::Random => run_random(&mut args),
+        Command::Enumerate => run_enumerate(&args),
+        Command::Extreme => run_extreme(&args),
+        Command::Verify => match (&args.partial, args.layout) {
+            (Some(partial), _) => check_partial(&args, partial),
+            (None, Some((rooms, rotations))) => check_layout(&args, rooms, rotations),
+            (None, None) => unreachable!(),
+        },
+        Command::VerifyCorpus => run_verify_corpus(&args),
+        Command::Batch => run_pipeline(&args),
+        #[cfg(all(feature = "dolphin", target_os = "linux"))]
+        Command::Track => run_track(&args),
+        Command::Completions(shell) => print!(
+            "{}",
+            completion_script(shell, "skykeep-puzzle", &CliSpec::from_usage(USAGE))
+        ),
+        Command::AnalyzeHeuristic(ref name, ref heuristic) => {
+            run_analyze_heuristic(&args, name, heuristic.as_ref())
         }
+        Command::AnalyzeStateSpace => {
+            let (rooms, rotations) = args.layout.expect("set with the command");
+            run_analyze_state_space(&args, &rooms, &rotations)
+        }
+    }
+}
+
+/// counts the states of the layout, for analyze state-space and --state-space
+fn run_analyze_state_space(args: &Args, rooms: &[Room; 9], rotations: &[Rotation; 9]) {
+    let space = state_space(rooms, rotations, &args.config);
+    if let Some(verdict) = &space.incomplete {
+        eprintln!("{}", verdict_text(verdict));
+        std::process::exit(1);
+    }
+    println!(
+        "{} states up to depth {}, on {} boards with {} combinations of open gates",
+        space.states(),
+        space.max_depth(),
+        space.boards,
+        space.gate_sets
+    );
+    println!(
+        "{:.2} states reached from a state on average, at most {}",
+        space.mean_branching(),
+        space.by_branching.len().saturating_sub(1)
+    );
+    let by_depth: Vec<String> = space.by_depth.iter().map(usize::to_string).collect();
+    println!("by depth: {}", by_depth.join(" "));
+    let by_branching: Vec<String> = space
+        .by_branching
+        .iter()
+        .enumerate()
+        .filter(|(_, count)| **count > 0)
+        .map(|(branching, count)| format!("{branching}:{count}"))
+        .collect();
+    println!("by branching: {}", by_branching.join(" "));
+    if args.stats {
+        println!("{}", space.stats);
+    }
+}
+
+/// compares the heuristic to the shortest solutions of random beatable
+/// layouts, drawn like --solvable-only does
+fn run_analyze_heuristic(args: &Args, name: &str, heuristic: &dyn Heuristic) {
+    let mut rng = seeded_rng(args);
+    let rotatable = args.config.rotatable_rooms;
+    let count = args.samples.unwrap_or(100);
+    let layouts: Vec<_> = (0..count)
+        .map_while(|_| {
+            random_solvable_layout(
+                &mut rng,
+                args.empty_tiles,
+                rotatable,
+                &args.config,
+                args.steps,
+            )
+        })
+        .map(|sample| (sample.rooms, sample.rotations))
+        .collect();
+    if layouts.len() < count {
+        eprintln!("no beatable layout in {} tries", args.steps);
+        std::process::exit(1);
+    }
+    let report = evaluate_heuristic(heuristic, layouts, &args.config);
+    println!(
+        "{name} over {} beatable layouts, {} left out:",
+        report.layouts, report.skipped
+    );
+    let mean = |total: u64| total as f64 / report.layouts.max(1) as f64;
+    println!(
+        "estimate {:.2}, shortest solution {:.2}, slack {:.2} operations on average",
+        mean(report.total_estimate),
+        mean(report.total_optimal),
+        report.mean_slack()
+    );
+    if report.admissible() {
+        println!("never more than the shortest solution");
+        return;
+    }
+    println!(
+        "more than the shortest solution on {} layouts:",
+        report.overestimates.len()
+    );
+    for overestimate in &report.overestimates {
+        println!(
+            "{} estimate {}, shortest solution {}",
+            layout_code(&overestimate.rooms, &overestimate.rotations),
+            overestimate.estimate,
+            overestimate.optimal
+        );
     }
 }
 
-#[derive(Debug, Sequence, Clone, Copy, PartialEq, Eq)]
-pub enum ControlPanel {
-    Start,
-    LanayruMiningFacility,
-    EarthTemple,
-    MiniBoss,
+fn run_rpc(args: &Args) {
+    let mut server = RpcServer::new(args.config.clone());
+    if let Some(broadcaster) = bind_websocket(args) {
+        server.on_state(move |message| broadcaster.send(&message.to_string()));
+    }
+    let mut stdout = std::io::stdout().lock();
+    for line in std::io::stdin().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = server.handle(&line) {
+            // the other side is gone
+            if writeln!(stdout, "{response}")
+                .and_then(|_| stdout.flush())
+                .is_err()
+            {
+                break;
+            }
+        }
+    }
 }
 
-impl ControlPanel {
-    pub fn entrance(&self) -> Entrance {
-        match self {
-            ControlPanel::Start => Entrance::StartDown,
-            ControlPanel::LanayruMiningFacility => Entrance::LanayruMiningFacilityDown,
-            ControlPanel::EarthTemple => Entrance::EarthTempleDown,
-            ControlPanel::MiniBoss => Entrance::MiniBossLeft,
+/// `batch`, a summary goes to stderr so stdout stays NDJSON
+fn run_pipeline(args: &Args) {
+    let mut stdout = std::io::stdout().lock();
+    match run_batch(
+        std::io::stdin().lock(),
+        &mut stdout,
+        &args.config,
+        args.solve,
+    ) {
+        Ok(summary) => eprintln!("{} layouts, {} errors", summary.layouts, summary.errors),
+        // the other side is gone
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => {}
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Sequence, Hash)]
-pub enum Room {
-    Start,
-    Skyview,
-    EarthTemple,
-    LanayruMiningFacility,
-    MiniBoss,
-    AncientCistern,
-    FireSanctuary,
-    Sandship,
-    Empty,
+fn bind_websocket(args: &Args) -> Option<Broadcaster> {
+    let addr = args.websocket.as_ref()?;
+    match Broadcaster::bind(addr) {
+        Ok(broadcaster) => Some(broadcaster),
+        Err(e) => {
+            eprintln!("can't listen on {addr}: {e}");
+            std::process::exit(1);
+        }
+    }
 }
 
-pub fn do_move(tile: u8, direction: Direction) -> Option<(u8, Direction)> {
-    match direction {
-        Direction::Up => {
-            if tile < 3 {
-                None
-            } else {
-                Some((tile - 3, Direction::Down))
+#[cfg(all(feature = "dolphin", target_os = "linux"))]
+fn run_track(args: &Args) {
+    use skykeep_puzzle::{
+        gate_line, optimal_finish, overlay_message, DolphinAddresses, DolphinMemory, GameState,
+        SolveState,
+    };
+
+    let fail = |e: String| -> ! {
+        eprintln!("{e}");
+        std::process::exit(1);
+    };
+    let addresses = args
+        .dolphin_addresses
+        .as_deref()
+        .ok_or_else(|| "track needs --dolphin-addresses".to_string())
+        .and_then(DolphinAddresses::parse)
+        .unwrap_or_else(|e| fail(e));
+    let mut memory = DolphinMemory::attach().unwrap_or_else(|e| fail(e.to_string()));
+    let broadcaster = bind_websocket(args);
+    let mut last: Option<GameState> = None;
+    loop {
+        let state = memory
+            .read_state(&addresses)
+            .unwrap_or_else(|e| fail(format!("can't read the game's memory: {e}")));
+        if last != Some(state) {
+            // gates opened since the last read, none on the first one
+            let new_gates = state
+                .gates
+                .difference(last.map_or(state.gates, |last| last.gates));
+            last = Some(state);
+            print!(
+                "{}",
+                ascii_board(&state.rooms, Some(state.player_tile), args.color)
+            );
+            println!(
+                "player on tile {}, gates {}",
+                state.player_tile,
+                gate_line(state.gates, new_gates, args.color)
+            );
+            // what's left from where the player stands with the gates that
+            // are open now
+            let config = SolverConfig {
+                initial_gates: state.gates,
+                ..args.config.clone()
+            };
+            let finish = match SolveState::at(
+                &state.rooms,
+                &NO_ROTATIONS,
+                &config,
+                state.player_tile,
+                state.player_direction,
+            ) {
+                Ok(start) => {
+                    let finish = optimal_finish(&start, &config);
+                    match &finish {
+                        Some(ops) => println!("{} operations left at best", ops.len()),
+                        None => println!("not beatable from here"),
+                    }
+                    finish
+                }
+                Err(verdict) => {
+                    println!("{}", verdict_text(&verdict));
+                    None
+                }
+            };
+            if let Some(broadcaster) = &broadcaster {
+                let message = overlay_message(
+                    &state.rooms,
+                    &NO_ROTATIONS,
+                    state.player_tile,
+                    state.gates,
+                    finish.as_deref(),
+                );
+                broadcaster.send(&message.to_string());
             }
         }
-        Direction::Left => {
-            if [0, 3, 6].contains(&tile) {
-                None
-            } else {
-                Some((tile - 1, Direction::Right))
+        std::thread::sleep(std::time::Duration::from_millis(250));
+    }
+}
+
+fn notify(url: &str, summary: Json) {
+    if let Err(e) = post_json(url, &summary) {
+        eprintln!("can't send the summary to the webhook: {e}");
+    }
+}
+
+/// the layouts from --start on, at most --limit of them
+fn layouts(args: &Args) -> impl Iterator<Item = [Room; 9]> {
+    layouts_from(index_to_layout(args.start)).take(args.limit.unwrap_or(usize::MAX))
+}
+
+fn run_enumerate(args: &Args) {
+    let start = Instant::now();
+    let mut beatable = 0;
+    let mut not_beatable = 0;
+    let mut invalid = 0;
+    let mut indeterminate = 0;
+    let mut disagreements = 0;
+    let mut heatmap = Heatmap::default();
+    // every layout, to compare the beatable ones in `heatmap` against
+    let mut all_positions = Heatmap::default();
+    let mut lengths = BTreeMap::new();
+    let mut database = Vec::new();
+    if args.format == Format::Csv && !args.heatmap {
+        println!("{CSV_HEADER}");
+    }
+    for rooms in layouts(args) {
+        let (verdict, min_length, stats) = if args.cross_check {
+            let (verification, disagreement) = cross_check(&rooms, &args.config);
+            if let Some(disagreement) = disagreement {
+                // keep the csv on stdout clean
+                match args.format {
+                    Format::Text => println!("disagreement: {disagreement}"),
+                    Format::Csv => eprintln!("disagreement: {disagreement}"),
+                }
+                disagreements += 1;
             }
+            (verification.verdict, None, verification.stats)
+        } else if args.solve {
+            let result = solve_rooms(&rooms, &args.config);
+            let min_length = result.solution.map(|s| s.operations.len());
+            (result.verdict, min_length, result.stats)
+        } else {
+            let verification = verify_rooms(&rooms, &args.config);
+            (verification.verdict, None, verification.stats)
+        };
+        if args.format == Format::Csv && !args.heatmap {
+            println!("{}", csv_row(&rooms, &verdict, min_length, &stats));
         }
-        Direction::Down => {
-            if tile >= 6 {
-                None
-            } else {
-                Some((tile + 3, Direction::Up))
+        all_positions.add(&rooms);
+        if let Some(length) = min_length {
+            *lengths.entry(length).or_insert(0) += 1;
+        }
+        match verdict {
+            Verdict::Beatable => {
+                beatable += 1;
+                heatmap.add(&rooms);
+                if args.write_db.is_some() {
+                    database.push(rooms);
+                }
             }
+            Verdict::NotBeatable(_) => not_beatable += 1,
+            Verdict::ChainCycle(_) => invalid += 1,
+            Verdict::Indeterminate(_) => indeterminate += 1,
         }
-        Direction::Right => {
-            if [2, 5, 8].contains(&tile) {
-                None
-            } else {
-                Some((tile + 1, Direction::Left))
+    }
+    let summary = format!(
+        "{} layouts: {beatable} beatable, {not_beatable} not beatable, {invalid} invalid, {indeterminate} indeterminate",
+        beatable + not_beatable + invalid + indeterminate
+    );
+    match args.format {
+        Format::Text => println!("{summary}"),
+        Format::Csv => eprintln!("{summary}"),
+    }
+    if args.heatmap {
+        match args.format {
+            Format::Text => print!("{heatmap}"),
+            Format::Csv => print!("{}", heatmap.to_csv()),
+        }
+    }
+    if args.cross_check {
+        match args.format {
+            Format::Text => println!("{disagreements} disagreements"),
+            Format::Csv => eprintln!("{disagreements} disagreements"),
+        }
+    }
+    if let Some(dir) = &args.charts {
+        let mut charts = vec![(
+            "solvable-by-position.svg",
+            solvable_by_position_svg(&heatmap, &all_positions),
+        )];
+        if args.solve {
+            charts.push(("solution-lengths.svg", length_histogram_svg(&lengths)));
+        }
+        for (name, svg) in charts {
+            let path = std::path::Path::new(dir).join(name);
+            if let Err(e) = std::fs::write(&path, svg) {
+                eprintln!("can't write {}: {e}", path.display());
+                std::process::exit(1);
             }
         }
     }
+    if let Some(path) = &args.write_db {
+        let bytes = SolvableLayouts::new(database).to_bytes();
+        let written = FileWriter::create(path)
+            .and_then(|mut out| out.write_all(&bytes).and_then(|()| out.finish()));
+        if let Err(e) = written {
+            eprintln!("can't write {path}: {e}");
+            std::process::exit(1);
+        }
+    }
+    if let Some(url) = &args.webhook {
+        let status = if disagreements > 0 {
+            "failed"
+        } else {
+            "finished"
+        };
+        let mut summary = vec![
+            ("status", status.into()),
+            ("command", "enumerate".into()),
+            ("summary", summary.into()),
+            ("beatable", beatable.into()),
+            ("not_beatable", not_beatable.into()),
+            ("invalid", invalid.into()),
+            ("indeterminate", indeterminate.into()),
+            ("elapsed", Json::Number(start.elapsed().as_secs_f64())),
+        ];
+        if args.cross_check {
+            summary.push(("disagreements", disagreements.into()));
+        }
+        notify(url, Json::object(summary));
+    }
+    if disagreements > 0 {
+        std::process::exit(1);
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct RoomAndPos {
-    rooms: [Room; 9],
-    pos_tile: u8,
-    pos_direction: Direction,
+fn run_extreme(args: &Args) {
+    let start = Instant::now();
+    let layouts = layouts(args);
+    let hardest = hardest_layout(layouts, &args.config, |rooms, solution| {
+        eprintln!(
+            "{} operations: {}",
+            solution.operations.len(),
+            layout_notation(rooms)
+        );
+    });
+    match &hardest {
+        Some((rooms, solution)) => {
+            print_rooms(rooms, args.color);
+            println!("code: {}", layout_code(rooms, &NO_ROTATIONS));
+            print_solution(solution);
+        }
+        None => println!("no beatable layout"),
+    }
+    if let Some(url) = &args.webhook {
+        let (code, length) = match &hardest {
+            Some((rooms, solution)) => (
+                layout_code(rooms, &NO_ROTATIONS).into(),
+                solution.operations.len().into(),
+            ),
+            None => (Json::Null, Json::Null),
+        };
+        notify(
+            url,
+            Json::object([
+                ("status", "finished".into()),
+                ("command", "extreme".into()),
+                ("code", code),
+                ("operations", length),
+                ("elapsed", Json::Number(start.elapsed().as_secs_f64())),
+            ]),
+        );
+    }
 }
 
-#[derive(Debug, Sequence)]
-pub enum Operations {
-    Reach(ControlPanel),
-    Move(Direction),
+fn run_verify_corpus(args: &Args) {
+    let mut checked = 0;
+    let mut failed = 0;
+    for entry in corpus::iter() {
+        checked += 1;
+        if let Err(e) = entry.check(&args.config) {
+            println!(
+                "{}: {e}, expected {:?}",
+                layout_notation(&entry.rooms),
+                entry.expected
+            );
+            failed += 1;
+        }
+    }
+    println!("{checked} layouts, {failed} failed");
+    if failed > 0 {
+        std::process::exit(1);
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Sequence, Hash)]
-pub enum Entrance {
-    StartDown,
-    StartRight,
-    SkyviewLeft,
-    SkyviewUp,
-    EarthTempleRight,
-    EarthTempleDown,
-    LanayruMiningFacilityDown,
-    LanayruMiningFacilityUp,
-    MiniBossLeft,
-    MiniBossDown,
-    AncientCisternRight,
-    AncientCisternDown,
-    FireSanctuaryLeft,
-    FireSanctuaryRight,
-    SandshipLeft,
+fn run_random(args: &mut Args) {
+    let (rooms, rotations) = match args.python_seed {
+        Some(seed) => generate(args, &mut PythonRandom::new(seed)),
+        None => {
+            let mut rng = seeded_rng(args);
+            generate(args, &mut rng)
+        }
+    };
+    check_layout(args, rooms, rotations);
 }
 
-impl Entrance {
-    pub fn from_room_direction(room: Room, direction: Direction) -> Option<Self> {
-        use Entrance::*;
-        Some(match (room, direction) {
-            (Room::Start, Direction::Down) => StartDown,
-            (Room::Start, Direction::Right) => StartRight,
-            (Room::Skyview, Direction::Up) => SkyviewUp,
-            (Room::Skyview, Direction::Left) => SkyviewLeft,
-            (Room::EarthTemple, Direction::Down) => EarthTempleDown,
-            (Room::EarthTemple, Direction::Right) => EarthTempleRight,
-            (Room::LanayruMiningFacility, Direction::Up) => LanayruMiningFacilityUp,
-            (Room::LanayruMiningFacility, Direction::Down) => LanayruMiningFacilityDown,
-            (Room::MiniBoss, Direction::Left) => MiniBossLeft,
-            (Room::MiniBoss, Direction::Down) => MiniBossDown,
-            (Room::AncientCistern, Direction::Down) => AncientCisternDown,
-            (Room::AncientCistern, Direction::Right) => AncientCisternRight,
-            (Room::FireSanctuary, Direction::Left) => FireSanctuaryLeft,
-            (Room::FireSanctuary, Direction::Right) => FireSanctuaryRight,
-            (Room::Sandship, Direction::Left) => SandshipLeft,
-            _ => return None,
-        })
+/// seeded with --seed, or randomly without it
+fn seeded_rng(args: &Args) -> rand_pcg::Pcg64 {
+    match args.seed {
+        Some(seed) => rand_pcg::Pcg64::seed_from_u64(seed),
+        None => rand_pcg::Pcg64::from_entropy(),
     }
+}
 
-    pub fn traverse_room(&self, gates: OpenedGates) -> Option<Entrance> {
-        use Entrance::*;
-        match self {
-            Entrance::StartDown => Some(StartRight),
-            Entrance::StartRight => gates.contains(OpenedGates::STARTING).then_some(StartDown),
-            Entrance::SkyviewLeft => Some(SkyviewUp),
-            Entrance::SkyviewUp => Some(SkyviewLeft),
-            Entrance::EarthTempleRight => gates
-                .contains(OpenedGates::EARTH_TEMPLE)
-                .then_some(EarthTempleDown),
-            Entrance::EarthTempleDown => Some(EarthTempleRight),
-            Entrance::LanayruMiningFacilityDown => Some(LanayruMiningFacilityUp),
-            Entrance::LanayruMiningFacilityUp => Some(LanayruMiningFacilityDown),
-            Entrance::MiniBossLeft => gates
-                .contains(OpenedGates::MINI_BOSS)
-                .then_some(MiniBossDown),
-            Entrance::MiniBossDown => Some(MiniBossLeft),
-            Entrance::AncientCisternRight => Some(AncientCisternDown),
-            Entrance::AncientCisternDown => Some(AncientCisternRight),
-            Entrance::FireSanctuaryLeft => gates
-                .contains(OpenedGates::FIRE_SANCTUARY)
-                .then_some(FireSanctuaryRight),
-            Entrance::FireSanctuaryRight => Some(FireSanctuaryLeft),
-            Entrance::SandshipLeft => None,
-        }
-    }
-
-    pub fn to_room_direction(&self) -> (Room, Direction) {
-        use Entrance::*;
-        match self {
-            StartDown => (Room::Start, Direction::Down),
-            StartRight => (Room::Start, Direction::Right),
-            SkyviewUp => (Room::Skyview, Direction::Up),
-            SkyviewLeft => (Room::Skyview, Direction::Left),
-            EarthTempleDown => (Room::EarthTemple, Direction::Down),
-            EarthTempleRight => (Room::EarthTemple, Direction::Right),
-            LanayruMiningFacilityUp => (Room::LanayruMiningFacility, Direction::Up),
-            LanayruMiningFacilityDown => (Room::LanayruMiningFacility, Direction::Down),
-            MiniBossLeft => (Room::MiniBoss, Direction::Left),
-            MiniBossDown => (Room::MiniBoss, Direction::Down),
-            AncientCisternDown => (Room::AncientCistern, Direction::Down),
-            AncientCisternRight => (Room::AncientCistern, Direction::Right),
-            FireSanctuaryLeft => (Room::FireSanctuary, Direction::Left),
-            FireSanctuaryRight => (Room::FireSanctuary, Direction::Right),
-            SandshipLeft => (Room::Sandship, Direction::Left),
-        }
-    }
-
-    pub fn has_control_panel(&self) -> bool {
-        use Entrance::*;
-        matches!(
-            self,
-            StartRight | LanayruMiningFacilityDown | EarthTempleDown | MiniBossLeft
-        )
-    }
-
-    pub fn open_gate(&self) -> Option<OpenedGates> {
-        match self {
-            Entrance::StartDown => Some(OpenedGates::STARTING),
-            Entrance::EarthTempleDown => Some(OpenedGates::EARTH_TEMPLE),
-            Entrance::MiniBossDown => Some(OpenedGates::MINI_BOSS),
-            Entrance::FireSanctuaryRight => Some(OpenedGates::FIRE_SANCTUARY),
-            _ => None,
+/// a random layout, or one in the --difficulty range. With --random-entry
+/// the entry point in `args` is replaced by a random one, a door of the
+/// layout or for --difficulty any edge, the swaps then look for a layout
+/// that's beatable from there. --random-gates replaces the initial gates the
+/// same way, --shuffle-doors moves the doors before the layout is picked
+fn generate(args: &mut Args, rng: &mut impl LayoutRng) -> ([Room; 9], [Rotation; 9]) {
+    let rotatable = args.config.rotatable_rooms;
+    if args.shuffle_doors {
+        shuffle_doors(rng, &mut args.config.door_rules);
+    }
+    if let Some(path) = &args.layout_db {
+        let layouts = compress::read_file(path)
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| SolvableLayouts::parse(&bytes))
+            .unwrap_or_else(|e| {
+                eprintln!("can't read {path}: {e}");
+                std::process::exit(1);
+            });
+        let Some(rooms) = sample_solvable(rng, &layouts) else {
+            eprintln!("no beatable layouts in {path}");
+            std::process::exit(1);
+        };
+        return (rooms, NO_ROTATIONS);
+    }
+    if args.solvable_only || !args.constraints.is_empty() {
+        let sample = random_constrained_layout(
+            rng,
+            args.empty_tiles,
+            rotatable,
+            &args.constraints,
+            &args.config,
+            args.steps,
+        );
+        let Some(sample) = sample else {
+            eprintln!("no beatable layout in {} tries", args.steps);
+            std::process::exit(1);
+        };
+        eprintln!("beatable layout found after {} tries", sample.tries);
+        return (sample.rooms, sample.rotations);
+    }
+    let Some(lengths) = &args.difficulty else {
+        let (rooms, rotations) = random_layout(rng, args.empty_tiles, rotatable);
+        if args.random_entry {
+            if let Some(entry) =
+                random_entry_point(rng, &rooms, &rotations, &args.config.door_rules)
+            {
+                args.config.entry = entry;
+            }
+        }
+        if args.random_gates {
+            args.config.initial_gates = random_gates(rng);
         }
+        return (rooms, rotations);
+    };
+    if args.random_entry {
+        args.config.entry = random_edge(rng);
     }
-}
-
-fn main() {
-    let mut rng = rand_pcg::Pcg64::from_entropy();
-    let mut rooms = [
-        Room::Start,
-        Room::Skyview,
-        Room::EarthTemple,
-        Room::LanayruMiningFacility,
-        Room::MiniBoss,
-        Room::AncientCistern,
-        Room::FireSanctuary,
-        Room::Sandship,
-        Room::Empty,
-    ];
-    rooms.shuffle(&mut rng);
-
-    print_rooms(&rooms);
-    match verify_rooms(&rooms) {
-        Ok(()) => {
-            println!("beatable: {rooms:?}");
+    if args.random_gates {
+        args.config.initial_gates = random_gates(rng);
+    }
+    let guided = guided_layout(
+        rng,
+        args.empty_tiles,
+        rotatable,
+        lengths.clone(),
+        &args.config,
+        args.steps,
+    );
+    match guided {
+        Some(layout) => {
+            eprintln!(
+                "shortest solution has {} operations, found after {} swaps",
+                layout.solution.operations.len(),
+                layout.steps
+            );
+            (layout.rooms, layout.rotations)
         }
-        Err(e) => {
-            println!("not beatable ({e}): {rooms:?}");
+        None => {
+            eprintln!(
+                "no layout with a shortest solution of {}-{} operations found in {} swaps",
+                lengths.start(),
+                lengths.end(),
+                args.steps
+            );
+            std::process::exit(1);
         }
     }
 }
 
-fn verify_rooms(rooms: &[Room; 9]) -> Result<(), &'static str> {
-    // print_rooms(rooms);
-    // check that we can enter at all
-    let Some(_) = Entrance::from_room_direction(rooms[7], Direction::Down) else {
-        return Err("no down first room");
+fn check_partial(args: &Args, partial: &PartialLayout) {
+    for row in partial.chunks_exact(3) {
+        let row: Vec<String> = row
+            .iter()
+            .map(|room| format!("{:<3}", room.map_or("?", |room| room.code())))
+            .collect();
+        println!("{}", row.join(" "));
+    }
+    let rotations = args.layout.map_or(NO_ROTATIONS, |(_, rotations)| rotations);
+    if args.scout {
+        let ranking = scout_ranking(partial, &rotations, &args.config).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            std::process::exit(2);
+        });
+        println!("tiles to look at first:");
+        for value in ranking {
+            println!("  tile {}: {:.3} bits", value.tile, value.information_gain);
+        }
+        return;
+    }
+    if args.probability {
+        let fraction = match args.samples {
+            Some(samples) => sample_solvable_fraction(
+                partial,
+                &rotations,
+                &args.config,
+                &mut seeded_rng(args),
+                samples,
+            ),
+            None => solvable_fraction(partial, &rotations, &args.config),
+        };
+        let fraction = fraction.unwrap_or_else(|e| {
+            eprintln!("{e}");
+            std::process::exit(2);
+        });
+        match fraction.fraction() {
+            Some(share) => println!(
+                "{:.1}% of the completions are beatable ({} of {}, {} indeterminate)",
+                share * 100.0,
+                fraction.beatable,
+                fraction.checked,
+                fraction.indeterminate
+            ),
+            None => println!("no completion could be decided"),
+        }
+        return;
+    }
+    let check = match check_partial_layout(partial, &rotations, &args.config) {
+        Ok(check) => check,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }
     };
-    // we need to find any control panel
-    let Some((panel_dir, panel_tile)) = follow_chain(
-        rooms,
-        OpenedGates::empty(),
-        7,
-        Direction::Down,
-        &mut |entrance, tile| {
-            entrance
-                .has_control_panel()
-                .then_some((entrance.to_room_direction().1, tile))
-        },
-    ) else {
-        return Err("no control panel");
+    let solvability = match check.solvability {
+        Solvability::Always => "beatable however the unknown tiles are filled in",
+        Solvability::Sometimes => "beatable for some ways to fill in the unknown tiles",
+        Solvability::Never => "not beatable however the unknown tiles are filled in",
+        Solvability::Undecided => "undecided, some searches were cut short",
     };
+    println!("{solvability}");
+    println!(
+        "checked {} completions: {} beatable, {} indeterminate",
+        check.checked, check.beatable, check.indeterminate
+    );
+}
 
-    let mut state_to_gate: HashMap<RoomAndPos, OpenedGates> = HashMap::new();
-
-    // let mut counter: usize = 0;
-    // let mut max_depth = 0;
-    let mut unreachable_entrances: HashSet<Entrance> = enum_iterator::all::<Entrance>().collect();
-    let mut stash: Vec<(RoomAndPos, Operations)> = Vec::new();
+/// verifies or solves the layout and prints the result
+fn check_layout(args: &Args, rooms: [Room; 9], rotations: [Rotation; 9]) {
+    let config = &args.config;
+    let code = layout_code(&rooms, &rotations);
+    if let Some(path) = &args.qr {
+        let svg = QrCode::encode(code.as_bytes())
+            .expect("layout codes are short")
+            .to_svg();
+        if let Err(e) = std::fs::write(path, svg) {
+            eprintln!("can't write {path}: {e}");
+            std::process::exit(1);
+        }
+    }
+    if args.reachability {
+        match ReachabilityMatrix::new(&rooms, &rotations, config) {
+            Ok(matrix) if args.format == Format::Csv => print!("{}", matrix.to_csv()),
+            Ok(matrix) => {
+                print_rooms(&rooms, args.color);
+                print!("{matrix}");
+            }
+            Err(verdict) => {
+                eprintln!("{}", verdict_text(&verdict));
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if args.state_graph {
+        let graph = state_graph(&rooms, &rotations, config);
+        if let Some(verdict) = &graph.incomplete {
+            eprintln!("{}", verdict_text(verdict));
+            std::process::exit(1);
+        }
+        println!(
+            "{} states, {} edges in {} strongly connected components, the largest {}",
+            graph.states,
+            graph.edges,
+            graph.components.len(),
+            graph.components.first().copied().unwrap_or(0)
+        );
+        println!(
+            "{} irreversible transitions, {} of them open a gate",
+            graph.irreversible, graph.irreversible_gate_openings
+        );
+        println!("{} components can't be left once entered", graph.sinks);
+        if args.stats {
+            println!("{}", graph.stats);
+        }
+        return;
+    }
+    if args.state_space {
+        run_analyze_state_space(args, &rooms, &rotations);
+        return;
+    }
+    if args.format == Format::Csv {
+        let (verdict, min_length, stats) = if args.solve {
+            let result = solve_rotated_rooms(&rooms, &rotations, config);
+            let min_length = result.solution.map(|s| s.operations.len());
+            (result.verdict, min_length, result.stats)
+        } else {
+            let verification = verify_rotated_rooms(&rooms, &rotations, config);
+            (verification.verdict, None, verification.stats)
+        };
+        println!("{CSV_HEADER}");
+        println!("{}", csv_row(&rooms, &verdict, min_length, &stats));
+        return;
+    }
 
-    let mut current_pos_room = RoomAndPos {
-        pos_tile: panel_tile,
-        pos_direction: panel_dir,
-        rooms: *rooms,
+    let board = Board {
+        rooms: &rooms,
+        rotations: &rotations,
+        gates: config.initial_gates,
+        doors: &config.door_rules,
+        player: None,
     };
-
-    let mut current_operation: Operations = Operations::first().unwrap();
-    let mut current_gates = OpenedGates::empty();
-    let beatable = 'main_loop: loop {
-        // max_depth = max_depth.max(stash.len());
-        // counter += 1;
-        // if (counter % 10000) == 0 {
-        //     println!("{counter}, {}", state_to_gate.len());
-        //     print_rooms(&current_pos_room.rooms);
-        // }
-        // perform operation
-        let op_result = match current_operation {
-            Operations::Reach(panel) => {
-                let panel_entrance = panel.entrance();
-                if let Some(panel_tile) = follow_chain_both(
-                    &current_pos_room.rooms,
-                    current_gates,
-                    current_pos_room.pos_tile,
-                    current_pos_room.pos_direction,
-                    &mut |entrance, tile| (panel_entrance == entrance).then_some(tile),
-                ) {
-                    Ok(RoomAndPos {
-                        rooms: current_pos_room.rooms,
-                        pos_direction: panel_entrance.to_room_direction().1,
-                        pos_tile: panel_tile,
-                    })
-                } else {
-                    Err(())
+    if let Some(path) = &args.svg {
+        if let Err(e) = std::fs::write(path, SvgRenderer.render(&board)) {
+            eprintln!("can't write {path}: {e}");
+            std::process::exit(1);
+        }
+    }
+    let renderer: Box<dyn BoardRenderer> = if args.box_drawing {
+        Box::new(UnicodeRenderer)
+    } else {
+        Box::new(AsciiRenderer { color: args.color })
+    };
+    std::io::stdout()
+        .write_all(&renderer.render(&board))
+        .expect("stdout is writable");
+    if rotations != NO_ROTATIONS {
+        println!("rotations: {rotations:?}");
+    }
+    let moved_doors = config.door_rules.moved_doors();
+    if !moved_doors.is_empty() {
+        let moved: Vec<String> = moved_doors
+            .iter()
+            .map(|(entrance, direction)| format!("{entrance:?}:{direction:?}"))
+            .collect();
+        println!("doors: {}", moved.join(" "));
+    }
+    println!("code: {code}");
+    if args.patch {
+        match patch_bytes(&rooms, &args.room_ids) {
+            Ok(bytes) => println!("patch: {}", patch_hex(&bytes)),
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+        if let Some(stages) = &args.stages {
+            for (tile, room) in rooms.iter().enumerate() {
+                match stages.get(*room) {
+                    Some(id) => println!("  tile {tile}: {id}"),
+                    None if *room == Room::Empty => println!("  tile {tile}: empty"),
+                    None => println!("  tile {tile}: no stage for {}", room.name()),
                 }
             }
-            Operations::Move(direction) => {
-                // if we move up into the empty space, we swap with the tile that is down
-                let empty_tile = current_pos_room
-                    .rooms
-                    .iter()
-                    .position(|r| r == &Room::Empty)
-                    .unwrap() as u8;
-                if let Some((other_tile, _)) = do_move(empty_tile, direction) {
-                    if other_tile != current_pos_room.pos_tile {
-                        let mut rooms = current_pos_room.rooms;
-                        rooms.swap(other_tile.into(), empty_tile.into());
-                        Ok(RoomAndPos {
-                            rooms,
-                            pos_tile: current_pos_room.pos_tile,
-                            pos_direction: current_pos_room.pos_direction,
-                        })
+        }
+    }
+    if config.entry != EntryPoint::default() {
+        println!(
+            "entering at the {:?} door of tile {}",
+            config.entry.direction, config.entry.tile
+        );
+    }
+    if !config.initial_gates.is_empty() {
+        println!("open from the start: {:?}", config.initial_gates);
+    }
+    if args.all_entries {
+        for (entry, verification) in verify_entry_points(&rooms, &rotations, config) {
+            println!(
+                "entering at the {:?} door of tile {}: {}",
+                entry.direction,
+                entry.tile,
+                verdict_text(&verification.verdict)
+            );
+        }
+        return;
+    }
+    let (verdict, stats) = if let Some(max_length) = args.count_paths {
+        let counts = count_solutions(&rooms, &rotations, config, max_length);
+        let lengths: Vec<String> = counts
+            .by_length
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(length, count)| format!("{length}: {count}"))
+            .collect();
+        println!(
+            "{} solutions with at most {max_length} operations",
+            counts.total()
+        );
+        if !lengths.is_empty() {
+            println!("by length: {}", lengths.join(", "));
+        }
+        (counts.verdict, counts.stats)
+    } else if let (true, Some(max)) = (args.solve, args.all_optimal) {
+        let all = all_optimal_solutions(&rooms, &rotations, config, max);
+        if let Some(first) = all.solutions.first() {
+            println!(
+                "{}{} shortest solution{} in {} operations:",
+                if all.truncated { "first " } else { "" },
+                all.solutions.len(),
+                if all.solutions.len() == 1 { "" } else { "s" },
+                first.operations.len()
+            );
+        }
+        for solution in &all.solutions {
+            let operations: Vec<String> = solution
+                .operations
+                .iter()
+                .map(|operation| format!("{operation:?}"))
+                .collect();
+            println!(
+                "  from {:?}: {}",
+                solution.start_panel,
+                operations.join(", ")
+            );
+        }
+        (all.verdict, all.stats)
+    } else if args.solve {
+        let SolveResult {
+            verdict,
+            solution,
+            stats,
+        } = match (&args.search_tree, &args.graphml) {
+            (Some(path), _) | (None, Some(path)) => {
+                let exported = FileWriter::create(path).and_then(|mut out| {
+                    let result = if args.graphml.is_some() {
+                        export_search_graphml(&rooms, &rotations, config, &mut out)?
                     } else {
-                        Err(())
-                    }
-                } else {
-                    Err(())
-                }
+                        export_search_tree(&rooms, &rotations, config, &mut out)?
+                    };
+                    out.finish()?;
+                    Ok(result)
+                });
+                exported.unwrap_or_else(|e| {
+                    eprintln!("can't write {path}: {e}");
+                    std::process::exit(1);
+                })
             }
+            _ if args.fastest => solve_weighted(&rooms, &rotations, config, &args.costs),
+            _ => solve_rotated_rooms(&rooms, &rotations, config),
         };
-        match op_result {
-            // operation could be performed, see if this is a new state or if we can reach more gates now
-            Ok(new_room_pos) => {
-                // try to open gates and reach entrances
-                follow_chain_both::<()>(
-                    &new_room_pos.rooms,
-                    current_gates,
-                    new_room_pos.pos_tile,
-                    new_room_pos.pos_direction,
-                    &mut |e, _| {
-                        if let Some(gate) = e.open_gate() {
-                            current_gates |= gate;
-                        }
-                        unreachable_entrances.remove(&e);
-                        None
-                    },
-                );
-                if unreachable_entrances.is_empty() {
-                    break true;
-                }
-                match state_to_gate.entry(new_room_pos.clone()) {
-                    Entry::Occupied(mut occupied) => {
-                        if occupied.get().contains(current_gates) {
-                            // we already found this state, with better gates
-                            // copied from err segment
-                            if let Some(nex_op) = current_operation.next() {
-                                current_operation = nex_op;
-                                continue 'main_loop;
-                            } else {
-                                while let Some((stack_room_pos, stack_op)) = stash.pop() {
-                                    if let Some(next_op) = stack_op.next() {
-                                        current_pos_room = stack_room_pos;
-                                        current_operation = next_op;
-                                        current_gates = state_to_gate
-                                            .get(&current_pos_room)
-                                            .cloned()
-                                            .unwrap_or(OpenedGates::empty());
-                                        continue 'main_loop;
-                                    }
-                                }
-                                // we have reached the end of the stack
-                                break false;
-                            }
-                        } else {
-                            // we have better gates now, continue
-                            occupied.insert(current_gates);
-                        }
-                    }
-                    Entry::Vacant(vacant) => {
-                        vacant.insert(current_gates);
-                    }
-                }
-                // this is now our new state, push the current one to the stack and restart operation
-                stash.push((new_room_pos.clone(), current_operation));
-                current_operation = Operations::first().unwrap();
-                current_pos_room = new_room_pos;
-            }
-            // operation couldn't be performed, try the next one
-            // if there isn't one, pop one from the stack
-            // if there isn't one, we're done
-            Err(()) => {
-                if let Some(nex_op) = current_operation.next() {
-                    current_operation = nex_op;
-                    continue 'main_loop;
-                } else {
-                    while let Some((stack_room_pos, stack_op)) = stash.pop() {
-                        if let Some(next_op) = stack_op.next() {
-                            current_pos_room = stack_room_pos;
-                            current_operation = next_op;
-                            current_gates = state_to_gate
-                                .get(&current_pos_room)
-                                .cloned()
-                                .unwrap_or(OpenedGates::empty());
-                            continue 'main_loop;
-                        }
-                    }
-                    // we have reached the end of the stack
-                    break false;
+        if let Some(solution) = solution {
+            // dropping a walk to a panel can make the rest slower
+            let solution = if args.fastest {
+                solution
+            } else {
+                solution.minimize(&rooms, &rotations, config)
+            };
+            print_solution(&solution);
+            if args.estimate_time {
+                println!("estimated time: {}", args.costs.route_time(&solution));
+            }
+            let steps = route_steps(&rooms, &rotations, &solution, config)
+                .expect("the solver's solutions can be played");
+            let mut exports = Vec::new();
+            if let Some(path) = &args.notes {
+                exports.push((
+                    path,
+                    livesplit_notes(solution.start_panel, &steps, &args.locale),
+                ));
+            }
+            if let Some(path) = &args.tas {
+                let script = tas_script(&rooms, &rotations, solution.start_panel, &steps);
+                exports.push((path, format!("{script}\n")));
+            }
+            for (path, contents) in exports {
+                if let Err(e) = std::fs::write(path, contents) {
+                    eprintln!("can't write {path}: {e}");
+                    std::process::exit(1);
                 }
             }
         }
+        (verdict, stats)
+    } else {
+        let Verification { verdict, stats } = verify_rotated_rooms(&rooms, &rotations, config);
+        (verdict, stats)
     };
+    println!("{}: {rooms:?}", verdict_text(&verdict));
+    if let Some(route) = &args.route {
+        if route.is_valid(&rooms, &rotations, config) {
+            println!(
+                "route solves the layout in {} operations",
+                route.operations.len()
+            );
+        } else {
+            println!("route doesn't solve the layout");
+        }
+    }
+    if let Some(max_swaps) = args.repair.filter(|_| verdict != Verdict::Beatable) {
+        match nearest_beatable(&rooms, &rotations, config, max_swaps) {
+            Some(repair) => {
+                let swaps: Vec<String> = repair
+                    .swaps
+                    .iter()
+                    .map(|(a, b)| format!("{a}<->{b}"))
+                    .collect();
+                println!("beatable after swapping tiles {}:", swaps.join(", "));
+                print_rooms(&repair.rooms, args.color);
+                println!("code: {}", layout_code(&repair.rooms, &repair.rotations));
+            }
+            None => println!("no beatable layout within {max_swaps} swaps"),
+        }
+    }
+    // running out of budget is always worth knowing the numbers for
+    if args.stats || matches!(verdict, Verdict::Indeterminate(_)) {
+        println!("{stats}");
+    }
+}
 
-    // let beatable = verify_rec(&mut state_to_gate, pos_room, gates, &mut counter, &mut unreachable_entrances);
-
-    // println!("count: {counter}");
-    // println!("depth: {max_depth}");
-    // println!("beatable: {}", unreachable_entrances.is_empty());
-
-    if beatable {
-        Ok(())
-    } else {
-        Err("unreachable entrances")
+fn verdict_text(verdict: &Verdict) -> String {
+    match verdict {
+        Verdict::Beatable => "beatable".to_string(),
+        Verdict::NotBeatable(e) => format!("not beatable ({e})"),
+        Verdict::ChainCycle(cycle) => format!("invalid layout ({cycle})"),
+        Verdict::Indeterminate(e) => format!("indeterminate ({e})"),
     }
 }
 
-fn follow_chain_both<T>(
+const CSV_HEADER: &str = "notation,solvable,reason,min_length,nodes_expanded";
+
+/// solvable is left empty if the search was cut short, min_length if no
+/// shortest solution was searched for
+fn csv_row(
     rooms: &[Room; 9],
-    gates: OpenedGates,
-    tile: u8,
-    direction: Direction,
-    check: &mut impl FnMut(Entrance, u8) -> Option<T>,
-) -> Option<T> {
-    follow_chain(rooms, gates, tile, direction, check).or_else(|| {
-        if let Some((tile, direction)) = do_move(tile, direction) {
-            follow_chain(rooms, gates, tile, direction, check)
-        } else {
-            None
-        }
-    })
+    verdict: &Verdict,
+    min_length: Option<usize>,
+    stats: &SearchStats,
+) -> String {
+    let (solvable, reason) = match verdict {
+        Verdict::Beatable => ("true", String::new()),
+        Verdict::NotBeatable(e) => ("false", e.to_string()),
+        Verdict::ChainCycle(cycle) => ("false", cycle.to_string()),
+        Verdict::Indeterminate(e) => ("", e.to_string()),
+    };
+    let min_length = min_length.map(|len| len.to_string()).unwrap_or_default();
+    format!(
+        "{},{solvable},{},{min_length},{}",
+        csv_field(&layout_notation(rooms)),
+        csv_field(&reason),
+        stats.nodes_expanded
+    )
 }
 
-fn follow_chain<T>(
-    rooms: &[Room; 9],
-    gates: OpenedGates,
-    mut tile: u8,
-    mut direction: Direction,
-    check: &mut impl FnMut(Entrance, u8) -> Option<T>,
-) -> Option<T> {
-    loop {
-        let Some(pos) = Entrance::from_room_direction(rooms[tile as usize], direction) else {
-            return None;
-        };
-        if let Some(val) = check(pos, tile) {
-            return Some(val);
-        }
-        let Some(pos) = pos.traverse_room(gates) else {
-            return None;
-        };
-        if let Some(val) = check(pos, tile) {
-            return Some(val);
-        }
-        direction = pos.to_room_direction().1;
-        if let Some((new_tile, new_dir)) = do_move(tile, direction) {
-            tile = new_tile;
-            direction = new_dir;
-        } else {
-            return None;
-        };
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
     }
 }
 
-fn print_rooms(rooms: &[Room; 9]) {
-    fn room_str(r: Room) -> &'static str {
-        match r {
-            Room::Start => "STR",
-            Room::Skyview => "SV ",
-            Room::EarthTemple => "ET ",
-            Room::LanayruMiningFacility => "LMF",
-            Room::MiniBoss => "BOS",
-            Room::AncientCistern => "AC ",
-            Room::FireSanctuary => "FS ",
-            Room::Sandship => "SSH",
-            Room::Empty => "   ",
-        }
-    }
-    for chunk in rooms.chunks_exact(3) {
-        for r in chunk {
-            print!("{} ", room_str(*r));
-        }
-        println!();
+fn print_solution(solution: &Solution) {
+    println!(
+        "solution in {} operations, starting at the {:?} panel:",
+        solution.operations.len(),
+        solution.start_panel
+    );
+    for operation in &solution.operations {
+        println!("  {operation:?}");
     }
+    println!("notation: {solution}");
+    let panels_used = solution
+        .panels_used()
+        .iter()
+        .map(|(panel, count)| format!("{panel:?} x{count}"))
+        .collect::<Vec<_>>();
+    println!("panels used: {}", panels_used.join(", "));
+}
+
+fn print_rooms(rooms: &[Room; 9], color: bool) {
+    print!("{}", ascii_board(rooms, None, color));
 }
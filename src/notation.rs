@@ -0,0 +1,122 @@
+//! A short text notation for operations, so solutions can be typed, stored
+//! and replayed as plain strings. `U`, `L`, `D` and `R` slide the room on
+//! that side of the empty tile into it, `M:ET` walks to the panel in a room
+//! by its code and `T:U` turns the room above the empty tile. A `2` after
+//! the direction, like `U2` or `T:L2`, uses the second empty tile instead
+//! of the first. Case doesn't matter.
+//!
+//! A whole [`Solution`] starts with the code of the room of its start panel
+//! and a colon, like `STR: U L M:ET R D`. That's also how solutions are
+//! displayed, upper case with single spaces, so the same route always reads
+//! the same.
+
+use std::{fmt, str::FromStr};
+
+use crate::{
+    puzzle_file::parse_room,
+    rooms::{ControlPanel, Direction, Room},
+    solve::Solution,
+    verify::{EmptySlot, Operations},
+};
+
+impl fmt::Display for Operations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (prefix, slot, direction) = match self {
+            Operations::Reach(panel) => return write!(f, "M:{}", panel.room().code()),
+            Operations::Move(slot, direction) => ("", slot, direction),
+            Operations::Rotate(slot, direction) => ("T:", slot, direction),
+        };
+        let letter = match direction {
+            Direction::Up => 'U',
+            Direction::Left => 'L',
+            Direction::Down => 'D',
+            Direction::Right => 'R',
+        };
+        let slot = match slot {
+            EmptySlot::First => "",
+            EmptySlot::Second => "2",
+        };
+        write!(f, "{prefix}{letter}{slot}")
+    }
+}
+
+impl FromStr for Operations {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, String> {
+        let upper = text.trim().to_ascii_uppercase();
+        if let Some(code) = upper.strip_prefix("M:") {
+            return panel_in(parse_room(code)?).map(Operations::Reach);
+        }
+        let (turn, slide) = match upper.strip_prefix("T:") {
+            Some(slide) => (true, slide),
+            None => (false, upper.as_str()),
+        };
+        let (slot, direction) =
+            parse_slide(slide).ok_or_else(|| format!("unknown operation: {text}"))?;
+        Ok(if turn {
+            Operations::Rotate(slot, direction)
+        } else {
+            Operations::Move(slot, direction)
+        })
+    }
+}
+
+fn panel_in(room: Room) -> Result<ControlPanel, String> {
+    enum_iterator::all::<ControlPanel>()
+        .find(|panel| panel.room() == room)
+        .ok_or_else(|| format!("no panel in {}", room.name()))
+}
+
+/// a direction letter, optionally followed by `2` for the second empty tile
+fn parse_slide(text: &str) -> Option<(EmptySlot, Direction)> {
+    let mut chars = text.chars();
+    let direction = match chars.next()? {
+        'U' => Direction::Up,
+        'L' => Direction::Left,
+        'D' => Direction::Down,
+        'R' => Direction::Right,
+        _ => return None,
+    };
+    let slot = match chars.as_str() {
+        "" => EmptySlot::First,
+        "2" => EmptySlot::Second,
+        _ => return None,
+    };
+    Some((slot, direction))
+}
+
+/// A sequence of operations in the short notation, separated by whitespace
+/// or commas, like `M:ET U L M:LMF`.
+pub fn parse_operations(text: &str) -> Result<Vec<Operations>, String> {
+    text.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|operation| !operation.is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+impl fmt::Display for Solution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:", self.start_panel.room().code())?;
+        for operation in &self.operations {
+            write!(f, " {operation}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Solution {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let (start, operations) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let start = start
+            .strip_suffix(':')
+            .ok_or_else(|| format!("expected the start panel like STR:, got {start}"))?;
+        Ok(Solution {
+            start_panel: panel_in(parse_room(&start.to_ascii_uppercase())?)?,
+            operations: parse_operations(operations)?,
+        })
+    }
+}
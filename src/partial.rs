@@ -0,0 +1,268 @@
+#[cfg(feature = "gen")]
+use crate::generate::LayoutRng;
+use crate::{
+    enumerate::next_permutation,
+    puzzle_file::parse_room,
+    rooms::{Room, Rotation, ALL_ROOMS},
+    verify::{verify_rotated_rooms, SolverConfig, Verdict},
+};
+
+/// A board where only some tiles are known, like a tracker that has only
+/// seen some of the rooms. `None` tiles can hold any of the rooms that
+/// aren't on the board yet.
+pub type PartialLayout = [Option<Room>; 9];
+
+/// Reads a partial layout in the same notation as
+/// [`layout_notation`](crate::rooms::layout_notation), with `?` for unknown
+/// tiles, like `STR ? ET/? BOS ?/FS SSH -`.
+pub fn parse_partial_layout(notation: &str) -> Result<PartialLayout, String> {
+    let tiles: Vec<&str> = notation
+        .split('/')
+        .flat_map(|row| row.split_whitespace())
+        .collect();
+    if tiles.len() != 9 {
+        return Err(format!("expected 9 tiles, got {}", tiles.len()));
+    }
+    let mut layout = [None; 9];
+    for (tile, name) in layout.iter_mut().zip(tiles) {
+        if name != "?" {
+            *tile = Some(parse_room(name)?);
+        }
+    }
+    Ok(layout)
+}
+
+/// the rooms that aren't on the board yet, `Err` if a room is on it more
+/// often than in the game
+fn missing_rooms(partial: &PartialLayout) -> Result<Vec<Room>, String> {
+    let mut missing = ALL_ROOMS.to_vec();
+    for room in partial.iter().flatten() {
+        let Some(i) = missing.iter().position(|r| r == room) else {
+            return Err(format!("too many {room:?} rooms"));
+        };
+        missing.remove(i);
+    }
+    missing.sort_by_key(|r| *r as u8);
+    Ok(missing)
+}
+
+fn unknown_tiles(partial: &PartialLayout) -> Vec<usize> {
+    (0..9).filter(|tile| partial[*tile].is_none()).collect()
+}
+
+/// Every way to fill in the unknown tiles with the rooms that are left,
+/// without duplicates. `Err` if a room is on the board more often than in
+/// the game.
+pub fn completions(partial: &PartialLayout) -> Result<impl Iterator<Item = [Room; 9]>, String> {
+    let missing = missing_rooms(partial)?;
+    let unknown = unknown_tiles(partial);
+    let mut next = Some(missing);
+    let partial = *partial;
+    Ok(std::iter::from_fn(move || {
+        let current = next.take()?;
+        let mut rooms = partial.map(|room| room.unwrap_or(Room::Empty));
+        for (tile, room) in unknown.iter().zip(&current) {
+            rooms[*tile] = *room;
+        }
+        let mut following = current;
+        if next_permutation(&mut following) {
+            next = Some(following);
+        }
+        Some(rooms)
+    }))
+}
+
+/// Whether the puzzle is beatable however the unknown tiles turn out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Solvability {
+    /// every completion is beatable
+    Always,
+    /// some completions are beatable and some aren't
+    Sometimes,
+    /// no completion is beatable
+    Never,
+    /// some searches were cut short and the others all agree, so it's not
+    /// clear which of the others it is
+    Undecided,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartialCheck {
+    pub solvability: Solvability,
+    /// completions verified, this stops as soon as the answer is
+    /// [`Solvability::Sometimes`]
+    pub checked: usize,
+    pub beatable: usize,
+    pub indeterminate: usize,
+}
+
+/// Verifies completions of the partial layout until it's clear if all, some
+/// or none of them are beatable. The rotations of unknown tiles are used
+/// for whatever room ends up there.
+pub fn check_partial_layout(
+    partial: &PartialLayout,
+    rotations: &[Rotation; 9],
+    config: &SolverConfig,
+) -> Result<PartialCheck, String> {
+    let mut check = PartialCheck {
+        solvability: Solvability::Undecided,
+        checked: 0,
+        beatable: 0,
+        indeterminate: 0,
+    };
+    for rooms in completions(partial)? {
+        check.checked += 1;
+        match verify_rotated_rooms(&rooms, rotations, config).verdict {
+            Verdict::Beatable => check.beatable += 1,
+            Verdict::Indeterminate(_) => check.indeterminate += 1,
+            Verdict::NotBeatable(_) | Verdict::ChainCycle(_) => {}
+        }
+        let not_beatable = check.checked - check.beatable - check.indeterminate;
+        if check.beatable > 0 && not_beatable > 0 {
+            check.solvability = Solvability::Sometimes;
+            return Ok(check);
+        }
+    }
+    check.solvability = if check.indeterminate > 0 {
+        Solvability::Undecided
+    } else if check.beatable == check.checked {
+        Solvability::Always
+    } else {
+        Solvability::Never
+    };
+    Ok(check)
+}
+
+/// How many of the checked completions of a partial layout are beatable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SolvableFraction {
+    pub checked: usize,
+    pub beatable: usize,
+    /// completions whose search was cut short, they don't count either way
+    pub indeterminate: usize,
+}
+
+impl SolvableFraction {
+    fn add(&mut self, verdict: &Verdict) {
+        self.checked += 1;
+        match verdict {
+            Verdict::Beatable => self.beatable += 1,
+            Verdict::Indeterminate(_) => self.indeterminate += 1,
+            Verdict::NotBeatable(_) | Verdict::ChainCycle(_) => {}
+        }
+    }
+
+    /// the share of beatable completions among the decided ones, `None` if
+    /// none were decided
+    pub fn fraction(&self) -> Option<f64> {
+        let decided = self.checked - self.indeterminate;
+        (decided > 0).then(|| self.beatable as f64 / decided as f64)
+    }
+}
+
+/// Verifies every completion of the partial layout, see
+/// [`check_partial_layout`] for stopping early once the answer is clear.
+pub fn solvable_fraction(
+    partial: &PartialLayout,
+    rotations: &[Rotation; 9],
+    config: &SolverConfig,
+) -> Result<SolvableFraction, String> {
+    let mut fraction = SolvableFraction::default();
+    for rooms in completions(partial)? {
+        fraction.add(&verify_rotated_rooms(&rooms, rotations, config).verdict);
+    }
+    Ok(fraction)
+}
+
+/// Like [`solvable_fraction`] but only for `samples` random completions,
+/// which can repeat, for when there are too many to check them all.
+#[cfg(feature = "gen")]
+pub fn sample_solvable_fraction(
+    partial: &PartialLayout,
+    rotations: &[Rotation; 9],
+    config: &SolverConfig,
+    rng: &mut impl LayoutRng,
+    samples: usize,
+) -> Result<SolvableFraction, String> {
+    let mut missing = missing_rooms(partial)?;
+    let unknown = unknown_tiles(partial);
+    let mut fraction = SolvableFraction::default();
+    for _ in 0..samples {
+        rng.shuffle(&mut missing);
+        let mut rooms = partial.map(|room| room.unwrap_or(Room::Empty));
+        for (tile, room) in unknown.iter().zip(&missing) {
+            rooms[*tile] = *room;
+        }
+        fraction.add(&verify_rotated_rooms(&rooms, rotations, config).verdict);
+    }
+    Ok(fraction)
+}
+
+/// How much revealing an unknown tile is expected to tell about whether the
+/// layout is beatable, in bits.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoutValue {
+    pub tile: usize,
+    /// how much the uncertainty about being beatable goes down on average,
+    /// weighted by how many completions have each room on the tile
+    pub information_gain: f64,
+}
+
+/// Ranks the unknown tiles by how much looking at them would help to tell
+/// if the layout is beatable, most useful first. Every completion is
+/// verified once, the ones whose search is cut short are left out.
+pub fn scout_ranking(
+    partial: &PartialLayout,
+    rotations: &[Rotation; 9],
+    config: &SolverConfig,
+) -> Result<Vec<ScoutValue>, String> {
+    let unknown = unknown_tiles(partial);
+    // (beatable, decided) per tile and room
+    let mut counts = [[(0usize, 0usize); 9]; 9];
+    let mut total = (0, 0);
+    for rooms in completions(partial)? {
+        let beatable = match verify_rotated_rooms(&rooms, rotations, config).verdict {
+            Verdict::Beatable => 1,
+            Verdict::NotBeatable(_) | Verdict::ChainCycle(_) => 0,
+            Verdict::Indeterminate(_) => continue,
+        };
+        total.0 += beatable;
+        total.1 += 1;
+        for tile in &unknown {
+            let count = &mut counts[*tile][rooms[*tile] as usize];
+            count.0 += beatable;
+            count.1 += 1;
+        }
+    }
+    let uncertainty = entropy(total);
+    let mut ranking: Vec<ScoutValue> = unknown
+        .iter()
+        .map(|tile| {
+            let remaining: f64 = counts[*tile]
+                .iter()
+                .filter(|count| count.1 > 0)
+                .map(|count| count.1 as f64 / total.1 as f64 * entropy(*count))
+                .sum();
+            ScoutValue {
+                tile: *tile,
+                information_gain: uncertainty - remaining,
+            }
+        })
+        .collect();
+    // stable, so ties stay in board order
+    ranking.sort_by(|a, b| b.information_gain.total_cmp(&a.information_gain));
+    Ok(ranking)
+}
+
+/// binary entropy of being beatable, for (beatable, total) completions
+fn entropy((beatable, total): (usize, usize)) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    let p = beatable as f64 / total as f64;
+    [p, 1.0 - p]
+        .into_iter()
+        .filter(|p| *p > 0.0)
+        .map(|p| -p * p.log2())
+        .sum()
+}
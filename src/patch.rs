@@ -0,0 +1,64 @@
+//! The layout the way the randomizer patches it into the game and the game
+//! keeps it in memory: one byte per tile in board order, holding the game's
+//! number for the room on it. How the game numbers the rooms is a table of
+//! room ids, the room at index `i` is written as `i`. It's the order of
+//! [`Room`] unless the game is found to number them differently.
+
+use crate::{
+    puzzle_file::parse_room,
+    rooms::{Room, ALL_ROOMS},
+};
+
+/// the room each id stands for, by id
+pub type RoomIds = [Room; 9];
+
+/// ids in the order of [`Room`]
+pub const DEFAULT_ROOM_IDS: RoomIds = ALL_ROOMS;
+
+/// Reads a table of room ids like `STR SV ET ...`, every room once.
+pub fn parse_room_ids(text: &str) -> Result<RoomIds, String> {
+    let rooms = text
+        .split_whitespace()
+        .map(parse_room)
+        .collect::<Result<Vec<_>, _>>()?;
+    let ids: RoomIds = rooms
+        .try_into()
+        .map_err(|_| "room ids need 9 rooms".to_string())?;
+    if let Some(missing) = ALL_ROOMS.iter().find(|room| !ids.contains(room)) {
+        return Err(format!("room ids are missing {}", missing.name()));
+    }
+    Ok(ids)
+}
+
+/// The bytes patched into the game for the layout.
+pub fn patch_bytes(rooms: &[Room; 9], ids: &RoomIds) -> Result<[u8; 9], String> {
+    let mut bytes = [0; 9];
+    for (byte, room) in bytes.iter_mut().zip(rooms) {
+        let id = ids
+            .iter()
+            .position(|r| r == room)
+            .ok_or_else(|| format!("no room id for {}", room.name()))?;
+        *byte = id as u8;
+    }
+    Ok(bytes)
+}
+
+/// The layout the patched bytes stand for.
+pub fn rooms_from_patch(bytes: &[u8; 9], ids: &RoomIds) -> Result<[Room; 9], String> {
+    let mut rooms = ALL_ROOMS;
+    for (room, id) in rooms.iter_mut().zip(bytes) {
+        *room = *ids
+            .get(*id as usize)
+            .ok_or_else(|| format!("unknown room id {id}"))?;
+    }
+    Ok(rooms)
+}
+
+/// The bytes as hex, separated by spaces, like `00 01 02 04 06 05 08 03 07`.
+pub fn patch_hex(bytes: &[u8; 9]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
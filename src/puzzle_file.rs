@@ -0,0 +1,578 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    cost::CostModel,
+    heuristic::{EmptyDistance, Heuristic, UnopenedGates, UnreachedEntrances},
+    rooms::{
+        validate_rooms, ControlPanel, Direction, DoorAccess, Entrance, OpenedGates, Room, Rotation,
+        NO_ROTATIONS,
+    },
+    solve::GateOrder,
+    store::StateHasher,
+    verify::{
+        DeepeningGrowth, DeepeningSchedule, EntryPoint, Operations, RandomProbes, SearchStrategy,
+        SolverConfig, WinCondition,
+    },
+};
+
+/// A layout together with the options to check it with, read from a YAML
+/// file like this, everything but the layout is optional:
+///
+/// ```yaml
+/// layout:
+///   - [STR, SV, ET]
+///   - [LMF, BOS, AC]
+///   - [FS, SSH, "-"]
+/// rotations:
+///   - [0, 0, 90]
+///   - [0, 180, 0]
+///   - [0, 0, 270]
+/// entry:
+///   tile: 7
+///   direction: down
+/// initial_gates: [starting]
+/// markers: [SkyviewUp, FireSanctuaryLeft]
+/// solver:
+///   max_nodes: 1000000
+///   timeout: 2.5
+///   rotatable_rooms: true
+///   toggling_gates: [mini-boss]
+///   win_condition: all-panels
+///   strategy: iterative-deepening
+///   deepening: 10+5
+///   heuristic: unreached-entrances
+///   random_probes: 50:200
+///   one_way_doors:
+///     StartDown: enter-only
+///   door_directions:
+///     SkyviewUp: right
+/// ```
+///
+/// Only the subset of YAML needed for this is understood: nested mappings,
+/// block and flow lists and plain or quoted scalars.
+#[derive(Debug, Clone)]
+pub struct PuzzleFile {
+    pub rooms: [Room; 9],
+    pub rotations: [Rotation; 9],
+    pub config: SolverConfig,
+}
+
+impl PuzzleFile {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let lines = yaml_lines(text);
+        let mut index = 0;
+        let root = match lines.first() {
+            Some(line) => parse_block(&lines, &mut index, line.indent)?,
+            None => return Err("empty puzzle file".to_string()),
+        };
+        if let Some(line) = lines.get(index) {
+            return Err(format!("line {}: unexpected indentation", line.number));
+        }
+
+        let mut rooms = None;
+        let mut rotations = NO_ROTATIONS;
+        let mut config = SolverConfig::default();
+        for (key, value) in root.as_map("puzzle file")? {
+            match key.as_str() {
+                "layout" => rooms = Some(parse_board(value, "layout", parse_room)?),
+                "rotations" => rotations = parse_board(value, "rotations", parse_rotation)?,
+                "entry" => config.entry = parse_entry(value)?,
+                "initial_gates" => config.initial_gates = parse_gate_list(value)?,
+                "markers" => {
+                    for entrance in value.as_list(key)? {
+                        config
+                            .markers
+                            .insert(parse_entrance(entrance.as_scalar(key)?)?);
+                    }
+                }
+                "solver" => parse_solver(value, &mut config)?,
+                _ => return Err(format!("unknown key: {key}")),
+            }
+        }
+        let rooms = rooms.ok_or("missing layout")?;
+        validate_rooms(&rooms)?;
+        check_markers(&config)?;
+        Ok(PuzzleFile {
+            rooms,
+            rotations,
+            config,
+        })
+    }
+}
+
+/// a room by its code from the layout notation or its name
+pub fn parse_room(name: &str) -> Result<Room, String> {
+    enum_iterator::all::<Room>()
+        .find(|room| room.code() == name || format!("{room:?}").eq_ignore_ascii_case(name))
+        .ok_or_else(|| format!("unknown room: {name}"))
+}
+
+pub fn parse_entrance(name: &str) -> Result<Entrance, String> {
+    enum_iterator::all::<Entrance>()
+        .find(|entrance| format!("{entrance:?}").eq_ignore_ascii_case(name))
+        .ok_or_else(|| format!("unknown entrance: {name}"))
+}
+
+/// an operation like it's debug printed, `Move(First, Up)`, ignoring case
+/// and spaces, or in the [short notation](crate::notation) like `U`
+pub fn parse_operation(text: &str) -> Result<Operations, String> {
+    let normalize = |s: &str| {
+        s.chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+            .to_lowercase()
+    };
+    let wanted = normalize(text);
+    enum_iterator::all::<Operations>()
+        .find(|operation| normalize(&format!("{operation:?}")) == wanted)
+        .map_or_else(|| text.parse(), Ok)
+}
+
+pub fn parse_direction(name: &str) -> Result<Direction, String> {
+    enum_iterator::all::<Direction>()
+        .find(|direction| format!("{direction:?}").eq_ignore_ascii_case(name))
+        .ok_or_else(|| format!("unknown direction: {name}"))
+}
+
+/// in degrees clockwise
+pub fn parse_rotation(degrees: &str) -> Result<Rotation, String> {
+    match degrees {
+        "0" => Ok(Rotation::Deg0),
+        "90" => Ok(Rotation::Deg90),
+        "180" => Ok(Rotation::Deg180),
+        "270" => Ok(Rotation::Deg270),
+        _ => Err(format!("unknown rotation: {degrees}")),
+    }
+}
+
+/// a gate like `mini-boss` or `MINI_BOSS`
+pub fn parse_gate(name: &str) -> Result<OpenedGates, String> {
+    OpenedGates::from_name(&name.to_uppercase().replace('-', "_"))
+        .ok_or_else(|| format!("unknown gate: {name}"))
+}
+
+/// a rule like `fire-sanctuary:last`, `starting:first` or
+/// `earth-temple:before:mini-boss`
+pub fn parse_gate_order(spec: &str) -> Result<GateOrder, String> {
+    match spec.split(':').collect::<Vec<_>>()[..] {
+        [gate, "first"] => Ok(GateOrder::First(parse_gate(gate)?)),
+        [gate, "last"] => Ok(GateOrder::Last(parse_gate(gate)?)),
+        [first, "before", second] => Ok(GateOrder::Before(parse_gate(first)?, parse_gate(second)?)),
+        _ => Err(format!("invalid gate order: {spec}")),
+    }
+}
+
+pub fn parse_door_access(name: &str) -> Result<DoorAccess, String> {
+    match name {
+        "enter-only" => Ok(DoorAccess::EnterOnly),
+        "exit-only" => Ok(DoorAccess::ExitOnly),
+        "both" => Ok(DoorAccess::Both),
+        _ => Err(format!("unknown door access: {name}")),
+    }
+}
+
+pub fn parse_win_condition(name: &str) -> Result<WinCondition, String> {
+    match name {
+        "all-entrances" => Ok(WinCondition::AllEntrances),
+        "all-panels" => Ok(WinCondition::AllPanels),
+        "all-entrances-and-panels" => Ok(WinCondition::AllEntrancesAndPanels),
+        "sandship" => Ok(WinCondition::Sandship),
+        "all-markers" => Ok(WinCondition::AllMarkers),
+        _ => Err(format!("unknown win condition: {name}")),
+    }
+}
+
+/// `Err` for [`WinCondition::AllMarkers`] without any markers, which leaves
+/// nothing to collect
+pub fn check_markers(config: &SolverConfig) -> Result<(), String> {
+    if config.win_condition == WinCondition::AllMarkers && config.markers.is_empty() {
+        return Err("the all-markers win condition needs at least one marker".to_string());
+    }
+    Ok(())
+}
+
+pub fn parse_strategy(name: &str) -> Result<SearchStrategy, String> {
+    match name {
+        "breadth-first" => Ok(SearchStrategy::BreadthFirst),
+        "best-first" => Ok(SearchStrategy::BestFirst),
+        "depth-first" => Ok(SearchStrategy::DepthFirst),
+        "iterative-deepening" => Ok(SearchStrategy::IterativeDeepening),
+        "a-star" => Ok(SearchStrategy::AStar),
+        _ => Err(format!("unknown strategy: {name}")),
+    }
+}
+
+/// one of the built in [heuristics](crate::heuristic), none of them is
+/// admissible
+pub fn parse_heuristic(name: &str) -> Result<Arc<dyn Heuristic>, String> {
+    match name {
+        "unreached-entrances" => Ok(Arc::new(UnreachedEntrances)),
+        "unopened-gates" => Ok(Arc::new(UnopenedGates)),
+        "empty-distance" => Ok(Arc::new(EmptyDistance)),
+        _ => Err(format!("unknown heuristic: {name}")),
+    }
+}
+
+/// the first bound and how it grows, like `10+5` for 10, 15, 20, ... or
+/// `4x2` for 4, 8, 16, ...
+pub fn parse_deepening(spec: &str) -> Result<DeepeningSchedule, String> {
+    let invalid = || format!("expected <start>+<step> or <start>x<factor>, got {spec}");
+    let (start, growth) = if let Some((start, step)) = spec.split_once('+') {
+        let step = step.parse().ok().filter(|step| *step >= 1);
+        (start, DeepeningGrowth::Add(step.ok_or_else(invalid)?))
+    } else if let Some((start, factor)) = spec.split_once('x') {
+        let factor = factor.parse().ok().filter(|factor| *factor >= 2);
+        (
+            start,
+            DeepeningGrowth::Multiply(factor.ok_or_else(invalid)?),
+        )
+    } else {
+        return Err(invalid());
+    };
+    let start = start.parse().ok().filter(|start| *start >= 1);
+    Ok(DeepeningSchedule {
+        start: start.ok_or_else(invalid)?,
+        growth,
+    })
+}
+
+/// `<count>:<nodes>`, how many probes and how many states each can expand
+pub fn parse_random_probes(spec: &str) -> Result<RandomProbes, String> {
+    spec.split_once(':')
+        .and_then(|(count, nodes)| {
+            Some(RandomProbes {
+                count: count.parse().ok()?,
+                nodes: nodes.parse().ok()?,
+                seed: 0,
+            })
+        })
+        .ok_or_else(|| format!("expected <count>:<nodes>, got {spec}"))
+}
+
+/// a panel like `mini-boss` or `MiniBoss`
+pub fn parse_control_panel(name: &str) -> Result<ControlPanel, String> {
+    let wanted = name.replace('-', "").to_lowercase();
+    enum_iterator::all::<ControlPanel>()
+        .find(|panel| format!("{panel:?}").to_lowercase() == wanted)
+        .ok_or_else(|| format!("unknown control panel: {name}"))
+}
+
+/// Changes to the default costs like `slide=3,reach@mini-boss=7.5`, in
+/// seconds. `reach`, `slide` and `turn` without a panel set it for all of
+/// them.
+pub fn parse_cost_model(spec: &str) -> Result<CostModel, String> {
+    let mut costs = CostModel::default();
+    for item in spec
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+    {
+        let (key, seconds) = item
+            .split_once('=')
+            .ok_or_else(|| format!("expected <operation>=<seconds>: {item}"))?;
+        let seconds: f64 = seconds
+            .parse()
+            .ok()
+            .filter(|seconds: &f64| *seconds >= 0.0)
+            .ok_or_else(|| format!("invalid seconds: {seconds}"))?;
+        let (operation, panel) = match key.split_once('@') {
+            Some((operation, panel)) => (operation, Some(parse_control_panel(panel)?)),
+            None => (key, None),
+        };
+        let table = match operation {
+            "reach" => &mut costs.reach,
+            "slide" => &mut costs.slide,
+            "turn" => &mut costs.turn,
+            _ => return Err(format!("unknown operation: {operation}")),
+        };
+        match panel {
+            Some(panel) => table[panel as usize] = seconds,
+            None => *table = [seconds; 4],
+        }
+    }
+    Ok(costs)
+}
+
+pub fn parse_hasher(name: &str) -> Result<StateHasher, String> {
+    match name {
+        "fx" => Ok(StateHasher::Fx),
+        "sip" => Ok(StateHasher::Sip),
+        _ => Err(format!("unknown hasher: {name}")),
+    }
+}
+
+/// seconds, fractions allowed
+pub fn parse_timeout(value: &str) -> Result<Duration, String> {
+    value
+        .parse()
+        .ok()
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+        .ok_or_else(|| format!("invalid timeout: {value}"))
+}
+
+/// the board as 3 rows of 3 or as 9 tiles
+fn parse_board<T>(
+    value: &Value,
+    what: &str,
+    parse: impl Fn(&str) -> Result<T, String>,
+) -> Result<[T; 9], String> {
+    let mut tiles = Vec::new();
+    for item in value.as_list(what)? {
+        match item {
+            Value::List(row) => {
+                for tile in row {
+                    tiles.push(parse(tile.as_scalar(what)?)?);
+                }
+            }
+            _ => tiles.push(parse(item.as_scalar(what)?)?),
+        }
+    }
+    let count = tiles.len();
+    tiles
+        .try_into()
+        .map_err(|_| format!("{what} needs 9 tiles, got {count}"))
+}
+
+fn parse_entry(value: &Value) -> Result<EntryPoint, String> {
+    let mut entry = EntryPoint::default();
+    for (key, value) in value.as_map("entry")? {
+        let scalar = value.as_scalar(key)?;
+        match key.as_str() {
+            "tile" => {
+                entry.tile = scalar
+                    .parse()
+                    .ok()
+                    .filter(|tile| *tile < 9)
+                    .ok_or_else(|| format!("invalid tile: {scalar}"))?
+            }
+            "direction" => entry.direction = parse_direction(scalar)?,
+            _ => return Err(format!("unknown key in entry: {key}")),
+        }
+    }
+    Ok(entry)
+}
+
+fn parse_gate_list(value: &Value) -> Result<OpenedGates, String> {
+    let mut gates = OpenedGates::empty();
+    for gate in value.as_list("gates")? {
+        gates |= parse_gate(gate.as_scalar("gates")?)?;
+    }
+    Ok(gates)
+}
+
+fn parse_solver(value: &Value, config: &mut SolverConfig) -> Result<(), String> {
+    for (key, value) in value.as_map("solver")? {
+        match key.as_str() {
+            "toggling_gates" => config.toggling_gates = parse_gate_list(value)?,
+            "one_way_doors" => {
+                for (entrance, access) in value.as_map(key)? {
+                    config.door_rules.set_access(
+                        parse_entrance(entrance)?,
+                        parse_door_access(access.as_scalar(entrance)?)?,
+                    );
+                }
+            }
+            "door_directions" => {
+                for (entrance, direction) in value.as_map(key)? {
+                    config.door_rules.set_direction(
+                        parse_entrance(entrance)?,
+                        parse_direction(direction.as_scalar(entrance)?)?,
+                    );
+                }
+            }
+            _ => {
+                let scalar = value.as_scalar(key)?;
+                match key.as_str() {
+                    "max_nodes" => {
+                        config.max_nodes = Some(
+                            scalar
+                                .parse()
+                                .map_err(|_| format!("invalid node count: {scalar}"))?,
+                        )
+                    }
+                    "timeout" => config.timeout = Some(parse_timeout(scalar)?),
+                    "rotatable_rooms" => {
+                        config.rotatable_rooms = scalar
+                            .parse()
+                            .map_err(|_| format!("invalid bool: {scalar}"))?
+                    }
+                    "win_condition" => config.win_condition = parse_win_condition(scalar)?,
+                    "strategy" => config.strategy = parse_strategy(scalar)?,
+                    "deepening" => config.deepening = parse_deepening(scalar)?,
+                    "heuristic" => config.heuristic = Some(parse_heuristic(scalar)?),
+                    "random_probes" => config.probes = parse_random_probes(scalar)?,
+                    _ => return Err(format!("unknown key in solver: {key}")),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Scalar(String),
+    List(Vec<Value>),
+    Map(Vec<(String, Value)>),
+}
+
+impl Value {
+    fn as_scalar(&self, what: &str) -> Result<&str, String> {
+        match self {
+            Value::Scalar(s) => Ok(s),
+            _ => Err(format!("expected a single value for {what}")),
+        }
+    }
+
+    fn as_list(&self, what: &str) -> Result<&[Value], String> {
+        match self {
+            Value::List(items) => Ok(items),
+            _ => Err(format!("expected a list for {what}")),
+        }
+    }
+
+    fn as_map(&self, what: &str) -> Result<&[(String, Value)], String> {
+        match self {
+            Value::Map(entries) => Ok(entries),
+            _ => Err(format!("expected a mapping for {what}")),
+        }
+    }
+}
+
+struct Line<'a> {
+    number: usize,
+    indent: usize,
+    content: &'a str,
+}
+
+/// the non empty lines without comments
+fn yaml_lines(text: &str) -> Vec<Line<'_>> {
+    text.lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let content = match line.find(" #") {
+                Some(comment) => &line[..comment],
+                None => line,
+            };
+            let trimmed = content.trim_start();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                return None;
+            }
+            Some(Line {
+                number: i + 1,
+                indent: content.len() - trimmed.len(),
+                content: trimmed.trim_end(),
+            })
+        })
+        .collect()
+}
+
+fn is_list_item(content: &str) -> bool {
+    content == "-" || content.starts_with("- ")
+}
+
+/// a list or a mapping made of the lines with exactly this indentation
+fn parse_block(lines: &[Line], index: &mut usize, indent: usize) -> Result<Value, String> {
+    if is_list_item(lines[*index].content) {
+        let mut items = Vec::new();
+        // a list at the same indentation as its key ends at the next key
+        while let Some(line) = lines
+            .get(*index)
+            .filter(|line| line.indent == indent && is_list_item(line.content))
+        {
+            *index += 1;
+            let rest = line.content[1..].trim_start();
+            items.push(if rest.is_empty() {
+                parse_nested(lines, index, indent, line.number)?
+            } else {
+                parse_inline(rest, line.number)?
+            });
+        }
+        return Ok(Value::List(items));
+    }
+    let mut entries = Vec::new();
+    while let Some(line) = lines.get(*index).filter(|line| line.indent == indent) {
+        let (key, rest) = match line.content.split_once(':') {
+            Some((key, rest)) if rest.is_empty() || rest.starts_with(' ') => (key, rest.trim()),
+            _ => return Err(format!("line {}: expected `key: value`", line.number)),
+        };
+        *index += 1;
+        let value = if !rest.is_empty() {
+            parse_inline(rest, line.number)?
+        } else if lines
+            .get(*index)
+            .is_some_and(|next| next.indent == indent && is_list_item(next.content))
+        {
+            // lists are allowed at the same indentation as their key
+            parse_block(lines, index, indent)?
+        } else {
+            parse_nested(lines, index, indent, line.number)?
+        };
+        entries.push((unquote(key.trim()).to_string(), value));
+    }
+    Ok(Value::Map(entries))
+}
+
+/// the block indented deeper than its parent, which must be there
+fn parse_nested(
+    lines: &[Line],
+    index: &mut usize,
+    parent_indent: usize,
+    number: usize,
+) -> Result<Value, String> {
+    match lines.get(*index) {
+        Some(next) if next.indent > parent_indent => parse_block(lines, index, next.indent),
+        _ => Err(format!("line {number}: missing value")),
+    }
+}
+
+/// a scalar or a flow list like `[a, b, c]`
+fn parse_inline(text: &str, number: usize) -> Result<Value, String> {
+    let Some(inner) = text.strip_prefix('[') else {
+        return Ok(Value::Scalar(unquote(text).to_string()));
+    };
+    let inner = inner
+        .strip_suffix(']')
+        .ok_or_else(|| format!("line {number}: unclosed `[`"))?;
+    if inner.trim().is_empty() {
+        return Ok(Value::List(Vec::new()));
+    }
+    Ok(Value::List(
+        inner
+            .split(',')
+            .map(|item| Value::Scalar(unquote(item.trim()).to_string()))
+            .collect(),
+    ))
+}
+
+fn unquote(text: &str) -> &str {
+    for quote in ['"', '\''] {
+        if let Some(inner) = text
+            .strip_prefix(quote)
+            .and_then(|text| text.strip_suffix(quote))
+        {
+            return inner;
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+
+    const LAYOUT: &str = "layout:\n  - [STR, SV, ET]\n  - [LMF, BOS, AC]\n  - [FS, SSH, \"-\"]\n";
+
+    #[test]
+    fn all_markers_needs_markers() {
+        let without = format!("{LAYOUT}solver:\n  win_condition: all-markers\n");
+        assert!(PuzzleFile::parse(&without).is_err());
+        let empty = format!("{without}markers: []\n");
+        assert!(PuzzleFile::parse(&empty).is_err());
+        let with = format!("{without}markers: [SkyviewUp]\n");
+        let file = PuzzleFile::parse(&with).unwrap();
+        assert_eq!(file.config.markers, BTreeSet::from([Entrance::SkyviewUp]));
+    }
+}
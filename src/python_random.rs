@@ -0,0 +1,157 @@
+//! Python's `random` module, as far as the randomizer uses it for Sky Keep,
+//! so the layout for a seed can be reproduced exactly.
+
+use crate::generate::LayoutRng;
+
+const N: usize = 624;
+const M: usize = 397;
+
+/// The Mersenne Twister behind Python's `random.Random`.
+#[derive(Clone)]
+pub struct PythonRandom {
+    state: [u32; N],
+    index: usize,
+}
+
+impl PythonRandom {
+    /// Same as `random.Random(seed)` for a non negative integer seed.
+    pub fn new(seed: u64) -> Self {
+        // python splits the seed into as few 32 bit words as possible
+        let key: &[u32] = &[seed as u32, (seed >> 32) as u32];
+        let key = if seed >> 32 == 0 { &key[..1] } else { key };
+        let mut rng = PythonRandom {
+            state: [0; N],
+            index: N,
+        };
+        rng.init_genrand(19650218);
+        let mut i = 1;
+        let mut j = 0;
+        for _ in 0..N.max(key.len()) {
+            rng.state[i] = (rng.state[i]
+                ^ (rng.state[i - 1] ^ (rng.state[i - 1] >> 30)).wrapping_mul(1664525))
+            .wrapping_add(key[j])
+            .wrapping_add(j as u32);
+            i += 1;
+            j += 1;
+            if i >= N {
+                rng.state[0] = rng.state[N - 1];
+                i = 1;
+            }
+            if j >= key.len() {
+                j = 0;
+            }
+        }
+        for _ in 0..N - 1 {
+            rng.state[i] = (rng.state[i]
+                ^ (rng.state[i - 1] ^ (rng.state[i - 1] >> 30)).wrapping_mul(1566083941))
+            .wrapping_sub(i as u32);
+            i += 1;
+            if i >= N {
+                rng.state[0] = rng.state[N - 1];
+                i = 1;
+            }
+        }
+        rng.state[0] = 0x8000_0000;
+        rng
+    }
+
+    fn init_genrand(&mut self, seed: u32) {
+        self.state[0] = seed;
+        for i in 1..N {
+            self.state[i] = 1812433253u32
+                .wrapping_mul(self.state[i - 1] ^ (self.state[i - 1] >> 30))
+                .wrapping_add(i as u32);
+        }
+        self.index = N;
+    }
+
+    fn twist(&mut self) {
+        for i in 0..N {
+            let y = (self.state[i] & 0x8000_0000) | (self.state[(i + 1) % N] & 0x7fff_ffff);
+            let mut next = self.state[(i + M) % N] ^ (y >> 1);
+            if y & 1 == 1 {
+                next ^= 0x9908_b0df;
+            }
+            self.state[i] = next;
+        }
+        self.index = 0;
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        if self.index >= N {
+            self.twist();
+        }
+        let mut y = self.state[self.index];
+        self.index += 1;
+        y ^= y >> 11;
+        y ^= (y << 7) & 0x9d2c_5680;
+        y ^= (y << 15) & 0xefc6_0000;
+        y ^ (y >> 18)
+    }
+
+    /// `random.getrandbits(k)` for up to 32 bits
+    pub fn getrandbits(&mut self, k: u32) -> u32 {
+        assert!((1..=32).contains(&k), "only up to 32 bits are supported");
+        self.next_u32() >> (32 - k)
+    }
+
+    /// `random.randrange(n)`, by rejection sampling just like python
+    pub fn randbelow(&mut self, n: usize) -> usize {
+        assert!(n > 0 && n <= u32::MAX as usize, "range out of bounds");
+        let k = usize::BITS - n.leading_zeros();
+        loop {
+            let r = self.getrandbits(k) as usize;
+            if r < n {
+                return r;
+            }
+        }
+    }
+}
+
+impl LayoutRng for PythonRandom {
+    fn below(&mut self, n: usize) -> usize {
+        self.randbelow(n)
+    }
+
+    /// `random.shuffle`, a Fisher-Yates shuffle from the back
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.randbelow(i + 1);
+            items.swap(i, j);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the values are from CPython's random.Random with the same seeds
+
+    #[test]
+    fn matches_python_getrandbits() {
+        let mut rng = PythonRandom::new(42);
+        let words: Vec<u32> = (0..3).map(|_| rng.getrandbits(32)).collect();
+        assert_eq!(words, [2746317213, 478163327, 107420369]);
+        let mut rng = PythonRandom::new(0);
+        let bits: Vec<u32> = (0..3).map(|_| rng.getrandbits(7)).collect();
+        assert_eq!(bits, [108, 49, 97]);
+    }
+
+    #[test]
+    fn splits_long_seeds_like_python() {
+        let mut rng = PythonRandom::new((1 << 40) + 5);
+        let words: Vec<u32> = (0..3).map(|_| rng.getrandbits(32)).collect();
+        assert_eq!(words, [2166296868, 2220160828, 1153647273]);
+    }
+
+    #[test]
+    fn matches_python_randbelow_and_shuffle() {
+        let mut rng = PythonRandom::new(42);
+        let below: Vec<usize> = (0..5).map(|_| rng.randbelow(9)).collect();
+        assert_eq!(below, [1, 0, 4, 3, 3]);
+        let mut items: Vec<u8> = (0..9).collect();
+        PythonRandom::new(7).shuffle(&mut items);
+        assert_eq!(items, [1, 6, 7, 4, 0, 8, 3, 2, 5]);
+    }
+}
@@ -0,0 +1,435 @@
+//! Just enough of a QR code encoder to show layout codes on screen: byte
+//! mode, error correction level L and versions 1 to 4, which all use a
+//! single error correction block.
+
+/// (data codewords, error correction codewords) for versions 1 to 4 at
+/// error correction level L
+const CODEWORDS: [(usize, usize); 4] = [(19, 7), (34, 10), (55, 15), (80, 20)];
+
+/// Dark and light modules of a QR code, without the quiet zone around it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QrCode {
+    size: usize,
+    modules: Vec<bool>,
+}
+
+impl QrCode {
+    /// Encodes the bytes in the smallest version they fit in.
+    pub fn encode(data: &[u8]) -> Result<Self, &'static str> {
+        // 4 bits of mode and 8 bits of length in front of the data
+        let version = (1..=CODEWORDS.len())
+            .find(|v| data.len() + 2 <= CODEWORDS[v - 1].0)
+            .ok_or("too much data for a QR code")?;
+        let (data_len, ec_len) = CODEWORDS[version - 1];
+
+        let mut bits = BitWriter::default();
+        bits.push(0b0100, 4);
+        bits.push(data.len() as u32, 8);
+        for byte in data {
+            bits.push(*byte as u32, 8);
+        }
+        bits.push(0, 4.min(data_len * 8 - bits.len()));
+        bits.push(0, (8 - bits.len() % 8) % 8);
+        let mut codewords = bits.into_bytes();
+        for pad in [0xEC, 0x11].into_iter().cycle() {
+            if codewords.len() == data_len {
+                break;
+            }
+            codewords.push(pad);
+        }
+        let ec = reed_solomon(&codewords, ec_len);
+        codewords.extend(ec);
+
+        let mut qr = QrCode {
+            size: 17 + 4 * version,
+            modules: Vec::new(),
+        };
+        qr.modules = vec![false; qr.size * qr.size];
+        let mut reserved = vec![false; qr.size * qr.size];
+        qr.draw_function_patterns(version, &mut reserved);
+        qr.draw_codewords(&codewords, &reserved);
+
+        let best = (0..8)
+            .map(|mask| {
+                let mut candidate = qr.clone();
+                candidate.apply_mask(mask, &reserved);
+                candidate.draw_format_bits(mask);
+                candidate
+            })
+            .min_by_key(QrCode::penalty)
+            .unwrap();
+        Ok(best)
+    }
+
+    /// the number of modules along a side
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.size + x]
+    }
+
+    /// An SVG image with one unit per module and the quiet zone around it.
+    pub fn to_svg(&self) -> String {
+        let border = 4;
+        let mut path = String::new();
+        for y in 0..self.size {
+            for x in 0..self.size {
+                if self.is_dark(x, y) {
+                    path.push_str(&format!("M{},{}h1v1h-1z", x + border, y + border));
+                }
+            }
+        }
+        let full = self.size + 2 * border;
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {full} {full}\" shape-rendering=\"crispEdges\">\
+             <rect width=\"100%\" height=\"100%\" fill=\"#fff\"/>\
+             <path d=\"{path}\" fill=\"#000\"/></svg>\n"
+        )
+    }
+
+    fn set(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y * self.size + x] = dark;
+    }
+
+    fn draw_function_patterns(&mut self, version: usize, reserved: &mut [bool]) {
+        let size = self.size;
+        let mut draw = |qr: &mut QrCode, x: usize, y: usize, dark: bool| {
+            qr.set(x, y, dark);
+            reserved[y * size + x] = true;
+        };
+        for i in 0..size {
+            draw(self, 6, i, i % 2 == 0);
+            draw(self, i, 6, i % 2 == 0);
+        }
+        // finder patterns with their separators
+        for (cx, cy) in [(3, 3), (size - 4, 3), (3, size - 4)] {
+            for dy in -4i32..=4 {
+                for dx in -4i32..=4 {
+                    let (x, y) = (cx as i32 + dx, cy as i32 + dy);
+                    if (0..size as i32).contains(&x) && (0..size as i32).contains(&y) {
+                        let ring = dx.abs().max(dy.abs());
+                        draw(self, x as usize, y as usize, ring != 2 && ring != 4);
+                    }
+                }
+            }
+        }
+        if version >= 2 {
+            let center = size - 7;
+            for dy in -2i32..=2 {
+                for dx in -2i32..=2 {
+                    let ring = dx.abs().max(dy.abs());
+                    let (x, y) = ((center as i32 + dx) as usize, (center as i32 + dy) as usize);
+                    draw(self, x, y, ring != 1);
+                }
+            }
+        }
+        // the format bits are drawn once the mask is known
+        for i in 0..9 {
+            reserved[8 * size + i] = true;
+            reserved[i * size + 8] = true;
+        }
+        for i in 0..8 {
+            reserved[8 * size + size - 1 - i] = true;
+            reserved[(size - 1 - i) * size + 8] = true;
+        }
+    }
+
+    /// zigzags up and down two columns at a time from the bottom right
+    fn draw_codewords(&mut self, codewords: &[u8], reserved: &[bool]) {
+        let size = self.size;
+        let bit_count = codewords.len() * 8;
+        let mut i = 0;
+        let mut right = size - 1;
+        loop {
+            // the vertical timing pattern is skipped entirely
+            if right == 6 {
+                right = 5;
+            }
+            let upward = (right + 1) & 2 == 0;
+            for vert in 0..size {
+                for x in [right, right - 1] {
+                    let y = if upward { size - 1 - vert } else { vert };
+                    if !reserved[y * size + x] && i < bit_count {
+                        self.set(x, y, codewords[i / 8] >> (7 - i % 8) & 1 == 1);
+                        i += 1;
+                    }
+                }
+            }
+            if right < 2 {
+                break;
+            }
+            right -= 2;
+        }
+    }
+
+    fn apply_mask(&mut self, mask: u8, reserved: &[bool]) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let flip = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                if flip && !reserved[y * self.size + x] {
+                    self.modules[y * self.size + x] ^= true;
+                }
+            }
+        }
+    }
+
+    fn draw_format_bits(&mut self, mask: u8) {
+        // level L is 0b01
+        let data = (0b01 << 3 | mask) as u32;
+        let mut rem = data;
+        for _ in 0..10 {
+            rem = (rem << 1) ^ ((rem >> 9) * 0x537);
+        }
+        let bits = (data << 10 | rem) ^ 0x5412;
+        let bit = |i: usize| bits >> i & 1 == 1;
+        let size = self.size;
+        for i in 0..6 {
+            self.set(8, i, bit(i));
+        }
+        self.set(8, 7, bit(6));
+        self.set(8, 8, bit(7));
+        self.set(7, 8, bit(8));
+        for i in 9..15 {
+            self.set(14 - i, 8, bit(i));
+        }
+        for i in 0..8 {
+            self.set(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set(8, size - 15 + i, bit(i));
+        }
+        // always dark
+        self.set(8, size - 8, true);
+    }
+
+    /// the standard's score for how hard the code is to scan, the mask with
+    /// the lowest one is used
+    fn penalty(&self) -> usize {
+        let size = self.size;
+        let line = |i: usize, j: usize, horizontal: bool| {
+            if horizontal {
+                self.is_dark(j, i)
+            } else {
+                self.is_dark(i, j)
+            }
+        };
+        let mut penalty = 0;
+        for horizontal in [true, false] {
+            for i in 0..size {
+                let mut run = 1;
+                for j in 1..size {
+                    if line(i, j, horizontal) == line(i, j - 1, horizontal) {
+                        run += 1;
+                        if run == 5 {
+                            penalty += 3;
+                        } else if run > 5 {
+                            penalty += 1;
+                        }
+                    } else {
+                        run = 1;
+                    }
+                }
+                // looks like a finder pattern
+                for j in 0..size.saturating_sub(10) {
+                    let window: Vec<bool> = (j..j + 11).map(|j| line(i, j, horizontal)).collect();
+                    let core = [true, false, true, true, true, false, true];
+                    if (window[..4].iter().all(|d| !d) && window[4..] == core)
+                        || (window[..7] == core && window[7..].iter().all(|d| !d))
+                    {
+                        penalty += 40;
+                    }
+                }
+            }
+        }
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let dark = self.is_dark(x, y);
+                if dark == self.is_dark(x + 1, y)
+                    && dark == self.is_dark(x, y + 1)
+                    && dark == self.is_dark(x + 1, y + 1)
+                {
+                    penalty += 3;
+                }
+            }
+        }
+        let dark = self.modules.iter().filter(|d| **d).count();
+        let total = self.modules.len();
+        penalty += (dark * 20)
+            .abs_diff(total * 10)
+            .div_ceil(total)
+            .saturating_sub(1)
+            * 10;
+        penalty
+    }
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bits: Vec<bool>,
+}
+
+impl BitWriter {
+    fn push(&mut self, value: u32, count: usize) {
+        for i in (0..count).rev() {
+            self.bits.push(value >> i & 1 == 1);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.bits.len()
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        self.bits
+            .chunks(8)
+            .map(|byte| byte.iter().fold(0, |acc, bit| acc << 1 | *bit as u8))
+            .collect()
+    }
+}
+
+/// multiplication in GF(256) with the QR code polynomial
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 == 1 {
+            product ^= a;
+        }
+        a = (a << 1) ^ if a & 0x80 != 0 { 0x1D } else { 0 };
+        b >>= 1;
+    }
+    product
+}
+
+/// the error correction codewords for the data
+fn reed_solomon(data: &[u8], ec_len: usize) -> Vec<u8> {
+    // generator polynomial (x - a^0)(x - a^1)..., highest coefficient left
+    // out since it's always 1
+    let mut generator = vec![0u8; ec_len];
+    generator[ec_len - 1] = 1;
+    let mut root = 1u8;
+    for _ in 0..ec_len {
+        for j in 0..ec_len {
+            generator[j] = gf_mul(generator[j], root);
+            if j + 1 < ec_len {
+                generator[j] ^= generator[j + 1];
+            }
+        }
+        root = gf_mul(root, 2);
+    }
+    let mut remainder = vec![0u8; ec_len];
+    for byte in data {
+        let factor = byte ^ remainder[0];
+        remainder.remove(0);
+        remainder.push(0);
+        for (r, g) in remainder.iter_mut().zip(&generator) {
+            *r ^= gf_mul(*g, factor);
+        }
+    }
+    remainder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reed_solomon_matches_the_hello_world_example() {
+        // "HELLO WORLD" as version 1-M, the usual worked example
+        let data = [
+            32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236, 17, 236, 17,
+        ];
+        assert_eq!(
+            reed_solomon(&data, 10),
+            [196, 35, 39, 119, 235, 215, 231, 226, 93, 23]
+        );
+    }
+
+    /// reads the format bits and the codewords back like a scanner would
+    fn decode(qr: &QrCode) -> Vec<u8> {
+        let size = qr.size();
+        let dark = |x: usize, y: usize| qr.is_dark(x, y) as u32;
+        let mut format = 0;
+        for i in 0..6 {
+            format |= dark(8, i) << i;
+        }
+        format |= dark(8, 7) << 6 | dark(8, 8) << 7 | dark(7, 8) << 8;
+        for i in 9..15 {
+            format |= dark(14 - i, 8) << i;
+        }
+        let mut copy = 0;
+        for i in 0..8 {
+            copy |= dark(size - 1 - i, 8) << i;
+        }
+        for i in 8..15 {
+            copy |= dark(8, size - 15 + i) << i;
+        }
+        assert_eq!(format, copy);
+        let format = (format ^ 0x5412) >> 10;
+        assert_eq!(format >> 3, 0b01, "error correction level L");
+
+        let version = (size - 17) / 4;
+        let mut unmasked = QrCode {
+            size,
+            modules: vec![false; size * size],
+        };
+        let mut reserved = vec![false; size * size];
+        unmasked.draw_function_patterns(version, &mut reserved);
+        unmasked.modules = qr.modules.clone();
+        unmasked.apply_mask(format as u8 & 7, &reserved);
+
+        let mut bits = BitWriter::default();
+        let mut right = size - 1;
+        loop {
+            if right == 6 {
+                right = 5;
+            }
+            let upward = (right + 1) & 2 == 0;
+            for vert in 0..size {
+                for x in [right, right - 1] {
+                    let y = if upward { size - 1 - vert } else { vert };
+                    if !reserved[y * size + x] {
+                        bits.push(unmasked.is_dark(x, y) as u32, 1);
+                    }
+                }
+            }
+            if right < 2 {
+                break;
+            }
+            right -= 2;
+        }
+        let (data_len, ec_len) = CODEWORDS[version - 1];
+        let mut codewords = bits.into_bytes();
+        codewords.truncate(data_len + ec_len);
+        assert_eq!(
+            reed_solomon(&codewords[..data_len], ec_len),
+            codewords[data_len..]
+        );
+        // byte mode and the length in the first 12 bits
+        assert_eq!(codewords[0] >> 4, 0b0100);
+        let len = ((codewords[0] & 0xF) << 4 | codewords[1] >> 4) as usize;
+        (0..len)
+            .map(|i| codewords[1 + i] << 4 | codewords[2 + i] >> 4)
+            .collect()
+    }
+
+    #[test]
+    fn layout_codes_round_trip() {
+        for data in [&b"EAUgcYMIQ"[..], b"", &[0xA5; 40]] {
+            let qr = QrCode::encode(data).unwrap();
+            assert_eq!(decode(&qr), data);
+        }
+        assert_eq!(QrCode::encode(b"EAUgcYMIQ").unwrap().size(), 21);
+        assert!(QrCode::encode(&[0; 79]).is_err());
+    }
+}
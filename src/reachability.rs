@@ -0,0 +1,72 @@
+use std::collections::BTreeSet;
+
+use crate::{
+    rooms::{gate_names, Entrance, OpenedGates, Room, Rotation},
+    verify::{reachable_entrances, SolverConfig, Verdict},
+};
+
+/// Which entrances of a layout can be reached when the player enters with
+/// each combination of gates already open, for writing logic that depends on
+/// the gates. More gates can still be opened along the way.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReachabilityMatrix {
+    /// one row per combination of gates, in order of their bits
+    pub rows: Vec<(OpenedGates, BTreeSet<Entrance>)>,
+}
+
+impl ReachabilityMatrix {
+    /// The initial gates in `config` are replaced by every combination in
+    /// turn, `Err` if any of the searches doesn't finish.
+    pub fn new(
+        rooms: &[Room; 9],
+        rotations: &[Rotation; 9],
+        config: &SolverConfig,
+    ) -> Result<Self, Verdict> {
+        let rows = (0..=OpenedGates::all().bits())
+            .map(|bits| {
+                let initial_gates = OpenedGates::from_bits_truncate(bits);
+                let config = SolverConfig {
+                    initial_gates,
+                    ..config.clone()
+                };
+                Ok((
+                    initial_gates,
+                    reachable_entrances(rooms, rotations, &config)?,
+                ))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(ReachabilityMatrix { rows })
+    }
+
+    /// one row per combination of gates and one column per entrance, 1 if
+    /// it's reachable
+    pub fn to_csv(&self) -> String {
+        let entrances: Vec<String> = enum_iterator::all::<Entrance>()
+            .map(|entrance| format!("{entrance:?}"))
+            .collect();
+        let mut csv = format!("gates,{}\n", entrances.join(","));
+        for (gates, reached) in &self.rows {
+            let cells: Vec<&str> = enum_iterator::all::<Entrance>()
+                .map(|entrance| {
+                    if reached.contains(&entrance) {
+                        "1"
+                    } else {
+                        "0"
+                    }
+                })
+                .collect();
+            csv.push_str(&format!("{},{}\n", gate_names(*gates), cells.join(",")));
+        }
+        csv
+    }
+}
+
+impl std::fmt::Display for ReachabilityMatrix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (gates, reached) in &self.rows {
+            let reached: Vec<String> = reached.iter().map(|e| format!("{e:?}")).collect();
+            writeln!(f, "{}: {}", gate_names(*gates), reached.join(", "))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,269 @@
+//! The board drawn for people, as text for terminals or as SVG.
+
+use crate::rooms::{gate_names, Direction, DoorRules, OpenedGates, Room, Rotation};
+
+/// Everything a renderer can show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Board<'a> {
+    pub rooms: &'a [Room; 9],
+    pub rotations: &'a [Rotation; 9],
+    pub gates: OpenedGates,
+    /// which sides the doors are on
+    pub doors: &'a DoorRules,
+    /// the tile the player is on, if known
+    pub player: Option<u8>,
+}
+
+/// Turns a board into something to show, implement it to draw the board
+/// some other way.
+pub trait BoardRenderer {
+    /// the rendered board, UTF-8 for the text based renderers
+    fn render(&self, board: &Board) -> Vec<u8>;
+}
+
+/// [`ascii_board`], rotations and gates aren't shown.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AsciiRenderer {
+    pub color: bool,
+}
+
+impl BoardRenderer for AsciiRenderer {
+    fn render(&self, board: &Board) -> Vec<u8> {
+        ascii_board(board.rooms, board.player, self.color).into_bytes()
+    }
+}
+
+/// [`unicode_board`], the player isn't shown.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnicodeRenderer;
+
+impl BoardRenderer for UnicodeRenderer {
+    fn render(&self, board: &Board) -> Vec<u8> {
+        unicode_board(board.rooms, board.rotations, board.doors, board.gates).into_bytes()
+    }
+}
+
+/// [`board_svg`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SvgRenderer;
+
+impl BoardRenderer for SvgRenderer {
+    fn render(&self, board: &Board) -> Vec<u8> {
+        board_svg(board).into_bytes()
+    }
+}
+
+/// The board row by row as 3 column room codes, with ANSI colors from
+/// [`RoomInfo`](crate::rooms::RoomInfo) if `color`. The `player` tile is
+/// shown inverted when colored and followed by a `*` otherwise.
+pub fn ascii_board(rooms: &[Room; 9], player: Option<u8>, color: bool) -> String {
+    let mut board = String::new();
+    for (row, chunk) in rooms.chunks_exact(3).enumerate() {
+        for (column, room) in chunk.iter().enumerate() {
+            let tile = (row * 3 + column) as u8;
+            let cell = room.info().cell(color);
+            if player == Some(tile) {
+                if color {
+                    board.push_str(&format!("\x1b[7m{cell}\x1b[27m "));
+                } else {
+                    board.push_str(&format!("{cell}*"));
+                }
+            } else {
+                board.push_str(&cell);
+                board.push(' ');
+            }
+        }
+        board.push('\n');
+    }
+    board
+}
+
+/// What's drawn on one side of a tile.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Wall,
+    Door,
+    /// a door that leads nowhere until its gate is open
+    Gate,
+}
+
+fn side(
+    room: Room,
+    rotation: Rotation,
+    doors: &DoorRules,
+    gates: OpenedGates,
+    direction: Direction,
+) -> Side {
+    match doors.on_board(room, rotation, direction) {
+        None => Side::Wall,
+        // blocked coming in through this door, until the gate is opened from
+        // the other side
+        Some(entrance)
+            if entrance.traverse_room(gates).is_none()
+                && entrance.traverse_room(OpenedGates::all()).is_some() =>
+        {
+            Side::Gate
+        }
+        Some(_) => Side::Door,
+    }
+}
+
+/// The board with a box per room, open on the sides that have a door and
+/// with a `≡` in doors behind a gate that isn't in `gates`. Every tile is
+/// 9 columns and 5 lines, empty tiles are left blank.
+pub fn unicode_board(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    doors: &DoorRules,
+    gates: OpenedGates,
+) -> String {
+    let mut board = String::new();
+    for row in 0..3 {
+        let mut lines: [String; 5] = Default::default();
+        for tile in row * 3..row * 3 + 3 {
+            let room = rooms[tile];
+            if room == Room::Empty {
+                for line in &mut lines {
+                    line.push_str(&" ".repeat(9));
+                }
+                continue;
+            }
+            let side = |direction| side(room, rotations[tile], doors, gates, direction);
+            let horizontal = |side: Side| match side {
+                Side::Wall => "───",
+                Side::Door => "   ",
+                Side::Gate => " ≡ ",
+            };
+            let vertical = |side: Side| match side {
+                Side::Wall => '│',
+                Side::Door => ' ',
+                Side::Gate => '≡',
+            };
+            let (left, right) = (side(Direction::Left), side(Direction::Right));
+            lines[0].push_str(&format!("┌──{}──┐", horizontal(side(Direction::Up))));
+            lines[1].push_str(&format!("│{:7}│", ""));
+            lines[2].push_str(&format!(
+                "{}{:^7}{}",
+                vertical(left),
+                room.code(),
+                vertical(right)
+            ));
+            lines[3].push_str(&format!("│{:7}│", ""));
+            lines[4].push_str(&format!("└──{}──┘", horizontal(side(Direction::Down))));
+        }
+        for line in lines {
+            board.push_str(line.trim_end());
+            board.push('\n');
+        }
+    }
+    board
+}
+
+/// The board as an SVG picture, a square per room in its terminal color
+/// with a gap in the border for every door, closed gates drawn as a red bar
+/// in the door and the player as a dot.
+pub fn board_svg(board: &Board) -> String {
+    let (cell, wall) = (100, 6);
+    let mut body = String::new();
+    for tile in 0..9 {
+        let room = board.rooms[tile];
+        let (x, y) = ((tile % 3) * cell, (tile / 3) * cell);
+        if room == Room::Empty {
+            continue;
+        }
+        body.push_str(&format!(
+            "<rect x=\"{x}\" y=\"{y}\" width=\"{cell}\" height=\"{cell}\" fill=\"{}\"><title>{}</title></rect>",
+            svg_color(room.info().color),
+            room.name()
+        ));
+        for direction in enum_iterator::all::<Direction>() {
+            let side = side(
+                room,
+                board.rotations[tile],
+                board.doors,
+                board.gates,
+                direction,
+            );
+            // the wall split in two around the door in the middle third
+            let segments: &[(usize, usize)] = match side {
+                Side::Wall => &[(0, cell)],
+                Side::Door | Side::Gate => &[(0, cell / 3), (cell - cell / 3, cell)],
+            };
+            for (from, to) in segments {
+                let (wx, wy, ww, wh) = match direction {
+                    Direction::Up => (x + from, y, to - from, wall),
+                    Direction::Down => (x + from, y + cell - wall, to - from, wall),
+                    Direction::Left => (x, y + from, wall, to - from),
+                    Direction::Right => (x + cell - wall, y + from, wall, to - from),
+                };
+                body.push_str(&format!(
+                    "<rect x=\"{wx}\" y=\"{wy}\" width=\"{ww}\" height=\"{wh}\" fill=\"#333\"/>"
+                ));
+            }
+            if side == Side::Gate {
+                let (gx, gy, gw, gh) = match direction {
+                    Direction::Up => (x + cell / 3, y, cell / 3, wall / 2),
+                    Direction::Down => (x + cell / 3, y + cell - wall / 2, cell / 3, wall / 2),
+                    Direction::Left => (x, y + cell / 3, wall / 2, cell / 3),
+                    Direction::Right => (x + cell - wall / 2, y + cell / 3, wall / 2, cell / 3),
+                };
+                body.push_str(&format!(
+                    "<rect x=\"{gx}\" y=\"{gy}\" width=\"{gw}\" height=\"{gh}\" fill=\"#c00\"/>"
+                ));
+            }
+        }
+        body.push_str(&format!(
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\" font-family=\"sans-serif\" font-size=\"20\">{}</text>",
+            x + cell / 2,
+            y + cell / 2 + 7,
+            room.code()
+        ));
+    }
+    if let Some(tile) = board.player {
+        let tile = tile as usize;
+        body.push_str(&format!(
+            "<circle cx=\"{}\" cy=\"{}\" r=\"8\" fill=\"#000\"/>",
+            (tile % 3) * cell + cell / 2,
+            (tile / 3) * cell + cell - 20
+        ));
+    }
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {0} {0}\">\
+         <rect width=\"100%\" height=\"100%\" fill=\"#fff\"/>{body}</svg>\n",
+        3 * cell
+    )
+}
+
+/// a light version of an ANSI color, so the room code stays readable on it
+fn svg_color(ansi: u8) -> &'static str {
+    match ansi {
+        31 => "#f4b6b6",
+        32 => "#b9e4b0",
+        33 => "#f1e2a6",
+        35 => "#e3bde8",
+        36 => "#b3e3e8",
+        91 => "#f7c9a6",
+        93 => "#f6f0b0",
+        _ => "#e6e6e6",
+    }
+}
+
+/// The open gates like `starting+mini-boss`, the ones in `new` in bold
+/// green if `color` and marked with a `*` otherwise.
+pub fn gate_line(gates: OpenedGates, new: OpenedGates, color: bool) -> String {
+    if gates.is_empty() {
+        return gate_names(gates);
+    }
+    let names: Vec<String> = gates
+        .iter()
+        .map(|gate| {
+            let name = gate_names(gate);
+            match (new.contains(gate), color) {
+                (false, _) => name,
+                (true, true) => format!("\x1b[1;32m{name}\x1b[0m"),
+                (true, false) => format!("{name}*"),
+            }
+        })
+        .collect();
+    names.join("+")
+}
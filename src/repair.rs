@@ -0,0 +1,68 @@
+use std::collections::HashSet;
+
+use crate::{
+    rooms::{canonical_rotations, Room, Rotation},
+    verify::{verify_rotated_rooms, SolverConfig, Verdict},
+};
+
+/// A beatable layout close to a given one and how to get there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repair {
+    /// pairs of tiles whose rooms are swapped, in order
+    pub swaps: Vec<(u8, u8)>,
+    pub rooms: [Room; 9],
+    pub rotations: [Rotation; 9],
+}
+
+/// Searches for the beatable layout with the fewest pairwise room swaps away
+/// from the given one, rooms keep their rotation when they are swapped.
+/// Layouts the verifier can't decide on within the budget count as not
+/// beatable. `None` if nothing within `max_swaps` swaps is beatable.
+pub fn nearest_beatable(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    config: &SolverConfig,
+    max_swaps: usize,
+) -> Option<Repair> {
+    let start = Repair {
+        swaps: Vec::new(),
+        rooms: *rooms,
+        rotations: *rotations,
+    };
+    let beatable = |layout: &Repair| {
+        verify_rotated_rooms(&layout.rooms, &layout.rotations, config).verdict == Verdict::Beatable
+    };
+    if beatable(&start) {
+        return Some(start);
+    }
+    // swaps that only lead to an equivalent layout don't need verifying again
+    let mut seen = HashSet::from([(*rooms, canonical_rotations(rooms, rotations))]);
+    let mut layer = vec![start];
+    for _ in 0..max_swaps {
+        let mut next_layer = Vec::new();
+        for layout in &layer {
+            for a in 0..9u8 {
+                for b in a + 1..9 {
+                    let (a_index, b_index) = (a as usize, b as usize);
+                    if layout.rooms[a_index] == layout.rooms[b_index] {
+                        continue;
+                    }
+                    let mut swapped = layout.clone();
+                    swapped.rooms.swap(a_index, b_index);
+                    swapped.rotations.swap(a_index, b_index);
+                    let canonical = canonical_rotations(&swapped.rooms, &swapped.rotations);
+                    if !seen.insert((swapped.rooms, canonical)) {
+                        continue;
+                    }
+                    swapped.swaps.push((a, b));
+                    if beatable(&swapped) {
+                        return Some(swapped);
+                    }
+                    next_layer.push(swapped);
+                }
+            }
+        }
+        layer = next_layer;
+    }
+    None
+}
@@ -0,0 +1,706 @@
+use enum_iterator::Sequence;
+
+bitflags::bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct OpenedGates : u8 {
+        const STARTING = 1 << 0;
+        const EARTH_TEMPLE = 1 << 1;
+        const MINI_BOSS = 1 << 2;
+        const FIRE_SANCTUARY = 1 << 3;
+    }
+}
+
+impl OpenedGates {
+    /// Checks that only bits of actual gates are set, which can't be taken
+    /// for granted for gates read back with `from_bits_retain`.
+    pub fn validate(&self) -> Result<(), String> {
+        let unknown = self.bits() & !OpenedGates::all().bits();
+        if unknown != 0 {
+            return Err(format!("unknown gate bits: {unknown:#010b}"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Sequence)]
+pub enum Direction {
+    Up,
+    Left,
+    Down,
+    Right,
+}
+
+impl Direction {
+    pub fn opposite(&self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Left => Direction::Right,
+            Direction::Down => Direction::Up,
+            Direction::Right => Direction::Left,
+        }
+    }
+
+    pub fn clockwise(&self) -> Direction {
+        match self {
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+        }
+    }
+
+    pub fn tile_move(&self) -> isize {
+        match self {
+            Direction::Up => -3,
+            Direction::Left => -1,
+            Direction::Down => 3,
+            Direction::Right => 1,
+        }
+    }
+}
+
+/// How far a room is turned clockwise on its tile, only used by the
+/// rotatable rooms variant, in vanilla every room stays at `Deg0`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Sequence, Default)]
+pub enum Rotation {
+    #[default]
+    Deg0,
+    Deg90,
+    Deg180,
+    Deg270,
+}
+
+pub const NO_ROTATIONS: [Rotation; 9] = [Rotation::Deg0; 9];
+
+impl Rotation {
+    fn quarter_turns(&self) -> usize {
+        match self {
+            Rotation::Deg0 => 0,
+            Rotation::Deg90 => 1,
+            Rotation::Deg180 => 2,
+            Rotation::Deg270 => 3,
+        }
+    }
+
+    pub fn clockwise(&self) -> Rotation {
+        match self {
+            Rotation::Deg0 => Rotation::Deg90,
+            Rotation::Deg90 => Rotation::Deg180,
+            Rotation::Deg180 => Rotation::Deg270,
+            Rotation::Deg270 => Rotation::Deg0,
+        }
+    }
+
+    /// direction on the board a door of the unrotated room ends up facing
+    pub fn rotate(&self, direction: Direction) -> Direction {
+        // a quarter turn clockwise is one step back in `DIRECTIONS`
+        DIRECTIONS[(direction as usize + 4 - self.quarter_turns()) % 4]
+    }
+
+    /// direction the door facing `direction` on the board has in the unrotated room
+    pub fn unrotate(&self, direction: Direction) -> Direction {
+        DIRECTIONS[(direction as usize + self.quarter_turns()) % 4]
+    }
+}
+
+/// in the order of their discriminants
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Left,
+    Direction::Down,
+    Direction::Right,
+];
+
+/// [`Entrance::from_room_direction`] for every room, rotation and direction
+/// on the board, walks through the rooms look doors up in here
+const DOORS: [[[Option<Entrance>; 4]; 4]; 9] = {
+    let mut doors = [[[None; 4]; 4]; 9];
+    let mut room = 0;
+    while room < 9 {
+        let mut turns = 0;
+        while turns < 4 {
+            let mut direction = 0;
+            while direction < 4 {
+                doors[room][turns][direction] = Entrance::from_room_direction(
+                    ALL_ROOMS[room],
+                    DIRECTIONS[(direction + turns) % 4],
+                );
+                direction += 1;
+            }
+            turns += 1;
+        }
+        room += 1;
+    }
+    doors
+};
+
+#[derive(Debug, Sequence, Clone, Copy, PartialEq, Eq)]
+pub enum ControlPanel {
+    Start,
+    LanayruMiningFacility,
+    EarthTemple,
+    MiniBoss,
+}
+
+impl ControlPanel {
+    /// the room the panel is in
+    pub fn room(&self) -> Room {
+        self.entrance().to_room_direction().0
+    }
+
+    pub fn entrance(&self) -> Entrance {
+        match self {
+            ControlPanel::Start => Entrance::StartDown,
+            ControlPanel::LanayruMiningFacility => Entrance::LanayruMiningFacilityDown,
+            ControlPanel::EarthTemple => Entrance::EarthTempleDown,
+            ControlPanel::MiniBoss => Entrance::MiniBossLeft,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Sequence, Hash)]
+pub enum Room {
+    Start,
+    Skyview,
+    EarthTemple,
+    LanayruMiningFacility,
+    MiniBoss,
+    AncientCistern,
+    FireSanctuary,
+    Sandship,
+    Empty,
+}
+
+/// every room once, which is what the board is made of in vanilla
+pub const ALL_ROOMS: [Room; 9] = [
+    Room::Start,
+    Room::Skyview,
+    Room::EarthTemple,
+    Room::LanayruMiningFacility,
+    Room::MiniBoss,
+    Room::AncientCistern,
+    Room::FireSanctuary,
+    Room::Sandship,
+    Room::Empty,
+];
+
+/// How a room is shown, shared by everything that prints or draws rooms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RoomInfo {
+    /// the name players know the room by
+    pub name: &'static str,
+    /// short name used in layout notation, at most 3 letters
+    pub code: &'static str,
+    /// ANSI foreground color for terminals, like `32` for green
+    pub color: u8,
+}
+
+impl RoomInfo {
+    /// the code padded to 3 columns, colored if `color`
+    pub fn cell(&self, color: bool) -> String {
+        let code = if self.code == "-" { "" } else { self.code };
+        if color {
+            format!("\x1b[{}m{code:<3}\x1b[0m", self.color)
+        } else {
+            format!("{code:<3}")
+        }
+    }
+}
+
+/// indexed by `Room as usize`
+pub const ROOM_INFO: [RoomInfo; 9] = [
+    RoomInfo {
+        name: "Start",
+        code: "STR",
+        color: 37,
+    },
+    RoomInfo {
+        name: "Skyview",
+        code: "SV",
+        color: 32,
+    },
+    RoomInfo {
+        name: "Earth Temple",
+        code: "ET",
+        color: 31,
+    },
+    RoomInfo {
+        name: "Lanayru Mining Facility",
+        code: "LMF",
+        color: 33,
+    },
+    RoomInfo {
+        name: "Mini Boss",
+        code: "BOS",
+        color: 35,
+    },
+    RoomInfo {
+        name: "Ancient Cistern",
+        code: "AC",
+        color: 36,
+    },
+    RoomInfo {
+        name: "Fire Sanctuary",
+        code: "FS",
+        color: 91,
+    },
+    RoomInfo {
+        name: "Sandship",
+        code: "SSH",
+        color: 93,
+    },
+    RoomInfo {
+        name: "empty",
+        code: "-",
+        color: 90,
+    },
+];
+
+impl Room {
+    pub fn info(self) -> &'static RoomInfo {
+        &ROOM_INFO[self as usize]
+    }
+
+    /// short name used in layout notation
+    pub fn code(self) -> &'static str {
+        self.info().code
+    }
+
+    /// the name players know the room by
+    pub fn name(self) -> &'static str {
+        self.info().name
+    }
+}
+
+/// The board row by row as room codes, like `STR SV ET/LMF BOS AC/FS SSH -`.
+pub fn layout_notation(rooms: &[Room; 9]) -> String {
+    rooms
+        .chunks_exact(3)
+        .map(|row| row.iter().map(|r| r.code()).collect::<Vec<_>>().join(" "))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// A board that can be played: no room is on it twice and there is an empty
+/// tile to slide rooms into. The solvers take rooms and rotations as they
+/// are and give meaningless verdicts for boards that aren't like this, so
+/// layouts read from outside go through [`Layout::new`] first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Layout {
+    rooms: [Room; 9],
+    rotations: [Rotation; 9],
+}
+
+impl Layout {
+    pub fn new(rooms: [Room; 9], rotations: [Rotation; 9]) -> Result<Self, String> {
+        validate_rooms(&rooms)?;
+        Ok(Layout { rooms, rotations })
+    }
+
+    pub fn rooms(&self) -> &[Room; 9] {
+        &self.rooms
+    }
+
+    pub fn rotations(&self) -> &[Rotation; 9] {
+        &self.rotations
+    }
+
+    pub fn into_parts(self) -> ([Room; 9], [Rotation; 9]) {
+        (self.rooms, self.rotations)
+    }
+
+    /// The one layout of all that play the same as this one, see
+    /// [`are_equivalent`].
+    pub fn canonicalize(&self) -> Layout {
+        Layout {
+            rooms: self.rooms,
+            rotations: canonical_rotations(&self.rooms, &self.rotations),
+        }
+    }
+}
+
+/// If two layouts play the same. The entry door is fixed, so the board
+/// can't be mirrored or turned as a whole, and rooms have no mirrored
+/// counterparts, which leaves turns of a room that put the same doors on the
+/// same sides. Of the rooms in the game only the empty tile has those, it
+/// can be turned any way.
+pub fn are_equivalent(a: &Layout, b: &Layout) -> bool {
+    a.canonicalize() == b.canonicalize()
+}
+
+/// every room turned the least way that puts the same doors on the same
+/// sides as `rotations`. Any turn moves a door to another side, so that's
+/// only ever a room without doors, which [`DoorRules`] can't give any since
+/// it only moves doors around their own room
+pub(crate) fn canonical_rotations(rooms: &[Room; 9], rotations: &[Rotation; 9]) -> [Rotation; 9] {
+    let mut canonical = *rotations;
+    for (rotation, room) in canonical.iter_mut().zip(rooms) {
+        let has_doors = enum_iterator::all::<Entrance>()
+            .any(|entrance| entrance.to_room_direction().0 == *room);
+        if !has_doors {
+            *rotation = Rotation::Deg0;
+        }
+    }
+    canonical
+}
+
+/// `Err` if a room other than [`Room::Empty`] is on the board twice or no
+/// tile is empty, more than one can be with rooms left out.
+pub fn validate_rooms(rooms: &[Room; 9]) -> Result<(), String> {
+    for (tile, room) in rooms.iter().enumerate() {
+        if *room != Room::Empty && rooms[..tile].contains(room) {
+            return Err(format!("{} is on the board twice", room.name()));
+        }
+    }
+    if !rooms.contains(&Room::Empty) {
+        return Err("no empty tile on the board".to_string());
+    }
+    Ok(())
+}
+
+/// the gates like [`parse_gate`](crate::puzzle_file::parse_gate) reads them,
+/// joined with `+`
+pub(crate) fn gate_names(gates: OpenedGates) -> String {
+    if gates.is_empty() {
+        return "none".to_string();
+    }
+    let names: Vec<String> = gates
+        .iter_names()
+        .map(|(name, _)| name.to_lowercase().replace('_', "-"))
+        .collect();
+    names.join("+")
+}
+
+pub fn do_move(tile: u8, direction: Direction) -> Option<(u8, Direction)> {
+    match direction {
+        Direction::Up => {
+            if tile < 3 {
+                None
+            } else {
+                Some((tile - 3, Direction::Down))
+            }
+        }
+        Direction::Left => {
+            if [0, 3, 6].contains(&tile) {
+                None
+            } else {
+                Some((tile - 1, Direction::Right))
+            }
+        }
+        Direction::Down => {
+            if tile >= 6 {
+                None
+            } else {
+                Some((tile + 3, Direction::Up))
+            }
+        }
+        Direction::Right => {
+            if [2, 5, 8].contains(&tile) {
+                None
+            } else {
+                Some((tile + 1, Direction::Left))
+            }
+        }
+    }
+}
+
+/// Which ways a doorway can be walked through, seen from the room the
+/// entrance belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DoorAccess {
+    #[default]
+    Both,
+    EnterOnly,
+    ExitOnly,
+}
+
+impl DoorAccess {
+    pub fn can_enter(&self) -> bool {
+        !matches!(self, DoorAccess::ExitOnly)
+    }
+
+    pub fn can_exit(&self) -> bool {
+        !matches!(self, DoorAccess::EnterOnly)
+    }
+}
+
+pub const ENTRANCE_COUNT: usize = enum_iterator::cardinality::<Entrance>();
+
+/// One-way doors and doors on other sides of their room than in the game,
+/// on top of the room logic. In vanilla every door can be walked through
+/// both ways and faces the side its [`Entrance`] is named after.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DoorRules {
+    access: [DoorAccess; ENTRANCE_COUNT],
+    /// the door of every unturned room facing every direction, indexed by
+    /// `Room as usize` and `Direction as usize`
+    doors: [[Option<Entrance>; 4]; 9],
+    /// the other way around, indexed by `Entrance as usize`
+    directions: [Direction; ENTRANCE_COUNT],
+}
+
+pub const BOTH_WAY_DOORS: DoorRules = {
+    let mut doors = [[None; 4]; 9];
+    let mut directions = [Direction::Up; ENTRANCE_COUNT];
+    let mut room = 0;
+    while room < 9 {
+        doors[room] = DOORS[room][0];
+        let mut direction = 0;
+        while direction < 4 {
+            if let Some(entrance) = doors[room][direction] {
+                directions[entrance as usize] = DIRECTIONS[direction];
+            }
+            direction += 1;
+        }
+        room += 1;
+    }
+    DoorRules {
+        access: [DoorAccess::Both; ENTRANCE_COUNT],
+        doors,
+        directions,
+    }
+};
+
+impl Default for DoorRules {
+    fn default() -> Self {
+        BOTH_WAY_DOORS
+    }
+}
+
+impl DoorRules {
+    pub fn access(&self, entrance: Entrance) -> DoorAccess {
+        self.access[entrance as usize]
+    }
+
+    pub fn set_access(&mut self, entrance: Entrance, access: DoorAccess) {
+        self.access[entrance as usize] = access;
+    }
+
+    /// if the room can be entered from the neighboring room through this entrance
+    pub fn can_enter(&self, entrance: Entrance) -> bool {
+        self.access(entrance).can_enter()
+    }
+
+    /// if the room can be left into the neighboring room through this entrance
+    pub fn can_exit(&self, entrance: Entrance) -> bool {
+        self.access(entrance).can_exit()
+    }
+
+    /// the door of the unturned room facing `direction`, like
+    /// [`Entrance::from_room_direction`]
+    pub fn door(&self, room: Room, direction: Direction) -> Option<Entrance> {
+        self.doors[room as usize][direction as usize]
+    }
+
+    /// the side of the unturned room the door faces, like
+    /// [`Entrance::to_room_direction`]
+    pub fn direction(&self, entrance: Entrance) -> Direction {
+        self.directions[entrance as usize]
+    }
+
+    /// the door of `room` turned by `rotation` that faces `direction` on
+    /// the board, like [`Entrance::on_board`]
+    pub fn on_board(
+        &self,
+        room: Room,
+        rotation: Rotation,
+        direction: Direction,
+    ) -> Option<Entrance> {
+        self.door(room, rotation.unrotate(direction))
+    }
+
+    /// Moves the door to the side `direction` of its unturned room. If
+    /// another door of the room is on that side, the two swap sides.
+    pub fn set_direction(&mut self, entrance: Entrance, direction: Direction) {
+        let room = entrance.to_room_direction().0 as usize;
+        let old = self.direction(entrance);
+        let other = self.doors[room][direction as usize];
+        self.doors[room][old as usize] = other;
+        self.doors[room][direction as usize] = Some(entrance);
+        if let Some(other) = other {
+            self.directions[other as usize] = old;
+        }
+        self.directions[entrance as usize] = direction;
+    }
+
+    /// the doors that don't face the side they do in the game, with the
+    /// side they face
+    pub fn moved_doors(&self) -> Vec<(Entrance, Direction)> {
+        enum_iterator::all::<Entrance>()
+            .filter(|entrance| self.direction(*entrance) != entrance.to_room_direction().1)
+            .map(|entrance| (entrance, self.direction(entrance)))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Sequence, Hash)]
+pub enum Entrance {
+    StartDown,
+    StartRight,
+    SkyviewLeft,
+    SkyviewUp,
+    EarthTempleRight,
+    EarthTempleDown,
+    LanayruMiningFacilityDown,
+    LanayruMiningFacilityUp,
+    MiniBossLeft,
+    MiniBossDown,
+    AncientCisternRight,
+    AncientCisternDown,
+    FireSanctuaryLeft,
+    FireSanctuaryRight,
+    SandshipLeft,
+}
+
+impl Entrance {
+    /// the door of `room` turned by `rotation` that faces `direction` on
+    /// the board
+    pub fn on_board(room: Room, rotation: Rotation, direction: Direction) -> Option<Self> {
+        DOORS[room as usize][rotation as usize][direction as usize]
+    }
+
+    pub const fn from_room_direction(room: Room, direction: Direction) -> Option<Self> {
+        use Entrance::*;
+        Some(match (room, direction) {
+            (Room::Start, Direction::Down) => StartDown,
+            (Room::Start, Direction::Right) => StartRight,
+            (Room::Skyview, Direction::Up) => SkyviewUp,
+            (Room::Skyview, Direction::Left) => SkyviewLeft,
+            (Room::EarthTemple, Direction::Down) => EarthTempleDown,
+            (Room::EarthTemple, Direction::Right) => EarthTempleRight,
+            (Room::LanayruMiningFacility, Direction::Up) => LanayruMiningFacilityUp,
+            (Room::LanayruMiningFacility, Direction::Down) => LanayruMiningFacilityDown,
+            (Room::MiniBoss, Direction::Left) => MiniBossLeft,
+            (Room::MiniBoss, Direction::Down) => MiniBossDown,
+            (Room::AncientCistern, Direction::Down) => AncientCisternDown,
+            (Room::AncientCistern, Direction::Right) => AncientCisternRight,
+            (Room::FireSanctuary, Direction::Left) => FireSanctuaryLeft,
+            (Room::FireSanctuary, Direction::Right) => FireSanctuaryRight,
+            (Room::Sandship, Direction::Left) => SandshipLeft,
+            _ => return None,
+        })
+    }
+
+    pub fn traverse_room(&self, gates: OpenedGates) -> Option<Entrance> {
+        use Entrance::*;
+        match self {
+            Entrance::StartDown => Some(StartRight),
+            Entrance::StartRight => gates.contains(OpenedGates::STARTING).then_some(StartDown),
+            Entrance::SkyviewLeft => Some(SkyviewUp),
+            Entrance::SkyviewUp => Some(SkyviewLeft),
+            Entrance::EarthTempleRight => gates
+                .contains(OpenedGates::EARTH_TEMPLE)
+                .then_some(EarthTempleDown),
+            Entrance::EarthTempleDown => Some(EarthTempleRight),
+            Entrance::LanayruMiningFacilityDown => Some(LanayruMiningFacilityUp),
+            Entrance::LanayruMiningFacilityUp => Some(LanayruMiningFacilityDown),
+            Entrance::MiniBossLeft => gates
+                .contains(OpenedGates::MINI_BOSS)
+                .then_some(MiniBossDown),
+            Entrance::MiniBossDown => Some(MiniBossLeft),
+            Entrance::AncientCisternRight => Some(AncientCisternDown),
+            Entrance::AncientCisternDown => Some(AncientCisternRight),
+            Entrance::FireSanctuaryLeft => gates
+                .contains(OpenedGates::FIRE_SANCTUARY)
+                .then_some(FireSanctuaryRight),
+            Entrance::FireSanctuaryRight => Some(FireSanctuaryLeft),
+            Entrance::SandshipLeft => None,
+        }
+    }
+
+    pub fn to_room_direction(&self) -> (Room, Direction) {
+        use Entrance::*;
+        match self {
+            StartDown => (Room::Start, Direction::Down),
+            StartRight => (Room::Start, Direction::Right),
+            SkyviewUp => (Room::Skyview, Direction::Up),
+            SkyviewLeft => (Room::Skyview, Direction::Left),
+            EarthTempleDown => (Room::EarthTemple, Direction::Down),
+            EarthTempleRight => (Room::EarthTemple, Direction::Right),
+            LanayruMiningFacilityUp => (Room::LanayruMiningFacility, Direction::Up),
+            LanayruMiningFacilityDown => (Room::LanayruMiningFacility, Direction::Down),
+            MiniBossLeft => (Room::MiniBoss, Direction::Left),
+            MiniBossDown => (Room::MiniBoss, Direction::Down),
+            AncientCisternDown => (Room::AncientCistern, Direction::Down),
+            AncientCisternRight => (Room::AncientCistern, Direction::Right),
+            FireSanctuaryLeft => (Room::FireSanctuary, Direction::Left),
+            FireSanctuaryRight => (Room::FireSanctuary, Direction::Right),
+            SandshipLeft => (Room::Sandship, Direction::Left),
+        }
+    }
+
+    pub fn has_control_panel(&self) -> bool {
+        use Entrance::*;
+        matches!(
+            self,
+            StartRight | LanayruMiningFacilityDown | EarthTempleDown | MiniBossLeft
+        )
+    }
+
+    pub fn open_gate(&self) -> Option<OpenedGates> {
+        match self {
+            Entrance::StartDown => Some(OpenedGates::STARTING),
+            Entrance::EarthTempleDown => Some(OpenedGates::EARTH_TEMPLE),
+            Entrance::MiniBossDown => Some(OpenedGates::MINI_BOSS),
+            Entrance::FireSanctuaryRight => Some(OpenedGates::FIRE_SANCTUARY),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vanilla_doors_face_their_names() {
+        for room in ALL_ROOMS {
+            for rotation in enum_iterator::all::<Rotation>() {
+                for direction in enum_iterator::all::<Direction>() {
+                    assert_eq!(
+                        BOTH_WAY_DOORS.on_board(room, rotation, direction),
+                        Entrance::on_board(room, rotation, direction)
+                    );
+                }
+            }
+        }
+        assert!(BOTH_WAY_DOORS.moved_doors().is_empty());
+    }
+
+    #[test]
+    fn set_direction_swaps_doors_of_the_room() {
+        let mut doors = BOTH_WAY_DOORS;
+        doors.set_direction(Entrance::StartDown, Direction::Right);
+        assert_eq!(
+            doors.door(Room::Start, Direction::Right),
+            Some(Entrance::StartDown)
+        );
+        assert_eq!(
+            doors.door(Room::Start, Direction::Down),
+            Some(Entrance::StartRight)
+        );
+        assert_eq!(doors.direction(Entrance::StartRight), Direction::Down);
+
+        doors.set_direction(Entrance::StartDown, Direction::Up);
+        assert_eq!(
+            doors.door(Room::Start, Direction::Up),
+            Some(Entrance::StartDown)
+        );
+        assert_eq!(doors.door(Room::Start, Direction::Right), None);
+        assert_eq!(
+            doors.on_board(
+                Room::Start,
+                Rotation::Deg90,
+                Rotation::Deg90.rotate(Direction::Up)
+            ),
+            Some(Entrance::StartDown)
+        );
+        assert_eq!(
+            doors.moved_doors(),
+            [
+                (Entrance::StartDown, Direction::Up),
+                (Entrance::StartRight, Direction::Down)
+            ]
+        );
+    }
+}
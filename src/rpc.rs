@@ -0,0 +1,353 @@
+//! JSON-RPC 2.0 over stdin and stdout, one request per line, so other tools
+//! can keep a single process around instead of starting one per layout.
+//!
+//! Every method takes the layout as its share `code`, the ones that look at
+//! a playthrough also take the `operations` done since the first panel,
+//! each like `Move(First, Up)` or in the [short notation](crate::notation):
+//!
+//! - `verify {code}`: the verifier's verdict
+//! - `solve {code}`: the verdict and a solution
+//! - `hint {code, operations}`: how many operations an optimal finish takes
+//!   and the next one of them
+//! - `apply-move {code, operations, operation}`: the state after doing one
+//!   more operation
+//!
+//! `max_nodes`, `timeout`, `win_condition` and `strategy` in the params
+//! override the server's config for that request. Requests without them
+//! share a [`Session`], so asking about a layout again is answered from
+//! memory.
+//!
+//! Every state `apply-move` ends up in is also passed to the callback from
+//! [`RpcServer::on_state`], as an [`overlay_message`].
+
+use std::{cell::RefCell, time::Duration};
+
+use crate::{
+    json::Json,
+    puzzle_file::{
+        check_markers, parse_operation, parse_strategy, parse_timeout, parse_win_condition,
+    },
+    rooms::{gate_names, Room, Rotation},
+    session::Session,
+    share::{layout_code, parse_layout_code},
+    solve::{apply, optimal_finish, solve_rotated_rooms, SolveState},
+    verify::{
+        verify_rotated_rooms, OperationCounts, Operations, SearchStats, SolverConfig, Verdict,
+    },
+    websocket::overlay_message,
+};
+
+const PARSE_ERROR: i32 = -32700;
+const INVALID_REQUEST: i32 = -32600;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+
+const METHODS: [&str; 4] = ["verify", "solve", "hint", "apply-move"];
+
+type OnState = Box<dyn Fn(&Json)>;
+
+/// the params that override the config
+const OVERRIDES: [&str; 4] = ["max_nodes", "timeout", "win_condition", "strategy"];
+
+pub struct RpcServer {
+    config: SolverConfig,
+    session: RefCell<Session>,
+    on_state: Option<OnState>,
+}
+
+impl RpcServer {
+    /// `config` is used for every request that doesn't override it
+    pub fn new(config: SolverConfig) -> Self {
+        RpcServer {
+            session: RefCell::new(Session::new(config.clone())),
+            config,
+            on_state: None,
+        }
+    }
+
+    /// calls `f` with every state a move is applied to get to
+    pub fn on_state(&mut self, f: impl Fn(&Json) + 'static) {
+        self.on_state = Some(Box::new(f));
+    }
+
+    /// The response to a line of input, `None` for notifications, which
+    /// don't get one.
+    pub fn handle(&self, line: &str) -> Option<String> {
+        let request = match Json::parse(line) {
+            Ok(request) => request,
+            Err(e) => return Some(error_response(Json::Null, PARSE_ERROR, e)),
+        };
+        let id = request.get("id").cloned();
+        let response_id = id.clone().unwrap_or(Json::Null);
+        let Some(method) = request.get("method").and_then(Json::as_str) else {
+            return Some(error_response(
+                response_id,
+                INVALID_REQUEST,
+                "missing method".to_string(),
+            ));
+        };
+        let params = request
+            .get("params")
+            .cloned()
+            .unwrap_or(Json::Object(Vec::new()));
+        let result = self.call(method, &params);
+        id.as_ref()?;
+        Some(match result {
+            Ok(result) => Json::object([
+                ("jsonrpc", "2.0".into()),
+                ("result", result),
+                ("id", response_id),
+            ])
+            .to_string(),
+            Err((code, message)) => error_response(response_id, code, message),
+        })
+    }
+
+    fn call(&self, method: &str, params: &Json) -> Result<Json, (i32, String)> {
+        if !METHODS.contains(&method) {
+            return Err((METHOD_NOT_FOUND, format!("unknown method: {method}")));
+        }
+        let invalid = |e: String| (INVALID_PARAMS, e);
+        let config = self.config_for(params).map_err(invalid)?;
+        let (rooms, rotations) = layout(params).map_err(invalid)?;
+        let overridden = OVERRIDES.iter().any(|key| params.get(key).is_some());
+        match method {
+            "verify" => {
+                let verification = if overridden {
+                    verify_rotated_rooms(&rooms, &rotations, &config)
+                } else {
+                    self.session.borrow_mut().verify(&rooms, &rotations)
+                };
+                let mut fields = verdict_fields(&verification.verdict);
+                fields.push(("stats".to_string(), stats_json(&verification.stats)));
+                Ok(Json::Object(fields))
+            }
+            "solve" => {
+                let result = if overridden {
+                    solve_rotated_rooms(&rooms, &rotations, &config)
+                } else {
+                    self.session.borrow_mut().solve(&rooms, &rotations)
+                };
+                let mut fields = verdict_fields(&result.verdict);
+                if let Some(solution) = result.solution {
+                    fields.push((
+                        "start_panel".to_string(),
+                        format!("{:?}", solution.start_panel).into(),
+                    ));
+                    fields.push((
+                        "operations".to_string(),
+                        operations_json(&solution.operations),
+                    ));
+                }
+                fields.push(("stats".to_string(), stats_json(&result.stats)));
+                Ok(Json::Object(fields))
+            }
+            "hint" => {
+                let state = replay(&rooms, &rotations, &config, params).map_err(invalid)?;
+                let finish = optimal_finish(&state, &config);
+                Ok(Json::object([
+                    (
+                        "remaining",
+                        finish.as_ref().map_or(Json::Null, |ops| ops.len().into()),
+                    ),
+                    (
+                        "next",
+                        finish
+                            .as_ref()
+                            .and_then(|ops| ops.first())
+                            .map_or(Json::Null, |op| format!("{op:?}").into()),
+                    ),
+                ]))
+            }
+            "apply-move" => {
+                let state = replay(&rooms, &rotations, &config, params).map_err(invalid)?;
+                let operation = params
+                    .get("operation")
+                    .and_then(Json::as_str)
+                    .ok_or_else(|| invalid("missing operation".to_string()))
+                    .and_then(|op| parse_operation(op).map_err(invalid))?;
+                let state = apply(&state, operation, &config)
+                    .map_err(|e| invalid(format!("{operation:?} can't be done: {e}")))?;
+                if let Some(on_state) = &self.on_state {
+                    let finish = optimal_finish(&state, &config);
+                    on_state(&overlay_message(
+                        state.rooms(),
+                        state.rotations(),
+                        state.tile(),
+                        state.gates(),
+                        finish.as_deref(),
+                    ));
+                }
+                Ok(state_json(&state))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn config_for(&self, params: &Json) -> Result<SolverConfig, String> {
+        config_with_overrides(&self.config, params)
+    }
+}
+
+/// `config` with the overrides in `params`, see the module docs
+pub(crate) fn config_with_overrides(
+    config: &SolverConfig,
+    params: &Json,
+) -> Result<SolverConfig, String> {
+    let mut config = config.clone();
+    if let Some(max_nodes) = params.get("max_nodes") {
+        config.max_nodes = Some(
+            max_nodes
+                .as_f64()
+                .filter(|n| *n >= 0.0)
+                .ok_or("invalid max_nodes")? as usize,
+        );
+    }
+    if let Some(timeout) = params.get("timeout") {
+        let seconds = timeout.as_f64().ok_or("invalid timeout")?;
+        config.timeout = Some(parse_timeout(&seconds.to_string())?);
+    }
+    if let Some(win_condition) = params.get("win_condition") {
+        config.win_condition =
+            parse_win_condition(win_condition.as_str().ok_or("invalid win_condition")?)?;
+    }
+    if let Some(strategy) = params.get("strategy") {
+        config.strategy = parse_strategy(strategy.as_str().ok_or("invalid strategy")?)?;
+    }
+    check_markers(&config)?;
+    Ok(config)
+}
+
+fn error_response(id: Json, code: i32, message: String) -> String {
+    Json::object([
+        ("jsonrpc", "2.0".into()),
+        (
+            "error",
+            Json::object([
+                ("code", Json::Number(code as f64)),
+                ("message", message.into()),
+            ]),
+        ),
+        ("id", id),
+    ])
+    .to_string()
+}
+
+fn layout(params: &Json) -> Result<([Room; 9], [Rotation; 9]), String> {
+    let code = params
+        .get("code")
+        .and_then(Json::as_str)
+        .ok_or("missing code")?;
+    parse_layout_code(code)
+}
+
+/// the state after the `operations` in the params
+fn replay(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    config: &SolverConfig,
+    params: &Json,
+) -> Result<SolveState, String> {
+    let mut state = SolveState::start(rooms, rotations, config)
+        .map_err(|verdict| format!("can't start the puzzle: {verdict:?}"))?;
+    let operations = match params.get("operations") {
+        Some(operations) => operations.as_array().ok_or("invalid operations")?,
+        None => &[],
+    };
+    for operation in operations {
+        let operation = parse_operation(operation.as_str().ok_or("invalid operation")?)?;
+        state = apply(&state, operation, config)
+            .map_err(|e| format!("{operation:?} can't be done: {e}"))?;
+    }
+    Ok(state)
+}
+
+pub(crate) fn verdict_fields(verdict: &Verdict) -> Vec<(String, Json)> {
+    let (name, reason) = match verdict {
+        Verdict::Beatable => ("beatable", None),
+        Verdict::NotBeatable(reason) => ("not-beatable", Some(reason.to_string())),
+        Verdict::Indeterminate(reason) => ("indeterminate", Some(reason.to_string())),
+        Verdict::ChainCycle(cycle) => ("chain-cycle", Some(cycle.to_string())),
+    };
+    let mut fields = vec![("verdict".to_string(), name.into())];
+    if let Some(reason) = reason {
+        fields.push(("reason".to_string(), reason.into()));
+    }
+    fields
+}
+
+pub(crate) fn operations_json(operations: &[Operations]) -> Json {
+    Json::Array(
+        operations
+            .iter()
+            .map(|op| format!("{op:?}").into())
+            .collect(),
+    )
+}
+
+/// tried, done and progress by kind of operation
+fn operation_counts_json(stats: &SearchStats) -> Json {
+    let counts = |counts: &OperationCounts| {
+        Json::object([
+            ("tried", counts.tried.into()),
+            ("done", counts.done.into()),
+            ("progress", counts.progress.into()),
+        ])
+    };
+    Json::object([
+        ("reach", counts(&stats.reach_counts)),
+        ("move", counts(&stats.move_counts)),
+        ("rotate", counts(&stats.rotate_counts)),
+    ])
+}
+
+pub(crate) fn stats_json(stats: &SearchStats) -> Json {
+    let seconds = |duration: Duration| Json::Number(duration.as_secs_f64());
+    let timings = cfg!(feature = "profile").then(|| {
+        (
+            "timings",
+            Json::object([
+                ("follow_chain", seconds(stats.timings.follow_chain)),
+                ("successors", seconds(stats.timings.successors)),
+                ("hashing", seconds(stats.timings.hashing)),
+            ]),
+        )
+    });
+    Json::object(
+        [
+            ("nodes_expanded", stats.nodes_expanded.into()),
+            ("states_visited", stats.states_visited.into()),
+            ("max_depth", stats.max_depth.into()),
+            ("mean_depth", Json::Number(stats.mean_depth())),
+            (
+                "effective_branching",
+                Json::Number(stats.effective_branching()),
+            ),
+            ("transposition_hits", stats.transposition_hits.into()),
+            ("follow_chain_calls", stats.follow_chain_calls.into()),
+            ("chain_cache_hits", stats.chain_cache_hits.into()),
+            ("operations", operation_counts_json(stats)),
+            ("elapsed", seconds(stats.elapsed)),
+        ]
+        .into_iter()
+        .chain(timings),
+    )
+}
+
+fn state_json(state: &SolveState) -> Json {
+    Json::object([
+        ("code", layout_code(state.rooms(), state.rotations()).into()),
+        ("tile", (state.tile() as usize).into()),
+        ("gates", gate_names(state.gates()).into()),
+        (
+            "reached",
+            Json::Array(
+                state
+                    .reached()
+                    .iter()
+                    .map(|entrance| format!("{entrance:?}").into())
+                    .collect(),
+            ),
+        ),
+    ])
+}
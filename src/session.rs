@@ -0,0 +1,108 @@
+//! Many verifications and solves with the same config, keeping what they
+//! learn in between instead of starting cold every time, for seed
+//! generation and the [RPC server](crate::rpc) that ask about one layout
+//! after another.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    enumerate::SolvableLayouts,
+    rooms::{Room, Rotation, NO_ROTATIONS},
+    solve::{solve_cached, SolveResult},
+    verify::{verify_cached, ChainCache, SearchStats, SolverConfig, Verdict, Verification},
+};
+
+/// Holds on to the walks around the board every search makes, the answers
+/// for the layouts already asked about and a database of solvable layouts
+/// if there's one. Answers that ran out of budget aren't kept, asking again
+/// searches again.
+///
+/// Everything kept grows with the layouts asked about, [`clear`] drops it.
+///
+/// [`clear`]: Session::clear
+pub struct Session {
+    config: SolverConfig,
+    chain_cache: ChainCache,
+    verdicts: HashMap<([Room; 9], [Rotation; 9]), Verdict>,
+    solutions: HashMap<([Room; 9], [Rotation; 9]), SolveResult>,
+    solvable: HashSet<[Room; 9]>,
+}
+
+impl Session {
+    pub fn new(config: SolverConfig) -> Self {
+        Session {
+            chain_cache: ChainCache::new(config.hasher),
+            config,
+            verdicts: HashMap::new(),
+            solutions: HashMap::new(),
+            solvable: HashSet::new(),
+        }
+    }
+
+    /// Answers unturned layouts in `layouts` as beatable without searching.
+    /// It has to be made with the same rules as the session's config, the
+    /// layouts that aren't in it are still verified, it can't tell the ones
+    /// that aren't beatable from the ones that weren't enumerated.
+    pub fn with_database(mut self, layouts: &SolvableLayouts) -> Self {
+        self.solvable
+            .extend((0..layouts.len()).filter_map(|index| layouts.get(index)));
+        self
+    }
+
+    pub fn config(&self) -> &SolverConfig {
+        &self.config
+    }
+
+    /// Like [`verify_rotated_rooms`](crate::verify::verify_rotated_rooms).
+    /// The stats are those of this call, empty if nothing had to be
+    /// searched.
+    pub fn verify(&mut self, rooms: &[Room; 9], rotations: &[Rotation; 9]) -> Verification {
+        let key = (*rooms, *rotations);
+        let known = self.verdicts.get(&key).cloned().or_else(|| {
+            let in_database = *rotations == NO_ROTATIONS && self.solvable.contains(rooms);
+            in_database.then_some(Verdict::Beatable)
+        });
+        if let Some(verdict) = known {
+            return Verification {
+                verdict,
+                stats: SearchStats::default(),
+            };
+        }
+        let verification = verify_cached(rooms, rotations, &self.config, &mut self.chain_cache);
+        if !matches!(verification.verdict, Verdict::Indeterminate(_)) {
+            self.verdicts.insert(key, verification.verdict.clone());
+        }
+        verification
+    }
+
+    /// Like [`solve_rotated_rooms`](crate::solve::solve_rotated_rooms), with
+    /// the stats of this call like [`verify`](Session::verify).
+    pub fn solve(&mut self, rooms: &[Room; 9], rotations: &[Rotation; 9]) -> SolveResult {
+        let key = (*rooms, *rotations);
+        if let Some(result) = self.solutions.get(&key) {
+            return SolveResult {
+                stats: SearchStats::default(),
+                ..result.clone()
+            };
+        }
+        let result = solve_cached(
+            rooms,
+            rotations,
+            &self.config,
+            &mut self.chain_cache,
+            &mut |_, _, _, _| {},
+        );
+        if !matches!(result.verdict, Verdict::Indeterminate(_)) {
+            self.verdicts.insert(key, result.verdict.clone());
+            self.solutions.insert(key, result.clone());
+        }
+        result
+    }
+
+    /// Forgets everything but the config and the database.
+    pub fn clear(&mut self) {
+        self.chain_cache = ChainCache::new(self.config.hasher);
+        self.verdicts.clear();
+        self.solutions.clear();
+    }
+}
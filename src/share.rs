@@ -0,0 +1,36 @@
+use crate::rooms::{Layout, Room, Rotation, ALL_ROOMS, NO_ROTATIONS};
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// A short code to share a layout with, one url safe base64 character per
+/// tile holding the room and its rotation.
+pub fn layout_code(rooms: &[Room; 9], rotations: &[Rotation; 9]) -> String {
+    rooms
+        .iter()
+        .zip(rotations)
+        .map(|(room, rotation)| ALPHABET[(*room as usize) << 2 | *rotation as usize] as char)
+        .collect()
+}
+
+/// Reads a code made by [`layout_code`], `Err` for codes of boards that
+/// can't be played, see [`Layout`].
+pub fn parse_layout_code(code: &str) -> Result<([Room; 9], [Rotation; 9]), String> {
+    let code = code.trim();
+    if code.len() != 9 {
+        return Err(format!("layout codes are 9 characters, got {}", code.len()));
+    }
+    let rotation_list: Vec<Rotation> = enum_iterator::all::<Rotation>().collect();
+    let mut rooms = ALL_ROOMS;
+    let mut rotations = NO_ROTATIONS;
+    for (tile, c) in code.bytes().enumerate() {
+        let value = ALPHABET
+            .iter()
+            .position(|a| *a == c)
+            .ok_or_else(|| format!("invalid character in layout code: {}", c as char))?;
+        rooms[tile] = *ALL_ROOMS
+            .get(value >> 2)
+            .ok_or_else(|| format!("invalid room in layout code: {}", c as char))?;
+        rotations[tile] = rotation_list[value & 3];
+    }
+    Layout::new(rooms, rotations).map(Layout::into_parts)
+}
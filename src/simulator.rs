@@ -0,0 +1,114 @@
+//! Playing the puzzle one operation at a time, telling listeners what
+//! happened instead of leaving them to compare states.
+
+use crate::{
+    chain::ChainCycle,
+    rooms::{Entrance, OpenedGates, Room, Rotation},
+    solve::SolveState,
+    verify::{Operations, SolverConfig, Verdict},
+};
+
+type Listener<T> = Box<dyn FnMut(T)>;
+
+/// Owns the state of a playthrough and calls the listeners for everything
+/// an operation changes. Nothing is reported for the state the board starts
+/// in.
+pub struct Simulator {
+    state: SolveState,
+    config: SolverConfig,
+    gate_opened: Vec<Listener<OpenedGates>>,
+    entrance_reached: Vec<Listener<Entrance>>,
+    slide: Vec<Listener<Slide>>,
+}
+
+/// A room that was slid into the empty tile next to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Slide {
+    pub room: Room,
+    pub from: u8,
+    pub to: u8,
+}
+
+impl Simulator {
+    /// right after entering the board, like [`SolveState::start`]
+    pub fn new(
+        rooms: &[Room; 9],
+        rotations: &[Rotation; 9],
+        config: SolverConfig,
+    ) -> Result<Self, Verdict> {
+        Ok(Simulator {
+            state: SolveState::start(rooms, rotations, &config)?,
+            config,
+            gate_opened: Vec::new(),
+            entrance_reached: Vec::new(),
+            slide: Vec::new(),
+        })
+    }
+
+    pub fn state(&self) -> &SolveState {
+        &self.state
+    }
+
+    /// called with every gate that is open after an operation but wasn't
+    /// before, one at a time
+    pub fn on_gate_opened(&mut self, f: impl FnMut(OpenedGates) + 'static) {
+        self.gate_opened.push(Box::new(f));
+    }
+
+    /// called with every entrance the win condition asks for when it's
+    /// reached for the first time
+    pub fn on_entrance_reached(&mut self, f: impl FnMut(Entrance) + 'static) {
+        self.entrance_reached.push(Box::new(f));
+    }
+
+    /// called for every room that is slid
+    pub fn on_slide(&mut self, f: impl FnMut(Slide) + 'static) {
+        self.slide.push(Box::new(f));
+    }
+
+    /// Does the operation and calls the listeners, `Ok(false)` if it can't
+    /// be done, which leaves the state as it was.
+    pub fn apply(&mut self, operation: Operations) -> Result<bool, ChainCycle> {
+        let Some(next) = self.state.after(operation, &self.config)? else {
+            return Ok(false);
+        };
+        let before = std::mem::replace(&mut self.state, next);
+        let after = &self.state;
+
+        if let Operations::Move(..) = operation {
+            // the room and the empty tile swapped places
+            let changed: Vec<usize> = (0..9)
+                .filter(|tile| before.rooms()[*tile] != after.rooms()[*tile])
+                .collect();
+            if let [a, b] = changed[..] {
+                let (from, to) = if after.rooms()[a] == Room::Empty {
+                    (a, b)
+                } else {
+                    (b, a)
+                };
+                let slide = Slide {
+                    room: after.rooms()[to],
+                    from: from as u8,
+                    to: to as u8,
+                };
+                for listener in &mut self.slide {
+                    listener(slide);
+                }
+            }
+        }
+        for gate in after.gates().difference(before.gates()).iter() {
+            for listener in &mut self.gate_opened {
+                listener(gate);
+            }
+        }
+        let reached_before = before.reached();
+        for entrance in after.reached() {
+            if !reached_before.contains(&entrance) {
+                for listener in &mut self.entrance_reached {
+                    listener(entrance);
+                }
+            }
+        }
+        Ok(true)
+    }
+}
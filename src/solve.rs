@@ -0,0 +1,1715 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, VecDeque},
+    future::Future,
+    io::{self, Write},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Mutex, MutexGuard, PoisonError,
+    },
+    task::{Context, Poll},
+    thread,
+    time::Instant,
+};
+
+use crate::{
+    chain::ChainCycle,
+    heuristic::estimate,
+    rooms::{
+        do_move, gate_names, layout_notation, ControlPanel, Direction, Entrance, OpenedGates, Room,
+        Rotation, NO_ROTATIONS,
+    },
+    store::{mix, pack, StateHasher},
+    verify::{
+        empty_tile, look_around, out_of_budget, perform, start_state, state_at, ChainCache,
+        EmptySlot, Operations, RandomProbes, RoomAndPos, SearchStats, SearchStrategy, SolverConfig,
+        Timer, Verdict,
+    },
+};
+
+/// A single sequence of operations that reaches everything the win
+/// condition asks for, starting at the first control panel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Solution {
+    /// the panel the player walks to after entering the board
+    pub start_panel: ControlPanel,
+    pub operations: Vec<Operations>,
+}
+
+impl Solution {
+    /// the control panel every slide or turn of a room is done from
+    pub fn panel_per_operation(&self) -> impl Iterator<Item = ControlPanel> + '_ {
+        let mut panel = self.start_panel;
+        self.operations
+            .iter()
+            .filter_map(move |operation| match operation {
+                Operations::Reach(reached) => {
+                    panel = *reached;
+                    None
+                }
+                Operations::Move(..) | Operations::Rotate(..) => Some(panel),
+            })
+    }
+
+    /// If it starts at the right panel, every operation can be done in order
+    /// and everything the win condition asks for is reached on the way.
+    pub fn is_valid(
+        &self,
+        rooms: &[Room; 9],
+        rotations: &[Rotation; 9],
+        config: &SolverConfig,
+    ) -> bool {
+        let stats = &mut SearchStats::default();
+        let Ok((mut state, start_panel)) = first_state(rooms, rotations, config, stats) else {
+            return false;
+        };
+        if start_panel != self.start_panel {
+            return false;
+        }
+        let targets = target_mask(rooms, config);
+        for operation in &self.operations {
+            match step(&state, *operation, targets, config, stats) {
+                Ok(Some(next)) => state = next,
+                _ => return false,
+            }
+        }
+        state.reached == targets
+    }
+
+    /// Drops runs of operations or pairs of them, like a slide there and
+    /// back, as long as the rest is still a valid solution.
+    pub fn minimize(
+        &self,
+        rooms: &[Room; 9],
+        rotations: &[Rotation; 9],
+        config: &SolverConfig,
+    ) -> Solution {
+        let mut best = self.clone();
+        'shorter: loop {
+            let len = best.operations.len();
+            // longest runs first to get rid of whole detours at once
+            let runs = (1..=len)
+                .rev()
+                .flat_map(|run| (0..=len - run).map(move |i| (i..i + run, None)));
+            let pairs = (0..len).flat_map(|i| (i + 2..len).map(move |j| (i..i + 1, Some(j))));
+            for (run, j) in runs.chain(pairs) {
+                let mut candidate = best.clone();
+                if let Some(j) = j {
+                    candidate.operations.remove(j);
+                }
+                candidate.operations.drain(run);
+                if candidate.is_valid(rooms, rotations, config) {
+                    best = candidate;
+                    continue 'shorter;
+                }
+            }
+            return best;
+        }
+    }
+
+    /// how often each panel is used to slide or turn rooms, panels that are
+    /// never used are left out
+    pub fn panels_used(&self) -> Vec<(ControlPanel, usize)> {
+        enum_iterator::all::<ControlPanel>()
+            .map(|panel| {
+                let count = self.panel_per_operation().filter(|p| *p == panel).count();
+                (panel, count)
+            })
+            .filter(|(_, count)| *count > 0)
+            .collect()
+    }
+
+    /// The gates in the order the solution opens them, the ones a single
+    /// operation opens together in one set. Gates that are open from the
+    /// start aren't in it, gates that close again count the first time.
+    /// Empty if the solution isn't valid.
+    pub fn gate_order(
+        &self,
+        rooms: &[Room; 9],
+        rotations: &[Rotation; 9],
+        config: &SolverConfig,
+    ) -> Vec<OpenedGates> {
+        // the irrelevant gates are opened too
+        let config = &SolverConfig {
+            keep_irrelevant_gates: true,
+            ..config.clone()
+        };
+        let stats = &mut SearchStats::default();
+        let Ok((mut state, _)) = first_state(rooms, rotations, config, stats) else {
+            return Vec::new();
+        };
+        let targets = target_mask(rooms, config);
+        let mut opened = config.initial_gates;
+        let mut order = Vec::new();
+        for operation in &self.operations {
+            let Ok(Some(next)) = step(&state, *operation, targets, config, stats) else {
+                return Vec::new();
+            };
+            state = next;
+            let new = state.gates.difference(opened);
+            if !new.is_empty() {
+                order.push(new);
+                opened |= new;
+            }
+        }
+        order
+    }
+}
+
+/// A rule on the order a solution opens gates in, see
+/// [`Solution::gate_order`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GateOrder {
+    /// opened on its own, before any other gate
+    First(OpenedGates),
+    /// opened on its own, after every other gate that's opened
+    Last(OpenedGates),
+    /// both are opened, the first with an earlier operation
+    Before(OpenedGates, OpenedGates),
+}
+
+impl GateOrder {
+    /// if it holds for an order from [`Solution::gate_order`]
+    pub fn holds(&self, order: &[OpenedGates]) -> bool {
+        let position = |gate: OpenedGates| order.iter().position(|opened| opened.contains(gate));
+        match *self {
+            GateOrder::First(gate) => order.first() == Some(&gate),
+            GateOrder::Last(gate) => order.last() == Some(&gate),
+            GateOrder::Before(first, second) => {
+                matches!((position(first), position(second)), (Some(a), Some(b)) if a < b)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SolveResult {
+    pub verdict: Verdict,
+    /// a solution if the layout is beatable, the shortest one unless
+    /// [`SearchStrategy::BestFirst`], [`SearchStrategy::DepthFirst`],
+    /// iterative deepening with bounds that skip lengths or a
+    /// [`SolverConfig::heuristic`] that estimates more operations than are
+    /// left is used
+    pub solution: Option<Solution>,
+    pub stats: SearchStats,
+}
+
+pub fn solve_rooms(rooms: &[Room; 9], config: &SolverConfig) -> SolveResult {
+    solve_rotated_rooms(rooms, &NO_ROTATIONS, config)
+}
+
+/// Searches for an operation sequence, breadth first for a shortest one by
+/// default. Unlike the verifier, which is happy if every entrance is reachable
+/// on some branch, this needs everything to be reached along one path, so the
+/// entrances reached so far are part of the searched state.
+pub fn solve_rotated_rooms(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    config: &SolverConfig,
+) -> SolveResult {
+    solve_cached(
+        rooms,
+        rotations,
+        config,
+        &mut ChainCache::new(config.hasher),
+        &mut |_, _, _, _| {},
+    )
+}
+
+/// Solves the layout like [`solve_rotated_rooms`] and writes every edge of
+/// the search tree to `out` as a line of JSON, in the order they were found.
+/// States get ids in the order they were found, starting at 0 for the first
+/// panel. An edge to a state that was already found has `"duplicate": true`
+/// and the id of that state, and isn't explored again unless iterative
+/// deepening starts over with a higher bound or A* found it on a shorter
+/// path.
+pub fn export_search_tree(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    config: &SolverConfig,
+    out: &mut impl Write,
+) -> io::Result<SolveResult> {
+    let mut ids: HashMap<SolveState, (usize, usize)> = HashMap::new();
+    let mut error = None;
+    // the edges have to come out of a single search
+    let config = &SolverConfig {
+        threads: 1,
+        probes: RandomProbes::default(),
+        ..config.clone()
+    };
+    let result = solve_cached(
+        rooms,
+        rotations,
+        config,
+        &mut ChainCache::new(config.hasher),
+        &mut |parent, operation, state, new| {
+            if error.is_some() {
+                return;
+            }
+            let mut lines = Vec::new();
+            if ids.is_empty() {
+                ids.insert(*parent, (0, 0));
+                lines.push(format!(
+                    "{{\"id\":0,\"parent\":null,\"operation\":null,{}}}",
+                    state_json(parent, 0)
+                ));
+            }
+            let (parent_id, parent_depth) = ids[parent];
+            if new {
+                let id = ids.len();
+                ids.insert(*state, (id, parent_depth + 1));
+                lines.push(format!(
+                    "{{\"id\":{id},\"parent\":{parent_id},\"operation\":\"{operation:?}\",{}}}",
+                    state_json(state, parent_depth + 1)
+                ));
+            } else {
+                lines.push(format!(
+                    "{{\"id\":{},\"parent\":{parent_id},\"operation\":\"{operation:?}\",\"duplicate\":true}}",
+                    ids[state].0
+                ));
+            }
+            for line in lines {
+                if let Err(e) = writeln!(out, "{line}") {
+                    error = Some(e);
+                    return;
+                }
+            }
+        },
+    );
+    match error {
+        Some(e) => Err(e),
+        None => Ok(result),
+    }
+}
+
+/// Solves the layout like [`export_search_tree`] but writes the states and
+/// edges the search found to `out` as GraphML, for graph tools like Gephi or
+/// NetworkX. Edges to states that were found before are kept, so it's the
+/// graph of the explored part of the state space rather than a tree. Nodes
+/// have the layout, the tile of the player, the depth they were found at and
+/// the open gates and reached entrances, also as counts.
+pub fn export_search_graphml(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    config: &SolverConfig,
+    out: &mut impl Write,
+) -> io::Result<SolveResult> {
+    const KEYS: [(&str, &str, &str); 8] = [
+        ("node", "layout", "string"),
+        ("node", "tile", "int"),
+        ("node", "depth", "int"),
+        ("node", "gates", "string"),
+        ("node", "gates_open", "int"),
+        ("node", "reached", "string"),
+        ("node", "reached_count", "int"),
+        ("edge", "operation", "string"),
+    ];
+    writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(
+        out,
+        "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">"
+    )?;
+    for (kind, name, ty) in KEYS {
+        writeln!(
+            out,
+            "  <key id=\"{name}\" for=\"{kind}\" attr.name=\"{name}\" attr.type=\"{ty}\"/>"
+        )?;
+    }
+    writeln!(out, "  <graph id=\"search\" edgedefault=\"directed\">")?;
+    let mut ids: HashMap<SolveState, (usize, usize)> = HashMap::new();
+    let mut error = None;
+    let config = &SolverConfig {
+        threads: 1,
+        probes: RandomProbes::default(),
+        ..config.clone()
+    };
+    let result = solve_cached(
+        rooms,
+        rotations,
+        config,
+        &mut ChainCache::new(config.hasher),
+        &mut |parent, operation, state, new| {
+            if error.is_some() {
+                return;
+            }
+            let mut lines = Vec::new();
+            if ids.is_empty() {
+                ids.insert(*parent, (0, 0));
+                lines.push(graphml_node(0, parent, 0));
+            }
+            let (parent_id, parent_depth) = ids[parent];
+            if new {
+                let id = ids.len();
+                ids.insert(*state, (id, parent_depth + 1));
+                lines.push(graphml_node(id, state, parent_depth + 1));
+            }
+            lines.push(format!(
+                "    <edge source=\"n{parent_id}\" target=\"n{}\"><data key=\"operation\">{operation:?}</data></edge>",
+                ids[state].0
+            ));
+            for line in lines {
+                if let Err(e) = writeln!(out, "{line}") {
+                    error = Some(e);
+                    return;
+                }
+            }
+        },
+    );
+    if let Some(e) = error {
+        return Err(e);
+    }
+    writeln!(out, "  </graph>\n</graphml>")?;
+    Ok(result)
+}
+
+fn graphml_node(id: usize, state: &SolveState, depth: usize) -> String {
+    let reached: Vec<String> = state
+        .reached()
+        .into_iter()
+        .map(|entrance| format!("{entrance:?}"))
+        .collect();
+    let data = [
+        ("layout", layout_notation(&state.room_pos.rooms)),
+        ("tile", state.room_pos.pos_tile.to_string()),
+        ("depth", depth.to_string()),
+        ("gates", gate_names(state.gates)),
+        ("gates_open", state.gates.iter().count().to_string()),
+        ("reached", reached.join(" ")),
+        ("reached_count", reached.len().to_string()),
+    ];
+    let data: Vec<String> = data
+        .iter()
+        .map(|(key, value)| format!("<data key=\"{key}\">{value}</data>"))
+        .collect();
+    format!("    <node id=\"n{id}\">{}</node>", data.concat())
+}
+
+/// the fields of a state in the search tree export
+fn state_json(state: &SolveState, depth: usize) -> String {
+    let reached: Vec<String> = state
+        .reached()
+        .into_iter()
+        .map(|entrance| format!("\"{entrance:?}\""))
+        .collect();
+    format!(
+        "\"depth\":{depth},\"layout\":\"{}\",\"tile\":{},\"gates\":\"{}\",\"reached\":[{}]",
+        layout_notation(&state.room_pos.rooms),
+        state.room_pos.pos_tile,
+        gate_names(state.gates),
+        reached.join(",")
+    )
+}
+
+/// Solves with the walks around kept in `chain_cache`. `on_edge` is called
+/// with the parent, the operation, the state it leads to and if that state
+/// is new for every edge of the search tree
+pub(crate) fn solve_cached(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    config: &SolverConfig,
+    chain_cache: &mut ChainCache,
+    on_edge: OnEdge,
+) -> SolveResult {
+    let start_time = Instant::now();
+    let mut stats = SearchStats::default();
+    let (verdict, solution) = solve_inner(
+        rooms,
+        rotations,
+        config,
+        &mut stats,
+        start_time,
+        chain_cache,
+        on_edge,
+    );
+    stats.elapsed = start_time.elapsed();
+    SolveResult {
+        verdict,
+        solution,
+        stats,
+    }
+}
+
+type OnEdge<'a> = &'a mut dyn FnMut(&SolveState, Operations, &SolveState, bool);
+
+/// Where a playthrough is at: the board, the panel the player stands at, the
+/// open gates and which of the entrances the win condition asks for were
+/// reached so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SolveState {
+    room_pos: RoomAndPos,
+    gates: OpenedGates,
+    pub(crate) reached: u32,
+}
+
+impl SolveState {
+    /// right after entering the board and walking to the first panel
+    pub fn start(
+        rooms: &[Room; 9],
+        rotations: &[Rotation; 9],
+        config: &SolverConfig,
+    ) -> Result<Self, Verdict> {
+        first_state(rooms, rotations, config, &mut SearchStats::default()).map(|(state, _)| state)
+    }
+
+    /// the player standing at the door of `tile` facing `direction`
+    /// somewhere on the board, after walking to the first panel from there
+    pub fn at(
+        rooms: &[Room; 9],
+        rotations: &[Rotation; 9],
+        config: &SolverConfig,
+        tile: u8,
+        direction: Direction,
+    ) -> Result<Self, Verdict> {
+        let (state, _) = state_at(
+            rooms,
+            rotations,
+            config,
+            &mut SearchStats::default(),
+            tile,
+            direction,
+        )?;
+        Ok(solve_state(state, config))
+    }
+
+    /// the state after the operation, `None` if it can't be done
+    pub fn after(
+        &self,
+        operation: Operations,
+        config: &SolverConfig,
+    ) -> Result<Option<Self>, ChainCycle> {
+        let targets = target_mask(&self.room_pos.rooms, config);
+        step(
+            self,
+            operation,
+            targets,
+            config,
+            &mut SearchStats::default(),
+        )
+    }
+
+    pub fn rooms(&self) -> &[Room; 9] {
+        &self.room_pos.rooms
+    }
+
+    pub fn rotations(&self) -> &[Rotation; 9] {
+        &self.room_pos.rotations
+    }
+
+    /// the tile of the panel the player stands at
+    pub fn tile(&self) -> u8 {
+        self.room_pos.pos_tile
+    }
+
+    pub fn gates(&self) -> OpenedGates {
+        self.gates
+    }
+
+    /// the entrances the win condition asks for that were reached so far
+    pub fn reached(&self) -> Vec<Entrance> {
+        enum_iterator::all::<Entrance>()
+            .filter(|entrance| self.reached & 1 << *entrance as u32 != 0)
+            .collect()
+    }
+
+    /// how promising the state is for [`SearchStrategy::BestFirst`]
+    fn progress(&self) -> u32 {
+        self.reached.count_ones() + self.gates.bits().count_ones()
+    }
+}
+
+/// Why [`apply`] couldn't do an operation. New rules can bring new reasons,
+/// so matches need a catch-all arm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MoveError {
+    /// no way leads from the player's panel to this one
+    PanelUnreachable(ControlPanel),
+    /// the player's panel is in [`SolverConfig::locked_panels`]
+    PanelLocked(ControlPanel),
+    /// the board has fewer empty tiles than the slot needs
+    NoEmptyTile(EmptySlot),
+    /// the room to slide or turn would come from outside the board
+    OffBoard,
+    /// the room to slide or turn is the one the player stands in
+    DisplacesPlayer,
+    /// the tile next to the empty one is empty too
+    NothingToSlide,
+    /// turning rooms needs [`SolverConfig::rotatable_rooms`]
+    RotationDisabled,
+    ChainCycle(ChainCycle),
+}
+
+impl std::fmt::Display for MoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MoveError::PanelUnreachable(panel) => write!(f, "the {panel:?} panel can't be reached"),
+            MoveError::PanelLocked(panel) => write!(f, "the {panel:?} panel is locked"),
+            MoveError::NoEmptyTile(slot) => write!(f, "there is no {slot:?} empty tile"),
+            MoveError::OffBoard => write!(f, "there is no room on that side of the empty tile"),
+            MoveError::DisplacesPlayer => write!(f, "the player stands in that room"),
+            MoveError::NothingToSlide => write!(f, "that tile is empty as well"),
+            MoveError::RotationDisabled => write!(f, "rooms can't be turned"),
+            MoveError::ChainCycle(cycle) => cycle.fmt(f),
+        }
+    }
+}
+
+/// Does one operation like [`SolveState::after`], but with the reason it
+/// can't be done instead of `None`, for playing, checking replays and bots.
+pub fn apply(
+    state: &SolveState,
+    operation: Operations,
+    config: &SolverConfig,
+) -> Result<SolveState, MoveError> {
+    match state.after(operation, config) {
+        Ok(Some(next)) => Ok(next),
+        Ok(None) => Err(why_not(state, operation, config)),
+        Err(cycle) => Err(MoveError::ChainCycle(cycle)),
+    }
+}
+
+/// the reason for an operation [`SolveState::after`] couldn't do
+fn why_not(state: &SolveState, operation: Operations, config: &SolverConfig) -> MoveError {
+    // slides and turns are refused at a locked panel before anything else
+    let at = state.rooms()[state.tile() as usize];
+    if let Some(&panel) = config.locked_panels.iter().find(|panel| panel.room() == at) {
+        if !matches!(operation, Operations::Reach(_)) {
+            return MoveError::PanelLocked(panel);
+        }
+    }
+    let (slot, direction) = match operation {
+        Operations::Reach(panel) => return MoveError::PanelUnreachable(panel),
+        Operations::Rotate(..) if !config.rotatable_rooms => return MoveError::RotationDisabled,
+        Operations::Move(slot, direction) | Operations::Rotate(slot, direction) => {
+            (slot, direction)
+        }
+    };
+    let Some(empty_tile) = empty_tile(state.rooms(), slot) else {
+        return MoveError::NoEmptyTile(slot);
+    };
+    match do_move(empty_tile, direction) {
+        None => MoveError::OffBoard,
+        Some((tile, _)) if tile == state.tile() => MoveError::DisplacesPlayer,
+        Some(_) => MoveError::NothingToSlide,
+    }
+}
+
+/// Every state a search found, with the one it was found from and the
+/// operation that led there. States are kept once, in the order they were
+/// found, and referred to by their index in there everywhere else.
+struct SearchTree {
+    nodes: Vec<(SolveState, Option<(usize, Operations)>)>,
+    ids: HashMap<SolveState, usize, StateHasher>,
+}
+
+impl SearchTree {
+    fn new(root: SolveState, hasher: StateHasher) -> Self {
+        let mut ids = HashMap::with_hasher(hasher);
+        ids.insert(root, 0);
+        SearchTree {
+            nodes: vec![(root, None)],
+            ids,
+        }
+    }
+
+    fn state(&self, id: usize) -> &SolveState {
+        &self.nodes[id].0
+    }
+
+    fn contains(&self, state: &SolveState) -> bool {
+        self.ids.contains_key(state)
+    }
+
+    fn id(&self, state: &SolveState) -> Option<usize> {
+        self.ids.get(state).copied()
+    }
+
+    /// adds a state that wasn't found before, returns its id
+    fn insert(&mut self, state: SolveState, parent: usize, operation: Operations) -> usize {
+        let id = self.nodes.len();
+        self.ids.insert(state, id);
+        self.nodes.push((state, Some((parent, operation))));
+        id
+    }
+
+    /// found again on a shorter path, from `parent` with `operation`
+    fn reparent(&mut self, id: usize, parent: usize, operation: Operations) {
+        self.nodes[id].1 = Some((parent, operation));
+    }
+
+    fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// the operations from the root to the state
+    fn path(&self, mut id: usize) -> Vec<Operations> {
+        let mut operations = Vec::new();
+        while let Some((parent, operation)) = self.nodes[id].1 {
+            operations.push(operation);
+            id = parent;
+        }
+        operations.reverse();
+        operations
+    }
+}
+
+/// The ids of the states that still have to be expanded with their depth, in
+/// the order of the strategy.
+enum Frontier {
+    Queue(VecDeque<(usize, usize)>),
+    Stack(Vec<(usize, usize)>),
+    /// ties are broken by the order states were found in, which is their id
+    Heap(BinaryHeap<(u32, Reverse<usize>, usize)>),
+    /// by the depth plus the estimate, the lowest first
+    Estimated(BinaryHeap<(Reverse<usize>, Reverse<usize>, usize)>),
+}
+
+impl Frontier {
+    fn new(strategy: SearchStrategy) -> Self {
+        match strategy {
+            SearchStrategy::BreadthFirst => Frontier::Queue(VecDeque::new()),
+            SearchStrategy::BestFirst => Frontier::Heap(BinaryHeap::new()),
+            SearchStrategy::DepthFirst => Frontier::Stack(Vec::new()),
+            SearchStrategy::AStar => Frontier::Estimated(BinaryHeap::new()),
+            SearchStrategy::IterativeDeepening => {
+                unreachable!("iterative deepening has no frontier")
+            }
+        }
+    }
+
+    /// `estimate` is only used by A*
+    fn push(&mut self, id: usize, state: &SolveState, depth: usize, estimate: u32) {
+        match self {
+            Frontier::Queue(queue) => queue.push_back((id, depth)),
+            Frontier::Stack(stack) => stack.push((id, depth)),
+            Frontier::Heap(heap) => heap.push((state.progress(), Reverse(id), depth)),
+            Frontier::Estimated(heap) => {
+                heap.push((Reverse(depth + estimate as usize), Reverse(id), depth))
+            }
+        }
+    }
+
+    fn pop(&mut self) -> Option<(usize, usize)> {
+        match self {
+            Frontier::Queue(queue) => queue.pop_front(),
+            Frontier::Stack(stack) => stack.pop(),
+            Frontier::Heap(heap) => heap.pop().map(|(_, Reverse(id), depth)| (id, depth)),
+            Frontier::Estimated(heap) => heap.pop().map(|(_, Reverse(id), depth)| (id, depth)),
+        }
+    }
+}
+
+fn solve_inner(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    config: &SolverConfig,
+    stats: &mut SearchStats,
+    start_time: Instant,
+    chain_cache: &mut ChainCache,
+    on_edge: OnEdge,
+) -> (Verdict, Option<Solution>) {
+    let (start, start_panel) = match first_state(rooms, rotations, config, stats) {
+        Ok(start) => start,
+        Err(verdict) => return (verdict, None),
+    };
+    let (verdict, operations) = search(start, config, stats, start_time, chain_cache, on_edge);
+    let solution = operations.map(|operations| Solution {
+        start_panel,
+        operations,
+    });
+    (verdict, solution)
+}
+
+/// How many operations an optimal finish from `state` takes, `None` if the
+/// win condition can't be met from there anymore or the search runs out of
+/// budget. Always searches breadth first.
+pub fn optimal_remaining(state: &SolveState, config: &SolverConfig) -> Option<u32> {
+    optimal_finish(state, config).map(|operations| operations.len() as u32)
+}
+
+/// the operations of an optimal finish from `state`, see
+/// [`optimal_remaining`]
+pub fn optimal_finish(state: &SolveState, config: &SolverConfig) -> Option<Vec<Operations>> {
+    let config = SolverConfig {
+        strategy: SearchStrategy::BreadthFirst,
+        ..config.clone()
+    };
+    let (_, operations) = search(
+        *state,
+        &config,
+        &mut SearchStats::default(),
+        Instant::now(),
+        &mut ChainCache::new(config.hasher),
+        &mut |_, _, _, _| {},
+    );
+    operations
+}
+
+/// a state with its depth and all the states on the level before it that
+/// lead there, with the operation
+type LevelNode = (SolveState, usize, Vec<(usize, Operations)>);
+
+/// Every shortest solution, or up to `limit` of them.
+#[derive(Debug, Clone)]
+pub struct AllOptimal {
+    pub verdict: Verdict,
+    /// all of the same length, in the order the search found their last
+    /// states
+    pub solutions: Vec<Solution>,
+    /// if there are more than `limit` shortest solutions
+    pub truncated: bool,
+    pub stats: SearchStats,
+}
+
+/// Searches breadth first like [`solve_rotated_rooms`] but finishes the
+/// level the first solution is on and keeps every way a state was reached
+/// on the level before it, to get all shortest solutions instead of one.
+/// Operations that lead from the same state to the same state, like sliding
+/// a room into either of two empty tiles that end up the same, count once,
+/// so every solution goes through a different sequence of states.
+pub fn all_optimal_solutions(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    config: &SolverConfig,
+    limit: usize,
+) -> AllOptimal {
+    let start_time = Instant::now();
+    let mut stats = SearchStats::default();
+    let mut result = AllOptimal {
+        verdict: Verdict::NotBeatable("unreachable entrances"),
+        solutions: Vec::new(),
+        truncated: false,
+        stats: SearchStats::default(),
+    };
+    let (start, start_panel) = match first_state(rooms, rotations, config, &mut stats) {
+        Ok(start) => start,
+        Err(verdict) => {
+            result.verdict = verdict;
+            return result;
+        }
+    };
+    let targets = target_mask(rooms, config);
+    let mut nodes: Vec<LevelNode> = vec![(start, 0, Vec::new())];
+    let mut ids: HashMap<SolveState, usize, StateHasher> = HashMap::with_hasher(config.hasher);
+    ids.insert(start, 0);
+    let mut level = vec![0];
+    let mut goals = Vec::new();
+    if nodes[0].0.reached == targets {
+        goals.push(0);
+    }
+    'levels: while goals.is_empty() && !level.is_empty() {
+        let mut next_level = Vec::new();
+        for id in level {
+            if let Some(verdict) = out_of_budget(config, &stats, start_time) {
+                result.verdict = verdict;
+                break 'levels;
+            }
+            let (state, depth) = (nodes[id].0, nodes[id].1);
+            stats.expanded(depth);
+            for operation in enum_iterator::all::<Operations>() {
+                let new_state = match step(&state, operation, targets, config, &mut stats) {
+                    Ok(Some(new_state)) => new_state,
+                    Ok(None) => continue,
+                    Err(cycle) => {
+                        result.verdict = Verdict::ChainCycle(cycle);
+                        break 'levels;
+                    }
+                };
+                match ids.get(&new_state) {
+                    Some(&known) => {
+                        stats.transposition_hits += 1;
+                        let (_, known_depth, parents) = &mut nodes[known];
+                        if *known_depth == depth + 1 && parents.iter().all(|(p, _)| *p != id) {
+                            parents.push((id, operation));
+                        }
+                    }
+                    None => {
+                        let new_id = nodes.len();
+                        if new_state.reached == targets {
+                            goals.push(new_id);
+                        }
+                        ids.insert(new_state, new_id);
+                        nodes.push((new_state, depth + 1, vec![(id, operation)]));
+                        next_level.push(new_id);
+                    }
+                }
+            }
+        }
+        level = next_level;
+    }
+    stats.states_visited = nodes.len();
+    if !goals.is_empty() {
+        result.verdict = Verdict::Beatable;
+        // walks back from the goals through every parent, depth first
+        let mut paths: Vec<(usize, Vec<Operations>)> =
+            goals.iter().rev().map(|goal| (*goal, Vec::new())).collect();
+        while let Some((id, operations)) = paths.pop() {
+            if nodes[id].2.is_empty() {
+                if result.solutions.len() == limit {
+                    result.truncated = true;
+                    break;
+                }
+                let mut operations = operations;
+                operations.reverse();
+                result.solutions.push(Solution {
+                    start_panel,
+                    operations,
+                });
+                continue;
+            }
+            for (parent, operation) in nodes[id].2.iter().rev() {
+                let mut operations = operations.clone();
+                operations.push(*operation);
+                paths.push((*parent, operations));
+            }
+        }
+    }
+    stats.elapsed = start_time.elapsed();
+    result.stats = stats;
+    result
+}
+
+/// the operations from `start` to a state that reached all targets
+fn search(
+    start: SolveState,
+    config: &SolverConfig,
+    stats: &mut SearchStats,
+    start_time: Instant,
+    chain_cache: &mut ChainCache,
+    on_edge: OnEdge,
+) -> (Verdict, Option<Vec<Operations>>) {
+    let targets = target_mask(&start.room_pos.rooms, config);
+    if start.reached == targets {
+        return (Verdict::Beatable, Some(Vec::new()));
+    }
+    if let Some(operations) = random_probes(start, targets, config, stats, start_time) {
+        return (Verdict::Beatable, Some(operations));
+    }
+    if config.threads > 1 && config.strategy == SearchStrategy::BreadthFirst {
+        return parallel_search(start, targets, config, stats, start_time);
+    }
+    if config.strategy == SearchStrategy::IterativeDeepening {
+        return deepening_search(
+            start,
+            targets,
+            config,
+            stats,
+            start_time,
+            chain_cache,
+            on_edge,
+        );
+    }
+    let mut search = FrontierSearch::new(start, targets, config);
+    loop {
+        if let Some(done) = search.run(config, stats, start_time, chain_cache, on_edge, usize::MAX)
+        {
+            return done;
+        }
+    }
+}
+
+/// The search of [`search`] with a frontier, which can stop after any
+/// number of expanded states and go on from there later.
+struct FrontierSearch {
+    targets: u32,
+    frontier: Frontier,
+    tree: SearchTree,
+    /// the fewest operations each state was found with so far, by id. A*
+    /// reopens a state found on a shorter path and skips the entries it
+    /// left in the frontier with more
+    depths: Vec<usize>,
+}
+
+impl FrontierSearch {
+    fn new(start: SolveState, targets: u32, config: &SolverConfig) -> Self {
+        let mut frontier = Frontier::new(config.strategy);
+        frontier.push(0, &start, 0, estimate(config, &start, targets));
+        FrontierSearch {
+            targets,
+            frontier,
+            tree: SearchTree::new(start, config.hasher),
+            depths: vec![0],
+        }
+    }
+
+    /// expands up to `max_nodes` states, `None` if the search isn't done
+    /// after them
+    fn run(
+        &mut self,
+        config: &SolverConfig,
+        stats: &mut SearchStats,
+        start_time: Instant,
+        chain_cache: &mut ChainCache,
+        on_edge: OnEdge,
+        max_nodes: usize,
+    ) -> Option<(Verdict, Option<Vec<Operations>>)> {
+        let FrontierSearch {
+            targets,
+            frontier,
+            tree,
+            depths,
+        } = self;
+        // the estimate can make a longer path to the goal come out of the
+        // frontier before a shorter one is even found, so A* only stops at
+        // a goal it pops
+        let estimated = matches!(frontier, Frontier::Estimated(_));
+        for _ in 0..max_nodes {
+            let Some((id, depth)) = frontier.pop() else {
+                stats.states_visited = tree.len();
+                return Some((Verdict::NotBeatable("unreachable entrances"), None));
+            };
+            if depth > depths[id] {
+                continue;
+            }
+            if estimated && tree.state(id).reached == *targets {
+                stats.states_visited = tree.len();
+                return Some((Verdict::Beatable, Some(tree.path(id))));
+            }
+            if let Some(verdict) = out_of_budget(config, stats, start_time) {
+                stats.states_visited = tree.len();
+                return Some((verdict, None));
+            }
+            stats.expanded(depth);
+            let state = *tree.state(id);
+            for operation in enum_iterator::all::<Operations>() {
+                let new_state = match step_cached(
+                    &state,
+                    operation,
+                    *targets,
+                    config,
+                    stats,
+                    config.chain_cache.then_some(&mut *chain_cache),
+                ) {
+                    Ok(Some(new_state)) => new_state,
+                    Ok(None) => continue,
+                    Err(cycle) => return Some((Verdict::ChainCycle(cycle), None)),
+                };
+                let timer = Timer::start();
+                let known = tree.id(&new_state);
+                timer.stop(&mut stats.timings.hashing);
+                on_edge(&state, operation, &new_state, known.is_none());
+                let new_id = match known {
+                    Some(known) if estimated && depth + 1 < depths[known] => {
+                        tree.reparent(known, id, operation);
+                        depths[known] = depth + 1;
+                        known
+                    }
+                    Some(_) => {
+                        stats.transposition_hits += 1;
+                        continue;
+                    }
+                    None => {
+                        let timer = Timer::start();
+                        let new_id = tree.insert(new_state, id, operation);
+                        timer.stop(&mut stats.timings.hashing);
+                        depths.push(depth + 1);
+                        new_id
+                    }
+                };
+                if !estimated && new_state.reached == *targets {
+                    stats.states_visited = tree.len();
+                    return Some((Verdict::Beatable, Some(tree.path(new_id))));
+                }
+                let estimate = estimate(config, &new_state, *targets);
+                frontier.push(new_id, &new_state, depth + 1, estimate);
+            }
+        }
+        None
+    }
+}
+
+/// [`solve_rotated_rooms`] as a future that hands control back to the
+/// executor after every `yield_every` expanded states, so a long search
+/// doesn't block a browser's event loop or an async server. Nothing is
+/// searched before the first poll. Iterative deepening, several threads
+/// and random probes aren't split up, they run in one go when it's polled.
+pub fn solve_async(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    config: SolverConfig,
+    yield_every: usize,
+) -> SolveFuture {
+    SolveFuture {
+        chain_cache: ChainCache::new(config.hasher),
+        config,
+        stats: SearchStats::default(),
+        start_time: None,
+        yield_every: yield_every.max(1),
+        phase: SolvePhase::NotStarted(*rooms, *rotations),
+    }
+}
+
+/// The future of [`solve_async`].
+pub struct SolveFuture {
+    config: SolverConfig,
+    chain_cache: ChainCache,
+    stats: SearchStats,
+    start_time: Option<Instant>,
+    yield_every: usize,
+    phase: SolvePhase,
+}
+
+enum SolvePhase {
+    NotStarted([Room; 9], [Rotation; 9]),
+    Searching(Box<FrontierSearch>, ControlPanel),
+    Done,
+}
+
+impl SolveFuture {
+    /// starts the search, `Some` if it's already done
+    fn start(
+        &mut self,
+        rooms: &[Room; 9],
+        rotations: &[Rotation; 9],
+        start_time: Instant,
+    ) -> Option<(Verdict, Option<Solution>)> {
+        let config = &self.config;
+        let stats = &mut self.stats;
+        let (start, start_panel) = match first_state(rooms, rotations, config, stats) {
+            Ok(start) => start,
+            Err(verdict) => return Some((verdict, None)),
+        };
+        let solution = |operations| Solution {
+            start_panel,
+            operations,
+        };
+        let targets = target_mask(&start.room_pos.rooms, config);
+        let in_one_go = start.reached == targets
+            || config.probes.count > 0
+            || (config.threads > 1 && config.strategy == SearchStrategy::BreadthFirst)
+            || config.strategy == SearchStrategy::IterativeDeepening;
+        if in_one_go {
+            let on_edge = &mut |_: &SolveState, _, _: &SolveState, _| {};
+            let (verdict, operations) = search(
+                start,
+                config,
+                stats,
+                start_time,
+                &mut self.chain_cache,
+                on_edge,
+            );
+            return Some((verdict, operations.map(solution)));
+        }
+        let search = FrontierSearch::new(start, targets, config);
+        self.phase = SolvePhase::Searching(Box::new(search), start_panel);
+        None
+    }
+}
+
+impl Future for SolveFuture {
+    type Output = SolveResult;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<SolveResult> {
+        let this = self.get_mut();
+        let start_time = *this.start_time.get_or_insert_with(Instant::now);
+        let done = match std::mem::replace(&mut this.phase, SolvePhase::Done) {
+            SolvePhase::NotStarted(rooms, rotations) => this.start(&rooms, &rotations, start_time),
+            SolvePhase::Searching(mut search, start_panel) => {
+                let on_edge = &mut |_: &SolveState, _, _: &SolveState, _| {};
+                let found = search.run(
+                    &this.config,
+                    &mut this.stats,
+                    start_time,
+                    &mut this.chain_cache,
+                    on_edge,
+                    this.yield_every,
+                );
+                match found {
+                    Some((verdict, operations)) => Some((
+                        verdict,
+                        operations.map(|operations| Solution {
+                            start_panel,
+                            operations,
+                        }),
+                    )),
+                    None => {
+                        this.phase = SolvePhase::Searching(search, start_panel);
+                        None
+                    }
+                }
+            }
+            SolvePhase::Done => panic!("SolveFuture polled after it was done"),
+        };
+        let Some((verdict, solution)) = done else {
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        };
+        let mut stats = std::mem::take(&mut this.stats);
+        stats.elapsed = start_time.elapsed();
+        Poll::Ready(SolveResult {
+            verdict,
+            solution,
+            stats,
+        })
+    }
+}
+
+/// Tries the [`RandomProbes`] of the config one after the other, the
+/// operations of the first solution one of them finds. Every probe keeps its
+/// own states, the ones it found count towards the states visited if the
+/// search stops here.
+fn random_probes(
+    start: SolveState,
+    targets: u32,
+    config: &SolverConfig,
+    stats: &mut SearchStats,
+    start_time: Instant,
+) -> Option<Vec<Operations>> {
+    let mut seed = config.probes.seed;
+    let mut random = || {
+        seed = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        mix(seed)
+    };
+    for _ in 0..config.probes.count {
+        let mut tree = SearchTree::new(start, config.hasher);
+        let mut stack = vec![(0, 0)];
+        let mut expanded = 0;
+        while let Some((id, depth)) = stack.pop() {
+            if expanded == config.probes.nodes || out_of_budget(config, stats, start_time).is_some()
+            {
+                break;
+            }
+            expanded += 1;
+            stats.expanded(depth);
+            let state = *tree.state(id);
+            let mut operations: Vec<Operations> = enum_iterator::all().collect();
+            for i in (1..operations.len()).rev() {
+                operations.swap(i, (random() % (i as u64 + 1)) as usize);
+            }
+            for operation in operations {
+                // operations into a chain cycle are skipped, the full search
+                // reports it
+                let Ok(Some(new_state)) = step(&state, operation, targets, config, stats) else {
+                    continue;
+                };
+                if tree.contains(&new_state) {
+                    stats.transposition_hits += 1;
+                    continue;
+                }
+                let new_id = tree.insert(new_state, id, operation);
+                if new_state.reached == targets {
+                    stats.states_visited = tree.len();
+                    return Some(tree.path(new_id));
+                }
+                stack.push((new_id, depth + 1));
+            }
+        }
+    }
+    None
+}
+
+/// [`search`] depth first with a bound on the depth, again with a higher
+/// bound as long as it cut off states that weren't reached with fewer
+/// operations somewhere else. Every iteration keeps the fewest operations
+/// each state was reached with, and only goes on from a state reached again
+/// if it's with fewer, so nothing within the bound is missed. With a
+/// heuristic states are also cut off when their depth plus the estimate
+/// goes past the bound. `on_edge` sees the edges of every iteration, states
+/// only count as new the first time any iteration finds them.
+fn deepening_search(
+    start: SolveState,
+    targets: u32,
+    config: &SolverConfig,
+    stats: &mut SearchStats,
+    start_time: Instant,
+    chain_cache: &mut ChainCache,
+    on_edge: OnEdge,
+) -> (Verdict, Option<Vec<Operations>>) {
+    let operations: Vec<Operations> = enum_iterator::all().collect();
+    let mut bound = config.deepening.start.max(1);
+    // a higher bound finds every state a lower one did, so the states the
+    // last iteration found are all that were found before this one
+    let mut found_before: HashMap<SolveState, usize, StateHasher> =
+        HashMap::with_hasher(config.hasher);
+    loop {
+        let mut depths: HashMap<SolveState, usize, StateHasher> =
+            HashMap::with_hasher(config.hasher);
+        depths.insert(start, 0);
+        // the states on the current path with the next operation to try on
+        // them, and the operations that led there
+        let mut stack = vec![(start, 0)];
+        let mut path = Vec::new();
+        // states the bound stopped the search at, with their depth
+        let mut cut_off = Vec::new();
+        while let Some((state, next)) = stack.last_mut() {
+            let state = *state;
+            let Some(&operation) = operations.get(*next) else {
+                stack.pop();
+                path.pop();
+                continue;
+            };
+            *next += 1;
+            let depth = path.len();
+            if *next == 1 {
+                if let Some(verdict) = out_of_budget(config, stats, start_time) {
+                    stats.states_visited = stats.states_visited.max(depths.len());
+                    return (verdict, None);
+                }
+                stats.expanded(depth);
+            }
+            let new_state = match step_cached(
+                &state,
+                operation,
+                targets,
+                config,
+                stats,
+                config.chain_cache.then_some(&mut *chain_cache),
+            ) {
+                Ok(Some(new_state)) => new_state,
+                Ok(None) => continue,
+                Err(cycle) => return (Verdict::ChainCycle(cycle), None),
+            };
+            let timer = Timer::start();
+            let known = depths.get(&new_state).copied();
+            let new = known.is_none() && !found_before.contains_key(&new_state);
+            timer.stop(&mut stats.timings.hashing);
+            on_edge(&state, operation, &new_state, new);
+            if known.is_some_and(|known| known <= depth + 1) {
+                stats.transposition_hits += 1;
+                continue;
+            }
+            let timer = Timer::start();
+            depths.insert(new_state, depth + 1);
+            timer.stop(&mut stats.timings.hashing);
+            if new_state.reached == targets {
+                stats.states_visited = stats.states_visited.max(depths.len());
+                path.push(operation);
+                return (Verdict::Beatable, Some(path));
+            }
+            // a state that isn't beaten needs at least one more operation
+            if depth + 1 + estimate(config, &new_state, targets).max(1) as usize > bound {
+                cut_off.push((new_state, depth + 1));
+                continue;
+            }
+            stack.push((new_state, 0));
+            path.push(operation);
+        }
+        stats.states_visited = stats.states_visited.max(depths.len());
+        // the search went on from the ones that were reached with fewer
+        // operations later, if it did from all there's nothing left to find
+        if cut_off.iter().all(|(state, depth)| depths[state] < *depth) {
+            return (Verdict::NotBeatable("unreachable entrances"), None);
+        }
+        bound = config.deepening.next(bound);
+        found_before = depths;
+    }
+}
+
+type Parents = HashMap<SolveState, Option<(SolveState, Operations)>, StateHasher>;
+
+/// a power of two, the shard is the top bits of a hash
+const SHARDS: usize = 64;
+/// depths with fewer states per thread are searched on the calling thread,
+/// starting threads for them takes longer than expanding them
+const MIN_STATES_PER_THREAD: usize = 512;
+
+/// [`search`] breadth first on `config.threads` threads, one depth at a time
+/// so the solution is still a shortest one. The threads share the states
+/// found so far, split into shards that each have their own lock.
+fn parallel_search(
+    start: SolveState,
+    targets: u32,
+    config: &SolverConfig,
+    stats: &mut SearchStats,
+    start_time: Instant,
+) -> (Verdict, Option<Vec<Operations>>) {
+    let shards: Vec<Mutex<Parents>> = (0..SHARDS)
+        .map(|_| Mutex::new(Parents::with_hasher(config.hasher)))
+        .collect();
+    // the maps hash the states again, this only has to spread them out
+    let shard = |state: &SolveState| {
+        let key = pack(&state.room_pos) ^ (state.reached as u64) << 20 ^ state.gates.bits() as u64;
+        &shards[(key.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 58) as usize]
+    };
+    lock(shard(&start)).insert(start, None);
+    let expanded = AtomicUsize::new(0);
+    let done = AtomicBool::new(false);
+
+    let mut level = vec![start];
+    let mut depth = 0;
+    let outcome = loop {
+        if level.is_empty() {
+            break Err(Verdict::NotBeatable("unreachable entrances"));
+        }
+        stats.max_depth = depth;
+        let threads = config
+            .threads
+            .min(level.len() / MIN_STATES_PER_THREAD)
+            .max(1);
+        let chunk = level.len().div_ceil(threads);
+        let expand = |states| {
+            expand_level(
+                states, targets, config, start_time, &shard, &expanded, &done,
+            )
+        };
+        let results: Vec<_> = if threads == 1 {
+            vec![expand(&level)]
+        } else {
+            thread::scope(|scope| {
+                let handles: Vec<_> = level
+                    .chunks(chunk)
+                    .map(|states| scope.spawn(|| expand(states)))
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|handle| {
+                        handle.join().unwrap_or_else(|_| {
+                            let verdict = Verdict::Indeterminate("search thread panicked");
+                            (Err(verdict), SearchStats::default())
+                        })
+                    })
+                    .collect()
+            })
+        };
+        let mut next = Vec::new();
+        let mut outcome = None;
+        for (result, thread_stats) in results {
+            stats.nodes_expanded += thread_stats.nodes_expanded;
+            stats.total_depth += thread_stats.nodes_expanded * depth;
+            stats.transposition_hits += thread_stats.transposition_hits;
+            stats.follow_chain_calls += thread_stats.follow_chain_calls;
+            stats.chain_cache_hits += thread_stats.chain_cache_hits;
+            stats.reach_counts.add(&thread_stats.reach_counts);
+            stats.move_counts.add(&thread_stats.move_counts);
+            stats.rotate_counts.add(&thread_stats.rotate_counts);
+            stats.timings.add(&thread_stats.timings);
+            match result {
+                Ok(Expanded::Next(states)) => next.extend(states),
+                Ok(Expanded::Found(state)) => outcome = outcome.or(Some(Ok(state))),
+                // a verdict wins over a solution found by another thread, as
+                // the single threaded search would have stopped there too
+                Err(verdict) => outcome = Some(Err(verdict)),
+            }
+        }
+        if let Some(outcome) = outcome {
+            break outcome;
+        }
+        level = next;
+        depth += 1;
+    };
+
+    let mut parents = Parents::with_hasher(config.hasher);
+    for shard in shards {
+        parents.extend(shard.into_inner().unwrap_or_else(PoisonError::into_inner));
+    }
+    stats.states_visited = parents.len();
+    match outcome {
+        Ok(found) => (Verdict::Beatable, Some(backtrack(&parents, found))),
+        Err(verdict) => (verdict, None),
+    }
+}
+
+/// a shard's lock, also after another thread panicked while holding it
+fn lock(shard: &Mutex<Parents>) -> MutexGuard<'_, Parents> {
+    shard.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+enum Expanded {
+    Next(Vec<SolveState>),
+    Found(SolveState),
+}
+
+/// expands one thread's share of a depth, stops early once any thread
+/// found a solution or ran into something that ends the search
+fn expand_level<'a>(
+    states: &[SolveState],
+    targets: u32,
+    config: &SolverConfig,
+    start_time: Instant,
+    shard: &impl Fn(&SolveState) -> &'a Mutex<Parents>,
+    expanded: &AtomicUsize,
+    done: &AtomicBool,
+) -> (Result<Expanded, Verdict>, SearchStats) {
+    let mut stats = SearchStats::default();
+    let mut next = Vec::new();
+    for state in states {
+        if done.load(Ordering::Relaxed) {
+            break;
+        }
+        let budget = SearchStats {
+            nodes_expanded: expanded.fetch_add(1, Ordering::Relaxed),
+            ..SearchStats::default()
+        };
+        if let Some(verdict) = out_of_budget(config, &budget, start_time) {
+            done.store(true, Ordering::Relaxed);
+            return (Err(verdict), stats);
+        }
+        stats.nodes_expanded += 1;
+        for operation in enum_iterator::all::<Operations>() {
+            let new_state = match step(state, operation, targets, config, &mut stats) {
+                Ok(Some(new_state)) => new_state,
+                Ok(None) => continue,
+                Err(cycle) => {
+                    done.store(true, Ordering::Relaxed);
+                    return (Err(Verdict::ChainCycle(cycle)), stats);
+                }
+            };
+            let timer = Timer::start();
+            let mut parents = lock(shard(&new_state));
+            if parents.contains_key(&new_state) {
+                timer.stop(&mut stats.timings.hashing);
+                stats.transposition_hits += 1;
+                continue;
+            }
+            parents.insert(new_state, Some((*state, operation)));
+            drop(parents);
+            timer.stop(&mut stats.timings.hashing);
+            if new_state.reached == targets {
+                done.store(true, Ordering::Relaxed);
+                return (Ok(Expanded::Found(new_state)), stats);
+            }
+            next.push(new_state);
+        }
+    }
+    (Ok(Expanded::Next(next)), stats)
+}
+
+pub(crate) fn target_mask(rooms: &[Room; 9], config: &SolverConfig) -> u32 {
+    config
+        .win_condition
+        .targets(rooms, &config.markers)
+        .into_iter()
+        .map(|entrance| 1 << entrance as u32)
+        .sum()
+}
+
+pub(crate) fn first_state(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    config: &SolverConfig,
+    stats: &mut SearchStats,
+) -> Result<(SolveState, ControlPanel), Verdict> {
+    let (start, start_panel) = start_state(rooms, rotations, config, stats)?;
+    Ok((solve_state(start, config), start_panel))
+}
+
+/// the state the search starts from at `start`, with the initially open
+/// gates and without the ones that can't make a difference
+fn solve_state(start: RoomAndPos, config: &SolverConfig) -> SolveState {
+    let rooms = &start.rooms;
+    let start = SolveState {
+        gates: start.toggled_gates | config.initial_gates.difference(config.toggling_gates),
+        room_pos: start,
+        reached: 0,
+    };
+    if config.keep_irrelevant_gates {
+        return start;
+    }
+    // nothing can open the gate of a room that isn't on the board, only the
+    // initial gates have to be cleared of them
+    let absent: OpenedGates = GATED_DOORS
+        .iter()
+        .filter(|(entrance, _)| !rooms.contains(&entrance.to_room_direction().0))
+        .map(|(_, gate)| *gate)
+        .collect();
+    let mut start = without_irrelevant_gates(start, config);
+    start.gates.remove(absent);
+    start.room_pos.toggled_gates.remove(absent);
+    start
+}
+
+/// Gates that can't change any walk: the ones that only block walking
+/// through their room from a door that can't be entered and has no panel to
+/// stand at. Gates of rooms that aren't on the board can't either, but they
+/// never open in the first place.
+pub(crate) fn irrelevant_gates(config: &SolverConfig) -> OpenedGates {
+    GATED_DOORS
+        .iter()
+        .filter(|(entrance, _)| {
+            !config.door_rules.can_enter(*entrance) && !entrance.has_control_panel()
+        })
+        .map(|(_, gate)| *gate)
+        .collect()
+}
+
+/// the doors a gate blocks walking through its room from
+const GATED_DOORS: [(Entrance, OpenedGates); 4] = [
+    (Entrance::StartRight, OpenedGates::STARTING),
+    (Entrance::EarthTempleRight, OpenedGates::EARTH_TEMPLE),
+    (Entrance::MiniBossLeft, OpenedGates::MINI_BOSS),
+    (Entrance::FireSanctuaryLeft, OpenedGates::FIRE_SANCTUARY),
+];
+
+/// the state with the [`irrelevant_gates`] closed, so states that only
+/// differ in them are the same, unless the config keeps them
+fn without_irrelevant_gates(mut state: SolveState, config: &SolverConfig) -> SolveState {
+    let open = state.gates | state.room_pos.toggled_gates;
+    if !config.keep_irrelevant_gates && !open.is_empty() {
+        let irrelevant = irrelevant_gates(config);
+        state.gates.remove(irrelevant);
+        state.room_pos.toggled_gates.remove(irrelevant);
+    }
+    state
+}
+
+/// performs the operation and looks around afterwards, `None` if it can't
+/// be done
+pub(crate) fn step(
+    state: &SolveState,
+    operation: Operations,
+    targets: u32,
+    config: &SolverConfig,
+    stats: &mut SearchStats,
+) -> Result<Option<SolveState>, ChainCycle> {
+    step_cached(state, operation, targets, config, stats, None)
+}
+
+/// [`step`] with the walks around looked up in `cache` if there's one
+pub(crate) fn step_cached(
+    state: &SolveState,
+    operation: Operations,
+    targets: u32,
+    config: &SolverConfig,
+    stats: &mut SearchStats,
+    cache: Option<&mut ChainCache>,
+) -> Result<Option<SolveState>, ChainCycle> {
+    let timer = Timer::start();
+    let next = successor(state, operation, targets, config, stats, cache);
+    timer.stop(&mut stats.timings.successors);
+    next
+}
+
+fn successor(
+    state: &SolveState,
+    operation: Operations,
+    targets: u32,
+    config: &SolverConfig,
+    stats: &mut SearchStats,
+    cache: Option<&mut ChainCache>,
+) -> Result<Option<SolveState>, ChainCycle> {
+    let Some(room_pos) = perform(&state.room_pos, operation, state.gates, config, stats)? else {
+        return Ok(None);
+    };
+    let (gates, reached) = look_around(&room_pos, state.gates, config, stats, cache)?;
+    let new_state = SolveState {
+        room_pos,
+        gates,
+        reached: (state.reached | reached) & targets,
+    };
+    if new_state.reached != state.reached || !state.gates.contains(gates) {
+        stats.counts_mut(operation).progress += 1;
+    }
+    Ok(Some(without_irrelevant_gates(new_state, config)))
+}
+
+fn backtrack(parents: &Parents, mut state: SolveState) -> Vec<Operations> {
+    let mut operations = Vec::new();
+    while let Some(Some((parent, operation))) = parents.get(&state) {
+        operations.push(*operation);
+        state = *parent;
+    }
+    operations.reverse();
+    operations
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        heuristic::{Heuristic, Unreached},
+        share::parse_layout_code,
+        verify::EntryPoint,
+    };
+
+    /// never more than the operations left, every unreached entrance takes
+    /// at least one
+    #[derive(Debug)]
+    struct AnyUnreached;
+
+    impl Heuristic for AnyUnreached {
+        fn estimate(&self, _: &SolveState, unreached: Unreached) -> u32 {
+            unreached.len().min(1)
+        }
+    }
+
+    #[test]
+    fn a_star_finds_a_shortest_solution() {
+        for code in ["EAUgcYMIQ", "IgMYUQcAE"] {
+            let (rooms, rotations) = parse_layout_code(code).unwrap();
+            let shortest = solve_rotated_rooms(&rooms, &rotations, &SolverConfig::default())
+                .solution
+                .unwrap()
+                .operations
+                .len();
+            let heuristics: [Option<Arc<dyn Heuristic>>; 2] = [None, Some(Arc::new(AnyUnreached))];
+            for heuristic in heuristics {
+                let config = SolverConfig {
+                    strategy: SearchStrategy::AStar,
+                    heuristic,
+                    ..Default::default()
+                };
+                let solution = solve_rotated_rooms(&rooms, &rotations, &config)
+                    .solution
+                    .unwrap();
+                assert_eq!(solution.operations.len(), shortest, "{code}");
+            }
+        }
+    }
+
+    #[test]
+    fn state_at_the_entry_point_is_the_start() {
+        let (rooms, rotations) = parse_layout_code("EAUgcYMIQ").unwrap();
+        let config = SolverConfig::default();
+        let EntryPoint { tile, direction } = config.entry;
+        assert_eq!(
+            SolveState::at(&rooms, &rotations, &config, tile, direction),
+            SolveState::start(&rooms, &rotations, &config)
+        );
+    }
+
+    #[test]
+    fn deepening_exports_every_state_once() {
+        let (rooms, rotations) = parse_layout_code("EAUgcYMIQ").unwrap();
+        let config = SolverConfig {
+            strategy: SearchStrategy::IterativeDeepening,
+            max_nodes: Some(20_000),
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        export_search_tree(&rooms, &rotations, &config, &mut out).unwrap();
+        let mut ids = std::collections::HashSet::new();
+        for line in String::from_utf8(out).unwrap().lines() {
+            let field = |name: &str| {
+                let start = line.find(&format!("\"{name}\":")).unwrap() + name.len() + 3;
+                line[start..].split([',', '}']).next().unwrap().to_string()
+            };
+            let id = field("id");
+            if line.contains("\"duplicate\":true") {
+                assert!(ids.contains(&id), "{line}");
+            } else {
+                assert!(ids.insert(id), "{line}");
+            }
+            let parent = field("parent");
+            assert!(parent == "null" || ids.contains(&parent), "{line}");
+        }
+        assert!(ids.len() > 1);
+    }
+
+    #[test]
+    fn moves_at_a_locked_panel_say_so() {
+        let (rooms, rotations) = parse_layout_code("EAUgcYMIQ").unwrap();
+        let config = SolverConfig::default();
+        let state = SolveState::start(&rooms, &rotations, &config).unwrap();
+        let at = state.rooms()[state.tile() as usize];
+        let panel = enum_iterator::all::<ControlPanel>()
+            .find(|panel| panel.room() == at)
+            .unwrap();
+        let slide = enum_iterator::all::<Operations>()
+            .find(|operation| {
+                matches!(operation, Operations::Move(..))
+                    && apply(&state, *operation, &config).is_ok()
+            })
+            .unwrap();
+        let locked = SolverConfig {
+            locked_panels: vec![panel],
+            ..Default::default()
+        };
+        assert_eq!(
+            apply(&state, slide, &locked),
+            Err(MoveError::PanelLocked(panel))
+        );
+    }
+}
@@ -0,0 +1,69 @@
+//! Sky Keep layouts from the spoiler logs of the randomizer, so a seed's
+//! puzzle can be checked independently of the randomizer's own logic.
+//!
+//! The layout is taken from the first section whose header mentions Sky
+//! Keep, like `Sky Keep Layout:`, up to the next blank line. The section
+//! either holds the three rows of the board, with the rooms separated by
+//! `|` or `,`, or by spaces for room codes, or one `<tile>: <room>` line per
+//! tile in board order. Rooms are read by their code or name, ignoring case
+//! and spaces, so `ET`, `EarthTemple` and `Earth Temple` are all the same.
+
+use crate::{
+    puzzle_file::parse_room,
+    rooms::{validate_rooms, Room},
+};
+
+/// The layout in the Sky Keep section of a spoiler log.
+pub fn parse_spoiler_log(text: &str) -> Result<[Room; 9], String> {
+    let mut lines = text.lines().enumerate();
+    let header = lines
+        .by_ref()
+        .find(|(_, line)| is_sky_keep_header(line))
+        .ok_or("no Sky Keep section in the spoiler log")?;
+    let section: Vec<(usize, &str)> = lines
+        .map(|(index, line)| (index, line.trim()))
+        .take_while(|(_, line)| !line.is_empty())
+        .collect();
+    let mut rooms = Vec::new();
+    for (index, line) in section {
+        let line_error = |e: String| format!("line {}: {e}", index + 1);
+        if let Some((_, room)) = line.split_once(':') {
+            rooms.push(room_by_name(room).map_err(line_error)?);
+            continue;
+        }
+        let names: Vec<&str> = if line.contains(['|', ',']) {
+            line.split(['|', ',']).collect()
+        } else {
+            line.split_whitespace().collect()
+        };
+        for name in names {
+            rooms.push(room_by_name(name).map_err(line_error)?);
+        }
+    }
+    let rooms: [Room; 9] = rooms.try_into().map_err(|rooms: Vec<Room>| {
+        format!(
+            "the Sky Keep section at line {} has {} rooms instead of 9",
+            header.0 + 1,
+            rooms.len()
+        )
+    })?;
+    validate_rooms(&rooms)?;
+    Ok(rooms)
+}
+
+fn is_sky_keep_header(line: &str) -> bool {
+    let line = line.trim().to_lowercase();
+    line.ends_with(':') && line.replace(' ', "").contains("skykeep")
+}
+
+fn room_by_name(name: &str) -> Result<Room, String> {
+    let name = name.trim();
+    let squashed = |text: &str| text.replace(' ', "").to_lowercase();
+    parse_room(name).or_else(|e| {
+        enum_iterator::all::<Room>()
+            .find(|room| {
+                squashed(room.name()) == squashed(name) || squashed(room.code()) == squashed(name)
+            })
+            .ok_or(e)
+    })
+}
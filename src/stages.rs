@@ -0,0 +1,87 @@
+//! Where each room of the board is in the game files, as the stage, room
+//! index and layer modding tools and the [patch](crate::patch) exporter
+//! refer to it by.
+//!
+//! None of them are built in, which stage each Sky Keep room is hasn't been
+//! checked in the game files here (see notes.txt). They are loaded from a
+//! JSON object by room, like
+//! `{"earth-temple": {"stage": "...", "room": 0, "layer": 0}}`, with the
+//! rooms named like the locale keys without `room.` or by their code.
+
+use crate::{json::Json, puzzle_file::parse_room, rooms::Room};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StageId {
+    pub stage: String,
+    pub room: u8,
+    pub layer: u8,
+}
+
+impl std::fmt::Display for StageId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} room {} layer {}", self.stage, self.room, self.layer)
+    }
+}
+
+/// The stage of every room that has one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StageIds {
+    /// indexed by `Room as usize`
+    ids: [Option<StageId>; 9],
+}
+
+impl StageIds {
+    /// Reads the JSON described in the [module](self) docs, `layer` can be
+    /// left out for layer 0.
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        let Json::Object(fields) = Json::parse(text)? else {
+            return Err("stage ids have to be a JSON object".to_string());
+        };
+        let mut ids = StageIds::default();
+        for (key, value) in fields {
+            let room = room_by_key(&key)?;
+            if room == Room::Empty {
+                return Err("the empty tile has no stage".to_string());
+            }
+            let stage = value
+                .get("stage")
+                .and_then(Json::as_str)
+                .ok_or_else(|| format!("{key}: missing stage"))?;
+            let number = |field: &str, default: Option<u8>| {
+                let Some(value) = value.get(field) else {
+                    return default.ok_or_else(|| format!("{key}: missing {field}"));
+                };
+                value
+                    .as_f64()
+                    .filter(|n| n.fract() == 0.0 && (0.0..=255.0).contains(n))
+                    .map(|n| n as u8)
+                    .ok_or_else(|| format!("{key}: invalid {field}"))
+            };
+            ids.set(
+                room,
+                StageId {
+                    stage: stage.to_string(),
+                    room: number("room", None)?,
+                    layer: number("layer", Some(0))?,
+                },
+            );
+        }
+        Ok(ids)
+    }
+
+    pub fn get(&self, room: Room) -> Option<&StageId> {
+        self.ids[room as usize].as_ref()
+    }
+
+    pub fn set(&mut self, room: Room, id: StageId) {
+        self.ids[room as usize] = Some(id);
+    }
+}
+
+/// a room by its name in lowercase with dashes, like `earth-temple`, or by
+/// anything [`parse_room`] reads
+fn room_by_key(key: &str) -> Result<Room, String> {
+    enum_iterator::all::<Room>()
+        .find(|room| room.name().to_lowercase().replace(' ', "-") == key)
+        .map_or_else(|| parse_room(key), Ok)
+}
@@ -0,0 +1,599 @@
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap},
+    fs::{self, File, OpenOptions},
+    hash::{BuildHasher, Hasher},
+    io::{self, BufReader, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{rooms::OpenedGates, verify::RoomAndPos};
+
+/// Where the verifier keeps the states it has already seen, together with
+/// the gates each was reached with.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum VisitedStore {
+    #[default]
+    Memory,
+    /// a sorted map keyed by the packed state, slower than [`Memory`] but
+    /// the states come out in the same order every run when debugging
+    ///
+    /// [`Memory`]: VisitedStore::Memory
+    Ordered,
+    /// a hash table in a file in the given directory, for searches whose
+    /// states don't fit in memory. The file is removed once the search ends
+    Disk(PathBuf),
+    /// a Bloom filter sized for `expected_states`, using a fraction of the
+    /// memory. A false positive makes the verifier skip a state it never
+    /// explored, so [`Verdict::Beatable`] is still always right but
+    /// [`Verdict::NotBeatable`] isn't guaranteed anymore
+    ///
+    /// [`Verdict::Beatable`]: crate::verify::Verdict::Beatable
+    /// [`Verdict::NotBeatable`]: crate::verify::Verdict::NotBeatable
+    Bloom {
+        expected_states: usize,
+        false_positive_rate: f64,
+    },
+}
+
+/// The hash function of the hash maps the verifier and the solver keep
+/// their states in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StateHasher {
+    /// the multiply and rotate hash rustc uses, a lot faster on the small
+    /// states here
+    #[default]
+    Fx,
+    /// SipHash like the standard library's maps, only with fixed keys
+    Sip,
+}
+
+impl BuildHasher for StateHasher {
+    type Hasher = StateHash;
+
+    fn build_hasher(&self) -> StateHash {
+        match self {
+            StateHasher::Fx => StateHash::Fx(0),
+            StateHasher::Sip => StateHash::Sip(DefaultHasher::new()),
+        }
+    }
+}
+
+pub enum StateHash {
+    Fx(u64),
+    Sip(DefaultHasher),
+}
+
+impl StateHash {
+    fn fx(hash: &mut u64, word: u64) {
+        *hash = (hash.rotate_left(5) ^ word).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95);
+    }
+}
+
+impl Hasher for StateHash {
+    fn finish(&self) -> u64 {
+        match self {
+            StateHash::Fx(hash) => *hash,
+            StateHash::Sip(hasher) => hasher.finish(),
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        match self {
+            StateHash::Fx(hash) => {
+                let mut chunks = bytes.chunks_exact(8);
+                for chunk in chunks.by_ref() {
+                    Self::fx(hash, u64::from_le_bytes(chunk.try_into().unwrap()));
+                }
+                for byte in chunks.remainder() {
+                    Self::fx(hash, *byte as u64);
+                }
+            }
+            StateHash::Sip(hasher) => hasher.write(bytes),
+        }
+    }
+
+    fn write_u8(&mut self, n: u8) {
+        self.write_u64(n as u64);
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.write_u64(n as u64);
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        match self {
+            StateHash::Fx(hash) => Self::fx(hash, n),
+            StateHash::Sip(hasher) => hasher.write_u64(n),
+        }
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.write_u64(n as u64);
+    }
+
+    fn write_isize(&mut self, n: isize) {
+        self.write_u64(n as u64);
+    }
+}
+
+/// Every set of gates a state was reached with that isn't part of a bigger
+/// one, bit `i` stands for the gates with bits `i`. Another path can reach a
+/// state with different gates instead of more, so one set isn't enough.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct GateSets(u16);
+
+const _: () = assert!(OpenedGates::all().bits() < 16);
+
+impl GateSets {
+    pub(crate) fn sets(self) -> impl Iterator<Item = OpenedGates> {
+        (0..16u8)
+            .filter(move |bits| self.0 & 1 << bits != 0)
+            .map(OpenedGates::from_bits_retain)
+    }
+
+    /// if one of the sets has at least these gates
+    pub(crate) fn covers(self, gates: OpenedGates) -> bool {
+        self.sets().any(|set| set.contains(gates))
+    }
+
+    /// adds `gates`, dropping the sets that are part of it
+    pub(crate) fn add(&mut self, gates: OpenedGates) {
+        let before = *self;
+        for set in before.sets() {
+            if gates.contains(set) {
+                self.0 &= !(1 << set.bits());
+            }
+        }
+        self.0 |= 1 << gates.bits();
+        debug_assert!(
+            before.sets().all(|set| self.covers(set)),
+            "adding {gates:?} to {before:?} lost gates"
+        );
+    }
+}
+
+pub(crate) trait StateStore {
+    /// the gates the state was reached with, if the store remembers them
+    fn get(&mut self, state: &RoomAndPos) -> Option<GateSets>;
+    /// if the state was already reached with at least these gates
+    fn seen(&mut self, state: &RoomAndPos, gates: OpenedGates) -> bool {
+        self.get(state).is_some_and(|sets| sets.covers(gates))
+    }
+    /// only for gates the state wasn't [`seen`](StateStore::seen) with, the
+    /// sets it's stored with are kept unless they're part of these
+    fn insert(&mut self, state: &RoomAndPos, gates: OpenedGates);
+    fn len(&mut self) -> usize;
+    /// the store couldn't be read or written, lookups since then may have
+    /// missed states
+    fn failed(&self) -> bool;
+}
+
+pub(crate) fn open_store(
+    store: &VisitedStore,
+    hasher: StateHasher,
+) -> io::Result<Box<dyn StateStore>> {
+    Ok(match store {
+        VisitedStore::Memory => Box::new(HashMap::with_hasher(hasher)),
+        VisitedStore::Ordered => Box::new(BTreeMap::new()),
+        VisitedStore::Disk(dir) => Box::new(DiskStore::create(dir.clone())?),
+        VisitedStore::Bloom {
+            expected_states,
+            false_positive_rate,
+        } => Box::new(BloomStore::new(*expected_states, *false_positive_rate)),
+    })
+}
+
+impl StateStore for HashMap<RoomAndPos, GateSets, StateHasher> {
+    fn get(&mut self, state: &RoomAndPos) -> Option<GateSets> {
+        HashMap::get(self, state).copied()
+    }
+
+    fn insert(&mut self, state: &RoomAndPos, gates: OpenedGates) {
+        let sets = self.entry(*state).or_default();
+        debug_assert!(!sets.covers(gates), "{state:?} stored again with {gates:?}");
+        sets.add(gates);
+    }
+
+    fn len(&mut self) -> usize {
+        HashMap::len(self)
+    }
+
+    fn failed(&self) -> bool {
+        false
+    }
+}
+
+impl StateStore for BTreeMap<u64, GateSets> {
+    fn get(&mut self, state: &RoomAndPos) -> Option<GateSets> {
+        BTreeMap::get(self, &pack(state)).copied()
+    }
+
+    fn insert(&mut self, state: &RoomAndPos, gates: OpenedGates) {
+        let sets = self.entry(pack(state)).or_default();
+        debug_assert!(!sets.covers(gates), "{state:?} stored again with {gates:?}");
+        sets.add(gates);
+    }
+
+    fn len(&mut self) -> usize {
+        BTreeMap::len(self)
+    }
+
+    fn failed(&self) -> bool {
+        false
+    }
+}
+
+/// Remembers which gates states were reached with, but not in a way they can
+/// be read back.
+struct BloomStore {
+    bits: Vec<u64>,
+    hashes: u32,
+    inserted: usize,
+}
+
+impl BloomStore {
+    fn new(expected_states: usize, false_positive_rate: f64) -> Self {
+        let expected_states = expected_states.max(1) as f64;
+        let false_positive_rate = false_positive_rate.clamp(f64::MIN_POSITIVE, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+        let bit_count = (-expected_states * false_positive_rate.ln() / (ln2 * ln2)).ceil();
+        let hashes = (bit_count / expected_states * ln2).round().max(1.0) as u32;
+        BloomStore {
+            bits: vec![0; (bit_count as usize).div_ceil(64)],
+            hashes,
+            inserted: 0,
+        }
+    }
+
+    /// double hashing, the `i`th bit is `h1 + i * h2`
+    fn bit_indices(&self, state: &RoomAndPos, gates: OpenedGates) -> impl Iterator<Item = usize> {
+        let h1 = mix(pack(state) ^ mix(gates.bits() as u64));
+        let h2 = mix(h1) | 1;
+        let len = self.bits.len() as u64 * 64;
+        (0..self.hashes as u64).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % len) as usize)
+    }
+
+    fn contains(&self, state: &RoomAndPos, gates: OpenedGates) -> bool {
+        self.bit_indices(state, gates)
+            .all(|bit| self.bits[bit / 64] & 1 << (bit % 64) != 0)
+    }
+}
+
+impl StateStore for BloomStore {
+    fn get(&mut self, _state: &RoomAndPos) -> Option<GateSets> {
+        None
+    }
+
+    fn seen(&mut self, state: &RoomAndPos, gates: OpenedGates) -> bool {
+        // every combination of gates that includes the current ones
+        let missing = OpenedGates::all().difference(gates);
+        let mut extra = missing;
+        loop {
+            if self.contains(state, gates | extra) {
+                return true;
+            }
+            if extra.is_empty() {
+                return false;
+            }
+            extra = OpenedGates::from_bits_retain((extra.bits() - 1) & missing.bits());
+        }
+    }
+
+    fn insert(&mut self, state: &RoomAndPos, gates: OpenedGates) {
+        let indices: Vec<_> = self.bit_indices(state, gates).collect();
+        for bit in indices {
+            self.bits[bit / 64] |= 1 << (bit % 64);
+        }
+        self.inserted += 1;
+    }
+
+    /// the number of inserts, states reached with other gates count again
+    fn len(&mut self) -> usize {
+        self.inserted
+    }
+
+    fn failed(&self) -> bool {
+        false
+    }
+}
+
+/// splitmix64 finalizer
+pub(crate) fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+/// Every field of a state fits in 64 bits: 6 per tile for room and rotation,
+/// 4 for the tile, 2 for the direction and 4 for the toggled gates.
+pub(crate) fn pack(state: &RoomAndPos) -> u64 {
+    let mut packed = 0;
+    for (room, rotation) in state.rooms.iter().zip(&state.rotations) {
+        packed = packed << 6 | (*room as u64) << 2 | *rotation as u64;
+    }
+    packed = packed << 4 | state.pos_tile as u64;
+    packed = packed << 2 | state.pos_direction as u64;
+    packed << 4 | state.toggled_gates.bits() as u64
+}
+
+// a slot is the packed state followed by the gate sets, a stored state has
+// at least one so an all zero slot is empty
+const SLOT_SIZE: u64 = 10;
+const INITIAL_BITS: u32 = 16;
+/// inserts are collected in memory and written out together
+const BATCH_SIZE: usize = 1 << 16;
+
+static NEXT_FILE: AtomicUsize = AtomicUsize::new(0);
+
+/// Open addressing hash table with linear probing in a file, doubling once
+/// it's half full.
+struct DiskStore {
+    path: PathBuf,
+    file: File,
+    /// the table has `1 << bits` slots
+    bits: u32,
+    on_disk: usize,
+    pending: HashMap<u64, GateSets>,
+    error: Option<io::Error>,
+}
+
+impl DiskStore {
+    fn create(dir: PathBuf) -> io::Result<Self> {
+        let path = dir.join(format!(
+            "skykeep-visited-{}-{}.bin",
+            std::process::id(),
+            NEXT_FILE.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = create_table(&path, INITIAL_BITS)?;
+        Ok(DiskStore {
+            path,
+            file,
+            bits: INITIAL_BITS,
+            on_disk: 0,
+            pending: HashMap::new(),
+            error: None,
+        })
+    }
+
+    fn record<T>(&mut self, result: io::Result<T>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.error.get_or_insert(e);
+                None
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if (self.on_disk + self.pending.len()) * 2 > 1 << self.bits {
+            self.grow()?;
+        }
+        let mut pending: Vec<_> = self.pending.drain().collect();
+        // write in slot order to keep the disk accesses close together, the
+        // key breaks ties so the file doesn't depend on the hash map's order
+        pending.sort_by_key(|(key, _)| (home_slot(*key, self.bits), *key));
+        for (key, sets) in pending {
+            if place(&mut self.file, self.bits, key, sets)? {
+                self.on_disk += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn grow(&mut self) -> io::Result<()> {
+        let mut bits = self.bits;
+        while (self.on_disk + self.pending.len()) * 2 > 1 << bits {
+            bits += 1;
+        }
+        let new_path = self.path.with_extension("grow");
+        let mut new_file = create_table(&new_path, bits)?;
+        self.file.seek(SeekFrom::Start(0))?;
+        let mut reader = BufReader::new(&self.file);
+        let mut slot = [0; SLOT_SIZE as usize];
+        for _ in 0..1u64 << self.bits {
+            reader.read_exact(&mut slot)?;
+            if let Some((key, sets)) = decode_slot(&slot) {
+                place(&mut new_file, bits, key, sets)?;
+            }
+        }
+        fs::rename(&new_path, &self.path)?;
+        self.file = new_file;
+        self.bits = bits;
+        Ok(())
+    }
+}
+
+impl StateStore for DiskStore {
+    fn get(&mut self, state: &RoomAndPos) -> Option<GateSets> {
+        let key = pack(state);
+        if let Some(sets) = self.pending.get(&key) {
+            return Some(*sets);
+        }
+        let found = find(&mut self.file, self.bits, key);
+        self.record(found).flatten()
+    }
+
+    fn insert(&mut self, state: &RoomAndPos, gates: OpenedGates) {
+        let mut sets = self.get(state).unwrap_or_default();
+        debug_assert!(!sets.covers(gates), "{state:?} stored again with {gates:?}");
+        sets.add(gates);
+        self.pending.insert(pack(state), sets);
+        if self.pending.len() >= BATCH_SIZE {
+            let flushed = self.flush();
+            self.record(flushed);
+        }
+    }
+
+    fn len(&mut self) -> usize {
+        let flushed = self.flush();
+        self.record(flushed);
+        self.on_disk
+    }
+
+    fn failed(&self) -> bool {
+        self.error.is_some()
+    }
+}
+
+impl Drop for DiskStore {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn create_table(path: &PathBuf, bits: u32) -> io::Result<File> {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(path)?;
+    // the file system fills this with zeros, which are empty slots
+    file.set_len((1 << bits) * SLOT_SIZE)?;
+    Ok(file)
+}
+
+fn home_slot(key: u64, bits: u32) -> u64 {
+    key.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> (64 - bits)
+}
+
+fn decode_slot(slot: &[u8; SLOT_SIZE as usize]) -> Option<(u64, GateSets)> {
+    let sets = u16::from_le_bytes(slot[8..].try_into().unwrap());
+    if sets == 0 {
+        return None;
+    }
+    let key = u64::from_le_bytes(slot[..8].try_into().unwrap());
+    Some((key, GateSets(sets)))
+}
+
+/// walks the probe sequence of `key` until it finds the key or an empty slot,
+/// returns the slot index and its contents
+fn probe(file: &mut File, bits: u32, key: u64) -> io::Result<(u64, Option<GateSets>)> {
+    let mask = (1 << bits) - 1;
+    let mut index = home_slot(key, bits);
+    let mut slot = [0; SLOT_SIZE as usize];
+    loop {
+        file.seek(SeekFrom::Start(index * SLOT_SIZE))?;
+        file.read_exact(&mut slot)?;
+        match decode_slot(&slot) {
+            None => return Ok((index, None)),
+            Some((found, sets)) if found == key => return Ok((index, Some(sets))),
+            Some(_) => index = (index + 1) & mask,
+        }
+    }
+}
+
+fn find(file: &mut File, bits: u32, key: u64) -> io::Result<Option<GateSets>> {
+    probe(file, bits, key).map(|(_, sets)| sets)
+}
+
+/// writes the gate sets for `key`, returns if it wasn't in the table before
+fn place(file: &mut File, bits: u32, key: u64, sets: GateSets) -> io::Result<bool> {
+    let (index, before) = probe(file, bits, key)?;
+    let mut slot = [0; SLOT_SIZE as usize];
+    slot[..8].copy_from_slice(&key.to_le_bytes());
+    slot[8..].copy_from_slice(&sets.0.to_le_bytes());
+    file.seek(SeekFrom::Start(index * SLOT_SIZE))?;
+    file.write_all(&slot)?;
+    Ok(before.is_none())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rooms::{Direction, Room, Rotation, ALL_ROOMS};
+
+    #[test]
+    fn gate_sets_keep_incomparable_gates() {
+        let mut sets = GateSets::default();
+        sets.add(OpenedGates::STARTING);
+        sets.add(OpenedGates::MINI_BOSS);
+        assert!(sets.covers(OpenedGates::STARTING));
+        assert!(sets.covers(OpenedGates::MINI_BOSS));
+        assert!(!sets.covers(OpenedGates::STARTING | OpenedGates::MINI_BOSS));
+        assert_eq!(sets.sets().count(), 2);
+
+        sets.add(OpenedGates::STARTING | OpenedGates::MINI_BOSS);
+        assert_eq!(
+            sets.sets().collect::<Vec<_>>(),
+            [OpenedGates::STARTING | OpenedGates::MINI_BOSS]
+        );
+        assert!(sets.covers(OpenedGates::empty()));
+    }
+
+    fn state() -> RoomAndPos {
+        RoomAndPos {
+            rooms: [Room::Start; 9],
+            rotations: [Rotation::Deg0; 9],
+            pos_tile: 4,
+            pos_direction: Direction::Up,
+            toggled_gates: OpenedGates::empty(),
+        }
+    }
+
+    #[test]
+    fn pack_puts_the_first_tile_on_top() {
+        let mut packed = state();
+        packed.rooms[0] = Room::Empty;
+        packed.rotations[0] = Rotation::Deg270;
+        packed.pos_direction = Direction::Right;
+        packed.toggled_gates = OpenedGates::EARTH_TEMPLE | OpenedGates::FIRE_SANCTUARY;
+        // 8 and 3 for the first tile, then tile 4, direction 3 and gates 0b1010
+        assert_eq!(
+            pack(&packed),
+            (8 << 2 | 3) << 58 | (4 << 2 | 3) << 4 | 0b1010
+        );
+    }
+
+    #[test]
+    fn pack_tells_positions_and_gates_apart() {
+        let mut seen = std::collections::HashSet::new();
+        for pos_tile in 0..9 {
+            for pos_direction in enum_iterator::all::<Direction>() {
+                for bits in 0..16 {
+                    let packed = RoomAndPos {
+                        pos_tile,
+                        pos_direction,
+                        toggled_gates: OpenedGates::from_bits_retain(bits),
+                        ..state()
+                    };
+                    assert!(seen.insert(pack(&packed)));
+                }
+            }
+        }
+        for tile in 0..9 {
+            for room in ALL_ROOMS {
+                for rotation in enum_iterator::all::<Rotation>() {
+                    let mut packed = state();
+                    packed.rooms[tile] = room;
+                    packed.rotations[tile] = rotation;
+                    if packed != state() {
+                        assert!(seen.insert(pack(&packed)), "{packed:?}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn stores_agree_on_seen_gates() {
+        let state = state();
+        let dir = std::env::temp_dir();
+        for store in [
+            VisitedStore::Memory,
+            VisitedStore::Ordered,
+            VisitedStore::Disk(dir.clone()),
+        ] {
+            let mut store = open_store(&store, StateHasher::default()).unwrap();
+            assert!(!store.seen(&state, OpenedGates::empty()));
+            store.insert(&state, OpenedGates::EARTH_TEMPLE);
+            store.insert(&state, OpenedGates::FIRE_SANCTUARY);
+            assert!(store.seen(&state, OpenedGates::EARTH_TEMPLE));
+            assert!(store.seen(&state, OpenedGates::FIRE_SANCTUARY));
+            assert!(!store.seen(&state, OpenedGates::all()));
+            assert_eq!(store.len(), 1);
+            assert!(!store.failed());
+        }
+    }
+}
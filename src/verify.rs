@@ -0,0 +1,999 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use enum_iterator::Sequence;
+
+use crate::{
+    chain::{follow_chain, follow_chain_both, walk_starts, ChainCycle},
+    heuristic::Heuristic,
+    rooms::{
+        do_move, ControlPanel, Direction, DoorRules, Entrance, OpenedGates, Room, Rotation,
+        NO_ROTATIONS,
+    },
+    store::{open_store, pack, StateHasher, VisitedStore},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct RoomAndPos {
+    pub(crate) rooms: [Room; 9],
+    pub(crate) rotations: [Rotation; 9],
+    pub(crate) pos_tile: u8,
+    pub(crate) pos_direction: Direction,
+    /// state of the gates in [`SolverConfig::toggling_gates`], unlike the
+    /// others these can close again so having more of them open isn't
+    /// always better
+    pub(crate) toggled_gates: OpenedGates,
+}
+
+/// Which of the empty tiles a move slides into, counted in board order.
+/// Vanilla only has one, variants can leave out rooms to get more.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Sequence)]
+pub enum EmptySlot {
+    First,
+    Second,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Sequence)]
+pub enum Operations {
+    Reach(ControlPanel),
+    Move(EmptySlot, Direction),
+    /// turns the room that `Move` with the same arguments would slide
+    /// clockwise, only with [`SolverConfig::rotatable_rooms`]
+    Rotate(EmptySlot, Direction),
+}
+
+/// Limits for a single verification, a search that runs into one of them
+/// ends as [`Verdict::Indeterminate`] instead of running to completion.
+#[derive(Debug, Clone, Default)]
+pub struct SolverConfig {
+    pub max_nodes: Option<usize>,
+    pub timeout: Option<Duration>,
+    /// allow turning rooms as an additional operation
+    pub rotatable_rooms: bool,
+    /// gates that flip every time their trigger is passed on the way to a
+    /// control panel instead of staying open once they are opened
+    pub toggling_gates: OpenedGates,
+    pub door_rules: DoorRules,
+    pub win_condition: WinCondition,
+    /// the order the solver expands states in
+    pub strategy: SearchStrategy,
+    /// the depth bounds of [`SearchStrategy::IterativeDeepening`]
+    pub deepening: DeepeningSchedule,
+    /// the estimate [`SearchStrategy::AStar`] and iterative deepening go
+    /// by, see the [heuristic](crate::heuristic) module. The built in ones
+    /// can estimate more operations than are left, which costs the
+    /// guarantee of a shortest solution
+    pub heuristic: Option<Arc<dyn Heuristic>>,
+    /// short random searches the solver tries before its own
+    pub probes: RandomProbes,
+    /// keep gates that can't make a difference on the board in the solver's
+    /// states instead of leaving them out, to check that leaving them out
+    /// doesn't change the results
+    pub keep_irrelevant_gates: bool,
+    /// where the verifier keeps the states it has seen
+    pub visited_store: VisitedStore,
+    /// the hash function of the states the verifier and the solver keep in
+    /// memory
+    pub hasher: StateHasher,
+    /// remember what walking around found at every position and look it up
+    /// when the search gets there again. The walks are short enough that
+    /// the lookups cost more than they save on the default rules
+    pub chain_cache: bool,
+    /// the door the player enters the board through
+    pub entry: EntryPoint,
+    /// gates that are already open when the player enters
+    pub initial_gates: OpenedGates,
+    /// entrances with something to collect behind them, like the Triforce
+    /// pieces, for [`WinCondition::AllMarkers`]
+    pub markers: BTreeSet<Entrance>,
+    /// panels the player can walk to but not slide or turn rooms from, to
+    /// find out if every solution needs one of them
+    pub locked_panels: Vec<ControlPanel>,
+    /// threads the solver searches breadth first on, 0 and 1 both search
+    /// on the calling thread. With more the stats vary a bit between runs
+    pub threads: usize,
+}
+
+/// A door of a tile on the edge of the board, facing outwards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EntryPoint {
+    pub tile: u8,
+    pub direction: Direction,
+}
+
+impl Default for EntryPoint {
+    /// the bottom door of the bottom middle tile, like in the game
+    fn default() -> Self {
+        EntryPoint {
+            tile: 7,
+            direction: Direction::Down,
+        }
+    }
+}
+
+/// How the solver picks the next state to expand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchStrategy {
+    /// finds a shortest solution
+    #[default]
+    BreadthFirst,
+    /// expands states that reached the most entrances and opened the most
+    /// gates first, finds some solution faster on hard layouts but it's not
+    /// necessarily the shortest
+    BestFirst,
+    /// goes on from the state found last, keeps the fewest states in the
+    /// frontier and the solution is whatever it runs into first, usually a
+    /// long one
+    DepthFirst,
+    /// depth first up to a bound that grows by
+    /// [`SolverConfig::deepening`] until there's a solution within it.
+    /// Finds a shortest solution if the bound starts at 1 and grows by 1.
+    /// With a [`SolverConfig::heuristic`] it's IDA*, states whose estimate
+    /// goes past the bound are cut off too
+    IterativeDeepening,
+    /// expands the states with the fewest operations so far plus the
+    /// [`SolverConfig::heuristic`]'s estimate first, breadth first without
+    /// one. Finds a shortest solution as long as the estimate is never more
+    /// than the operations left
+    AStar,
+}
+
+/// The depth bounds an iterative deepening search tries, in order. Starting
+/// higher or growing faster does fewer iterations over the same states, but
+/// the solution found can be up to the bound long instead of the shortest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeepeningSchedule {
+    /// the bound of the first iteration
+    pub start: usize,
+    pub growth: DeepeningGrowth,
+}
+
+impl Default for DeepeningSchedule {
+    fn default() -> Self {
+        DeepeningSchedule {
+            start: 1,
+            growth: DeepeningGrowth::Add(1),
+        }
+    }
+}
+
+impl DeepeningSchedule {
+    /// the bound of the iteration after one with `bound`
+    pub fn next(&self, bound: usize) -> usize {
+        match self.growth {
+            DeepeningGrowth::Add(step) => bound.saturating_add(step.max(1)),
+            DeepeningGrowth::Multiply(factor) => bound.saturating_mul(factor.max(2)),
+        }
+    }
+}
+
+/// Depth first searches that try the operations in a random order and give
+/// up after a few nodes. Easy layouts have so many solutions that one of
+/// them often runs right into one, long before the full search would be
+/// done. The solution is whatever the probe found, not a shortest one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RandomProbes {
+    /// none by default
+    pub count: usize,
+    /// the states every probe can expand
+    pub nodes: usize,
+    /// the same seed tries the same orders
+    pub seed: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeepeningGrowth {
+    Add(usize),
+    Multiply(usize),
+}
+
+/// What has to be reachable at some point for a layout to count as beatable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WinCondition {
+    /// every entrance of the rooms on the board
+    #[default]
+    AllEntrances,
+    /// every mover control panel, including the ones in rooms that were
+    /// left out of the board
+    AllPanels,
+    AllEntrancesAndPanels,
+    /// only the Sandship's door, which is all that matters for the Triforce
+    /// with some randomizer settings
+    Sandship,
+    /// every entrance in [`SolverConfig::markers`], beatable as soon as the
+    /// first panel is reached without any
+    AllMarkers,
+}
+
+impl WinCondition {
+    /// the entrances that have to be reached on the given board
+    pub fn targets(&self, rooms: &[Room; 9], markers: &BTreeSet<Entrance>) -> BTreeSet<Entrance> {
+        // rooms left out to make room for more empty tiles can't be reached
+        let entrances =
+            enum_iterator::all::<Entrance>().filter(|e| rooms.contains(&e.to_room_direction().0));
+        let panels = enum_iterator::all::<ControlPanel>().map(|panel| panel.entrance());
+        match self {
+            WinCondition::AllEntrances => entrances.collect(),
+            WinCondition::AllPanels => panels.collect(),
+            WinCondition::AllEntrancesAndPanels => entrances.chain(panels).collect(),
+            WinCondition::Sandship => BTreeSet::from([Entrance::SandshipLeft]),
+            WinCondition::AllMarkers => markers.clone(),
+        }
+    }
+}
+
+/// Everything but `elapsed` only depends on the layout and the config, so
+/// two runs on the same input report the same numbers.
+#[derive(Debug, Clone, Default)]
+pub struct SearchStats {
+    pub nodes_expanded: usize,
+    pub states_visited: usize,
+    /// the most operations any explored state was away from the start
+    pub max_depth: usize,
+    /// the operations every explored state was away from the start, added
+    /// up, see [`mean_depth`](Self::mean_depth)
+    pub total_depth: usize,
+    /// how often a state was found again and not explored a second time
+    pub transposition_hits: usize,
+    /// walks through the rooms, which is where most of the time goes
+    pub follow_chain_calls: usize,
+    /// walks around a position that were looked up instead of done again
+    pub chain_cache_hits: usize,
+    pub reach_counts: OperationCounts,
+    pub move_counts: OperationCounts,
+    pub rotate_counts: OperationCounts,
+    pub timings: Timings,
+    pub elapsed: Duration,
+}
+
+/// Where the time of a search went. Only measured with the `profile`
+/// feature, timing every call costs more than the calls themselves on the
+/// default rules, without it they stay zero.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timings {
+    /// walking through the rooms
+    pub follow_chain: Duration,
+    /// doing operations and walking around afterwards, including the
+    /// follow_chain and the hash map time of that
+    pub successors: Duration,
+    /// looking up and storing states and walks in hash maps and the
+    /// verifier's visited store
+    pub hashing: Duration,
+}
+
+impl Timings {
+    pub(crate) fn add(&mut self, other: &Timings) {
+        self.follow_chain += other.follow_chain;
+        self.successors += other.successors;
+        self.hashing += other.hashing;
+    }
+}
+
+/// Measures the time until [`stop`](Timer::stop) with the `profile`
+/// feature, does nothing without it.
+pub(crate) struct Timer {
+    #[cfg(feature = "profile")]
+    start: Instant,
+}
+
+impl Timer {
+    pub(crate) fn start() -> Self {
+        Timer {
+            #[cfg(feature = "profile")]
+            start: Instant::now(),
+        }
+    }
+
+    /// adds the time since the start to `total`
+    pub(crate) fn stop(self, total: &mut Duration) {
+        #[cfg(feature = "profile")]
+        {
+            *total += self.start.elapsed();
+        }
+        #[cfg(not(feature = "profile"))]
+        let _ = total;
+    }
+}
+
+/// How often operations of one kind were tried on explored states, could be
+/// done, and reached an entrance or opened a gate the state hadn't.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OperationCounts {
+    pub tried: usize,
+    pub done: usize,
+    pub progress: usize,
+}
+
+impl OperationCounts {
+    pub(crate) fn add(&mut self, other: &OperationCounts) {
+        self.tried += other.tried;
+        self.done += other.done;
+        self.progress += other.progress;
+    }
+}
+
+impl SearchStats {
+    /// how many operations the explored states were away from the start on
+    /// average
+    pub fn mean_depth(&self) -> f64 {
+        self.total_depth as f64 / self.nodes_expanded.max(1) as f64
+    }
+
+    /// The branching factor of a tree as deep as the search went in which
+    /// every state leads to the same number of others, to explore as many
+    /// states. 1 for a search that explored a single path, 0 if it never
+    /// went past the start.
+    pub fn effective_branching(&self) -> f64 {
+        let depth = self.max_depth as i32;
+        let nodes = self.nodes_expanded as f64;
+        if depth == 0 {
+            return 0.0;
+        }
+        // 1 + b + b^2 + ... + b^depth grows with b, so bisect
+        let tree_size = |b: f64| (0..=depth).map(|level| b.powi(level)).sum::<f64>();
+        let (mut low, mut high) = (1.0, nodes.max(1.0));
+        for _ in 0..64 {
+            let middle = (low + high) / 2.0;
+            if tree_size(middle) < nodes {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+        low
+    }
+
+    /// the counts of the kind of `operation`
+    pub fn counts(&self, operation: Operations) -> &OperationCounts {
+        match operation {
+            Operations::Reach(_) => &self.reach_counts,
+            Operations::Move(..) => &self.move_counts,
+            Operations::Rotate(..) => &self.rotate_counts,
+        }
+    }
+
+    pub(crate) fn counts_mut(&mut self, operation: Operations) -> &mut OperationCounts {
+        match operation {
+            Operations::Reach(_) => &mut self.reach_counts,
+            Operations::Move(..) => &mut self.move_counts,
+            Operations::Rotate(..) => &mut self.rotate_counts,
+        }
+    }
+
+    /// counts a state `depth` operations from the start as explored
+    pub(crate) fn expanded(&mut self, depth: usize) {
+        self.nodes_expanded += 1;
+        self.max_depth = self.max_depth.max(depth);
+        self.total_depth += depth;
+    }
+}
+
+impl std::fmt::Display for SearchStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "explored {} nodes, {} states, max depth {}, mean depth {:.1}, effective branching factor {:.2}, {} transposition hits, {} follow_chain calls, {} chain cache hits in {:?}",
+            self.nodes_expanded,
+            self.states_visited,
+            self.max_depth,
+            self.mean_depth(),
+            self.effective_branching(),
+            self.transposition_hits,
+            self.follow_chain_calls,
+            self.chain_cache_hits,
+            self.elapsed
+        )?;
+        let kinds = [
+            ("reach", &self.reach_counts),
+            ("move", &self.move_counts),
+            ("rotate", &self.rotate_counts),
+        ];
+        for (i, (name, counts)) in kinds.into_iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(
+                f,
+                "{name}: tried {}, done {}, made progress {}",
+                counts.tried, counts.done, counts.progress
+            )?;
+        }
+        if cfg!(feature = "profile") {
+            write!(
+                f,
+                "\ntime in follow_chain {:?}, successors {:?}, hash maps {:?}",
+                self.timings.follow_chain, self.timings.successors, self.timings.hashing
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Beatable,
+    NotBeatable(&'static str),
+    /// the search was aborted before it could decide either way
+    Indeterminate(&'static str),
+    /// the doors of the layout form a loop, the room logic is broken
+    ChainCycle(ChainCycle),
+}
+
+#[derive(Debug, Clone)]
+pub struct Verification {
+    pub verdict: Verdict,
+    pub stats: SearchStats,
+}
+
+/// `Some` once the search has used up the budget from the config
+pub(crate) fn out_of_budget(
+    config: &SolverConfig,
+    stats: &SearchStats,
+    start_time: Instant,
+) -> Option<Verdict> {
+    if config
+        .max_nodes
+        .is_some_and(|max_nodes| stats.nodes_expanded >= max_nodes)
+    {
+        return Some(Verdict::Indeterminate("node budget exhausted"));
+    }
+    // checking the clock on every node is measurably slower
+    if stats.nodes_expanded.is_multiple_of(1024)
+        && config
+            .timeout
+            .is_some_and(|timeout| start_time.elapsed() >= timeout)
+    {
+        return Some(Verdict::Indeterminate("timeout"));
+    }
+    None
+}
+
+/// Enters the board at the entry point and walks to the first control panel,
+/// which is where the player starts operating the puzzle from. The toggling
+/// gates of the returned state include the initially open ones.
+pub(crate) fn start_state(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    config: &SolverConfig,
+    stats: &mut SearchStats,
+) -> Result<(RoomAndPos, ControlPanel), Verdict> {
+    // check that we can enter at all
+    let EntryPoint { tile, direction } = config.entry;
+    if tile >= 9 || do_move(tile, direction).is_some() {
+        return Err(Verdict::NotBeatable(
+            "entry point not on the edge of the board",
+        ));
+    }
+    let Some(first_entrance) =
+        config
+            .door_rules
+            .on_board(rooms[tile as usize], rotations[tile as usize], direction)
+    else {
+        return Err(Verdict::NotBeatable("no door at the entry point"));
+    };
+    if !config.door_rules.can_enter(first_entrance) {
+        return Err(Verdict::NotBeatable("first room can't be entered"));
+    }
+    walk_to_first_panel(rooms, rotations, config, stats, tile, direction, false)
+}
+
+/// The player standing at the door of `tile` facing `direction` anywhere on
+/// the board, like the game was left, and walking to the first control
+/// panel from there like after an operation.
+pub(crate) fn state_at(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    config: &SolverConfig,
+    stats: &mut SearchStats,
+    tile: u8,
+    direction: Direction,
+) -> Result<(RoomAndPos, ControlPanel), Verdict> {
+    if tile >= 9 {
+        return Err(Verdict::NotBeatable("player not on the board"));
+    }
+    walk_to_first_panel(rooms, rotations, config, stats, tile, direction, true)
+}
+
+/// `both` also walks through the door into the neighboring room, see
+/// [`follow_chain_both`]
+fn walk_to_first_panel(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    config: &SolverConfig,
+    stats: &mut SearchStats,
+    tile: u8,
+    direction: Direction,
+    both: bool,
+) -> Result<(RoomAndPos, ControlPanel), Verdict> {
+    // we need to find any control panel
+    let mut first_toggled = config.initial_gates & config.toggling_gates;
+    let mut check = |entrance: Entrance, tile| {
+        if let Some(gate) = entrance.open_gate() {
+            first_toggled ^= gate & config.toggling_gates;
+        }
+        entrance.has_control_panel().then_some((entrance, tile))
+    };
+    let doors = &config.door_rules;
+    let gates = config.initial_gates;
+    stats.follow_chain_calls += 1;
+    let timer = Timer::start();
+    let first_panel = if both {
+        follow_chain_both(rooms, rotations, doors, gates, tile, direction, &mut check)
+    } else {
+        follow_chain(rooms, rotations, doors, gates, tile, direction, &mut check)
+    };
+    timer.stop(&mut stats.timings.follow_chain);
+    let (panel_entrance, panel_tile) = match first_panel {
+        Ok(Some(panel)) => panel,
+        Ok(None) => return Err(Verdict::NotBeatable("no control panel")),
+        Err(cycle) => return Err(Verdict::ChainCycle(cycle)),
+    };
+    // the start room's panel is found at its right door but reached through
+    // the bottom one, so match the panel by room
+    let panel_room = panel_entrance.to_room_direction().0;
+    let Some(panel) = enum_iterator::all::<ControlPanel>()
+        .find(|panel| panel.entrance().to_room_direction().0 == panel_room)
+    else {
+        return Err(Verdict::NotBeatable("no control panel"));
+    };
+    let state = RoomAndPos {
+        pos_tile: panel_tile,
+        pos_direction: rotations[panel_tile as usize]
+            .rotate(config.door_rules.direction(panel_entrance)),
+        rooms: *rooms,
+        rotations: *rotations,
+        toggled_gates: first_toggled,
+    };
+    Ok((state, panel))
+}
+
+/// Performs a single operation, `None` if it can't be done from `state`.
+/// The gates are the ones opened before the operation.
+pub(crate) fn perform(
+    state: &RoomAndPos,
+    operation: Operations,
+    gates: OpenedGates,
+    config: &SolverConfig,
+    stats: &mut SearchStats,
+) -> Result<Option<RoomAndPos>, ChainCycle> {
+    stats.counts_mut(operation).tried += 1;
+    // slides and turns are done from the panel the player stands at
+    let at = state.rooms[state.pos_tile as usize];
+    if !matches!(operation, Operations::Reach(_))
+        && config.locked_panels.iter().any(|panel| panel.room() == at)
+    {
+        return Ok(None);
+    }
+    let new_state = match operation {
+        Operations::Reach(panel) => {
+            let panel_entrance = panel.entrance();
+            walk_to_entrance(state, config, gates, panel_entrance, stats)?.map(
+                |(panel_tile, flipped)| RoomAndPos {
+                    rooms: state.rooms,
+                    rotations: state.rotations,
+                    pos_direction: state.rotations[panel_tile as usize]
+                        .rotate(config.door_rules.direction(panel_entrance)),
+                    pos_tile: panel_tile,
+                    toggled_gates: state.toggled_gates ^ flipped,
+                },
+            )
+        }
+        Operations::Move(slot, direction) => {
+            // if we move up into the empty space, we swap with the tile that is down
+            let empty_tile = empty_tile(&state.rooms, slot);
+            let other_tile = empty_tile
+                .and_then(|empty_tile| do_move(empty_tile, direction))
+                .map(|(other_tile, _)| other_tile);
+            match (empty_tile, other_tile) {
+                // sliding an empty tile into another one doesn't change anything
+                (Some(empty_tile), Some(other_tile))
+                    if other_tile != state.pos_tile
+                        && state.rooms[other_tile as usize] != Room::Empty =>
+                {
+                    let mut rooms = state.rooms;
+                    rooms.swap(other_tile.into(), empty_tile.into());
+                    let mut rotations = state.rotations;
+                    rotations.swap(other_tile.into(), empty_tile.into());
+                    Some(RoomAndPos {
+                        rooms,
+                        rotations,
+                        pos_tile: state.pos_tile,
+                        pos_direction: state.pos_direction,
+                        toggled_gates: state.toggled_gates,
+                    })
+                }
+                _ => None,
+            }
+        }
+        Operations::Rotate(slot, direction) => {
+            let other_tile = empty_tile(&state.rooms, slot)
+                .and_then(|empty_tile| do_move(empty_tile, direction))
+                .map(|(other_tile, _)| other_tile);
+            match other_tile {
+                // the room the player stands in can't be turned
+                Some(other_tile)
+                    if config.rotatable_rooms
+                        && other_tile != state.pos_tile
+                        && state.rooms[other_tile as usize] != Room::Empty =>
+                {
+                    let mut rotations = state.rotations;
+                    rotations[other_tile as usize] = rotations[other_tile as usize].clockwise();
+                    Some(RoomAndPos {
+                        rooms: state.rooms,
+                        rotations,
+                        pos_tile: state.pos_tile,
+                        pos_direction: state.pos_direction,
+                        toggled_gates: state.toggled_gates,
+                    })
+                }
+                _ => None,
+            }
+        }
+    };
+    if new_state.is_some() {
+        stats.counts_mut(operation).done += 1;
+    }
+    Ok(new_state)
+}
+
+/// What [`look_around`] found from a position with some gates open, kept
+/// for one search, which gets to the same positions over and over, or for a
+/// whole [`Session`](crate::session::Session). It only depends on the door
+/// rules and the toggling gates of the config besides the position.
+pub(crate) struct ChainCache {
+    /// by the packed position and the bits of the gates
+    entries: HashMap<(u64, u8), Walked, StateHasher>,
+}
+
+/// the gates open afterwards and the entrances reached, one bit each
+type Walked = Result<(OpenedGates, u32), ChainCycle>;
+
+impl ChainCache {
+    pub(crate) fn new(hasher: StateHasher) -> Self {
+        ChainCache {
+            entries: HashMap::with_hasher(hasher),
+        }
+    }
+}
+
+/// Walks around from the player's position after an operation, returning the
+/// gates that are open afterwards and the entrances that can be reached, one
+/// bit per entrance. Looked up in `cache` instead if it's there.
+pub(crate) fn look_around(
+    state: &RoomAndPos,
+    gates: OpenedGates,
+    config: &SolverConfig,
+    stats: &mut SearchStats,
+    cache: Option<&mut ChainCache>,
+) -> Walked {
+    let Some(cache) = cache else {
+        return walk_around(state, gates, config, stats);
+    };
+    let key = (pack(state), gates.bits());
+    let timer = Timer::start();
+    let known = cache.entries.get(&key).copied();
+    timer.stop(&mut stats.timings.hashing);
+    if let Some(found) = known {
+        stats.chain_cache_hits += 1;
+        return found;
+    }
+    let found = walk_around(state, gates, config, stats);
+    let timer = Timer::start();
+    cache.entries.insert(key, found);
+    timer.stop(&mut stats.timings.hashing);
+    found
+}
+
+fn walk_around(
+    state: &RoomAndPos,
+    gates: OpenedGates,
+    config: &SolverConfig,
+    stats: &mut SearchStats,
+) -> Walked {
+    let mut reached = 0;
+    let mut gates = gates.difference(config.toggling_gates) | state.toggled_gates;
+    // looking around means walking back the same way, so toggling
+    // gates are passed twice and end up unchanged
+    stats.follow_chain_calls += 1;
+    let timer = Timer::start();
+    let walked = follow_chain_both::<()>(
+        &state.rooms,
+        &state.rotations,
+        &config.door_rules,
+        gates,
+        state.pos_tile,
+        state.pos_direction,
+        &mut |e, _| {
+            if let Some(gate) = e.open_gate() {
+                gates |= gate.difference(config.toggling_gates);
+            }
+            reached |= 1 << e as u32;
+            None
+        },
+    );
+    timer.stop(&mut stats.timings.follow_chain);
+    walked?;
+    Ok((gates, reached))
+}
+
+/// walks from the player's position to `target` like [`follow_chain_both`],
+/// also returning the toggling gates whose trigger was passed on the way
+fn walk_to_entrance(
+    state: &RoomAndPos,
+    config: &SolverConfig,
+    gates: OpenedGates,
+    target: Entrance,
+    stats: &mut SearchStats,
+) -> Result<Option<(u8, OpenedGates)>, ChainCycle> {
+    let starts = walk_starts(
+        &state.rooms,
+        &state.rotations,
+        &config.door_rules,
+        state.pos_tile,
+        state.pos_direction,
+    );
+    for (tile, direction) in starts {
+        let mut flipped = OpenedGates::empty();
+        stats.follow_chain_calls += 1;
+        let timer = Timer::start();
+        let found = follow_chain(
+            &state.rooms,
+            &state.rotations,
+            &config.door_rules,
+            gates,
+            tile,
+            direction,
+            &mut |entrance, tile| {
+                if let Some(gate) = entrance.open_gate() {
+                    flipped ^= gate & config.toggling_gates;
+                }
+                (entrance == target).then_some(tile)
+            },
+        );
+        timer.stop(&mut stats.timings.follow_chain);
+        if let Some(tile) = found? {
+            return Ok(Some((tile, flipped)));
+        }
+    }
+    Ok(None)
+}
+
+/// position of the `slot`th empty tile in board order
+pub(crate) fn empty_tile(rooms: &[Room; 9], slot: EmptySlot) -> Option<u8> {
+    rooms
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| *r == &Room::Empty)
+        .nth(slot as usize)
+        .map(|(tile, _)| tile as u8)
+}
+
+pub fn verify_rooms(rooms: &[Room; 9], config: &SolverConfig) -> Verification {
+    verify_rotated_rooms(rooms, &NO_ROTATIONS, config)
+}
+
+/// verifies a layout where rooms can start out turned
+pub fn verify_rotated_rooms(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    config: &SolverConfig,
+) -> Verification {
+    verify_cached(
+        rooms,
+        rotations,
+        config,
+        &mut ChainCache::new(config.hasher),
+    )
+}
+
+/// [`verify_rotated_rooms`] with the walks around kept in `chain_cache`
+pub(crate) fn verify_cached(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    config: &SolverConfig,
+    chain_cache: &mut ChainCache,
+) -> Verification {
+    let start_time = Instant::now();
+    let mut stats = SearchStats::default();
+    let mut unreachable_entrances = config.win_condition.targets(rooms, &config.markers);
+    let verdict = verify_rooms_inner(
+        rooms,
+        rotations,
+        config,
+        &mut stats,
+        start_time,
+        &mut unreachable_entrances,
+        chain_cache,
+    );
+    stats.elapsed = start_time.elapsed();
+    Verification { verdict, stats }
+}
+
+/// Every entrance of the rooms on the board that can be reached at some
+/// point, ignoring the win condition. `Err` if the search was cut short or
+/// the layout is broken.
+pub fn reachable_entrances(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    config: &SolverConfig,
+) -> Result<BTreeSet<Entrance>, Verdict> {
+    let targets = WinCondition::AllEntrances.targets(rooms, &config.markers);
+    let mut unreachable_entrances = targets.clone();
+    let verdict = verify_rooms_inner(
+        rooms,
+        rotations,
+        config,
+        &mut SearchStats::default(),
+        Instant::now(),
+        &mut unreachable_entrances,
+        &mut ChainCache::new(config.hasher),
+    );
+    match verdict {
+        Verdict::Beatable | Verdict::NotBeatable(_) => Ok(targets
+            .difference(&unreachable_entrances)
+            .copied()
+            .collect()),
+        Verdict::Indeterminate(_) | Verdict::ChainCycle(_) => Err(verdict),
+    }
+}
+
+fn verify_rooms_inner(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    config: &SolverConfig,
+    stats: &mut SearchStats,
+    start_time: Instant,
+    unreachable_entrances: &mut BTreeSet<Entrance>,
+    chain_cache: &mut ChainCache,
+) -> Verdict {
+    // print_rooms(rooms);
+    let (mut current_pos_room, _) = match start_state(rooms, rotations, config, stats) {
+        Ok(start) => start,
+        Err(verdict) => return verdict,
+    };
+    // like the solver, nothing to reach is done right away
+    if unreachable_entrances.is_empty() {
+        return Verdict::Beatable;
+    }
+
+    let mut state_to_gate = match open_store(&config.visited_store, config.hasher) {
+        Ok(store) => store,
+        Err(_) => return Verdict::Indeterminate("visited store unavailable"),
+    };
+
+    let mut stash: Vec<(RoomAndPos, OpenedGates, Operations)> = Vec::new();
+    let mut unreachable_mask: u32 = unreachable_entrances
+        .iter()
+        .map(|entrance| 1 << *entrance as u32)
+        .sum();
+
+    let mut current_operation: Operations = Operations::first().unwrap();
+    let mut current_gates =
+        current_pos_room.toggled_gates | config.initial_gates.difference(config.toggling_gates);
+    let verdict = 'main_loop: loop {
+        if let Some(verdict) = out_of_budget(config, stats, start_time) {
+            break verdict;
+        }
+        if state_to_gate.failed() {
+            break Verdict::Indeterminate("visited store failed");
+        }
+        stats.expanded(stash.len());
+        // if (stats.nodes_expanded % 10000) == 0 {
+        //     println!("{}, {}", stats.nodes_expanded, state_to_gate.len());
+        //     print_rooms(&current_pos_room.rooms);
+        // }
+        // perform operation
+        let timer = Timer::start();
+        let op_result = match perform(
+            &current_pos_room,
+            current_operation,
+            current_gates,
+            config,
+            stats,
+        ) {
+            Ok(op_result) => op_result,
+            Err(cycle) => break 'main_loop Verdict::ChainCycle(cycle),
+        };
+        timer.stop(&mut stats.timings.successors);
+        match op_result {
+            // operation could be performed, see if this is a new state or if we can reach more gates now
+            Some(new_room_pos) => {
+                let gates_before = current_gates;
+                // try to open gates and reach entrances
+                let reached;
+                let timer = Timer::start();
+                (current_gates, reached) = match look_around(
+                    &new_room_pos,
+                    current_gates,
+                    config,
+                    stats,
+                    config.chain_cache.then_some(&mut *chain_cache),
+                ) {
+                    Ok(found) => found,
+                    Err(cycle) => break 'main_loop Verdict::ChainCycle(cycle),
+                };
+                timer.stop(&mut stats.timings.successors);
+                if reached & unreachable_mask != 0 || !gates_before.contains(current_gates) {
+                    stats.counts_mut(current_operation).progress += 1;
+                }
+                if reached & unreachable_mask != 0 {
+                    unreachable_mask &= !reached;
+                    unreachable_entrances.retain(|e| reached & 1 << *e as u32 == 0);
+                }
+                if unreachable_entrances.is_empty() {
+                    break Verdict::Beatable;
+                }
+                let timer = Timer::start();
+                let seen = state_to_gate.seen(&new_room_pos, current_gates);
+                timer.stop(&mut stats.timings.hashing);
+                if seen {
+                    // we already found this state, with at least these gates
+                    stats.transposition_hits += 1;
+                    // the toggling gates belong to the state we came from
+                    current_gates = current_gates.difference(config.toggling_gates)
+                        | gates_before.intersection(config.toggling_gates);
+                    // copied from err segment
+                    if let Some(nex_op) = current_operation.next() {
+                        current_operation = nex_op;
+                        continue 'main_loop;
+                    } else {
+                        while let Some((stack_room_pos, stack_gates, stack_op)) = stash.pop() {
+                            if let Some(next_op) = stack_op.next() {
+                                current_pos_room = stack_room_pos;
+                                current_operation = next_op;
+                                current_gates = stack_gates;
+                                continue 'main_loop;
+                            }
+                        }
+                        // we have reached the end of the stack
+                        break Verdict::NotBeatable("unreachable entrances");
+                    }
+                } else {
+                    // new state or we have other gates now, continue. The
+                    // gates it was reached with before are kept unless
+                    // they're part of these, another path can open
+                    // different gates instead of more
+                    debug_assert_eq!(current_gates.validate(), Ok(()));
+                    let timer = Timer::start();
+                    state_to_gate.insert(&new_room_pos, current_gates);
+                    timer.stop(&mut stats.timings.hashing);
+                }
+                // this is now our new state, push the current one to the stack and restart operation
+                stash.push((current_pos_room, gates_before, current_operation));
+                current_operation = Operations::first().unwrap();
+                current_pos_room = new_room_pos;
+            }
+            // operation couldn't be performed, try the next one
+            // if there isn't one, pop one from the stack
+            // if there isn't one, we're done
+            None => {
+                if let Some(nex_op) = current_operation.next() {
+                    current_operation = nex_op;
+                    continue 'main_loop;
+                } else {
+                    while let Some((stack_room_pos, stack_gates, stack_op)) = stash.pop() {
+                        if let Some(next_op) = stack_op.next() {
+                            current_pos_room = stack_room_pos;
+                            current_operation = next_op;
+                            current_gates = stack_gates;
+                            continue 'main_loop;
+                        }
+                    }
+                    // we have reached the end of the stack
+                    break Verdict::NotBeatable("unreachable entrances");
+                }
+            }
+        }
+    };
+
+    stats.states_visited = state_to_gate.len();
+
+    // let beatable = verify_rec(&mut state_to_gate, pos_room, gates, &mut counter, &mut unreachable_entrances);
+
+    // println!("beatable: {}", unreachable_entrances.is_empty());
+
+    verdict
+}
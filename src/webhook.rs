@@ -0,0 +1,103 @@
+//! Posting JSON to a webhook, so long runs can report back when they're done.
+//!
+//! There's no TLS here, `http://` URLs are posted to directly and `https://`
+//! ones, which services like Discord and Slack need, by the `curl` program
+//! on the `PATH` with the `curl` feature. Without it they're an error.
+
+use std::{
+    io::{self, Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+use crate::json::Json;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// POSTs `body` to `url` and fails unless the answer is a 2xx.
+pub fn post_json(url: &str, body: &Json) -> io::Result<()> {
+    let invalid = |e: &str| io::Error::new(io::ErrorKind::InvalidInput, format!("{url}: {e}"));
+    let rest = match url.split_once("://") {
+        Some(("http", rest)) => rest,
+        Some(("https", _)) => return post_with_curl(url, body),
+        _ => return Err(invalid("not an http URL")),
+    };
+    let (host, path) = match rest.find('/') {
+        Some(i) => rest.split_at(i),
+        None => (rest, "/"),
+    };
+    if host.is_empty() {
+        return Err(invalid("no host"));
+    }
+    let addr = if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{host}:80")
+    };
+
+    let mut stream = TcpStream::connect(addr)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let body = body.to_string();
+    write!(
+        stream,
+        "POST {path} HTTP/1.1\r\n\
+         Host: {host}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n\
+         {body}",
+        body.len()
+    )?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response)?;
+    let response = String::from_utf8_lossy(&response);
+    let status = response.lines().next().unwrap_or_default();
+    match status.split_whitespace().nth(1) {
+        Some(code) if code.starts_with('2') => Ok(()),
+        _ => Err(io::Error::other(format!("{url} answered {status:?}"))),
+    }
+}
+
+#[cfg(feature = "curl")]
+fn post_with_curl(url: &str, body: &Json) -> io::Result<()> {
+    use std::process::{Command, Stdio};
+
+    let mut child = Command::new("curl")
+        .args([
+            "-sS",
+            "--fail",
+            "-X",
+            "POST",
+            "-H",
+            "Content-Type: application/json",
+        ])
+        .arg("--max-time")
+        .arg(TIMEOUT.as_secs().to_string())
+        .args(["--data-binary", "@-", "--", url])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| io::Error::new(e.kind(), format!("can't run curl: {e}")))?;
+    // the body is small enough for the pipe, curl reads all of it before
+    // sending anything
+    let mut stdin = child.stdin.take().expect("piped");
+    stdin.write_all(body.to_string().as_bytes())?;
+    drop(stdin);
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(format!("{url}: {}", error.trim())));
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "curl"))]
+fn post_with_curl(url: &str, _: &Json) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("{url}: https webhooks need the curl feature"),
+    ))
+}
@@ -0,0 +1,209 @@
+//! A WebSocket server that only ever sends, for overlays that show the
+//! puzzle while it's being played. Every connected client gets each message,
+//! clients that connect later start with the last one.
+//!
+//! The messages are JSON made by [`overlay_message`].
+
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::{Arc, Mutex, PoisonError},
+    thread,
+};
+
+use crate::{
+    json::Json,
+    rooms::{gate_names, OpenedGates, Room, Rotation},
+    share::layout_code,
+    verify::Operations,
+};
+
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+#[derive(Default)]
+struct Clients {
+    streams: Vec<TcpStream>,
+    last: Option<String>,
+}
+
+pub struct Broadcaster {
+    clients: Arc<Mutex<Clients>>,
+}
+
+impl Broadcaster {
+    /// Listens on `addr` and accepts clients in the background.
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let clients = Arc::new(Mutex::new(Clients::default()));
+        let accepted = Arc::clone(&clients);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else {
+                    continue;
+                };
+                // a client that doesn't speak WebSocket is just dropped
+                if handshake(&mut stream).is_err() {
+                    continue;
+                }
+                let mut clients = accepted.lock().unwrap_or_else(PoisonError::into_inner);
+                if let Some(last) = &clients.last {
+                    if write_frame(&mut stream, last).is_err() {
+                        continue;
+                    }
+                }
+                clients.streams.push(stream);
+            }
+        });
+        Ok(Broadcaster { clients })
+    }
+
+    /// Sends the text to every client, dropping the ones that are gone.
+    pub fn send(&self, text: &str) {
+        let mut clients = self.clients.lock().unwrap_or_else(PoisonError::into_inner);
+        clients
+            .streams
+            .retain_mut(|stream| write_frame(stream, text).is_ok());
+        clients.last = Some(text.to_string());
+    }
+}
+
+/// The board and a hint for an overlay: the layout's share `code`, the
+/// player's `tile`, the open `gates`, and the length of an optimal finish
+/// from there with its first operation as `remaining` and `next`, both
+/// null if there's none.
+pub fn overlay_message(
+    rooms: &[Room; 9],
+    rotations: &[Rotation; 9],
+    tile: u8,
+    gates: OpenedGates,
+    finish: Option<&[Operations]>,
+) -> Json {
+    Json::object([
+        ("code", layout_code(rooms, rotations).into()),
+        ("tile", (tile as usize).into()),
+        ("gates", gate_names(gates).into()),
+        (
+            "remaining",
+            finish.map_or(Json::Null, |ops| ops.len().into()),
+        ),
+        (
+            "next",
+            finish
+                .and_then(|ops| ops.first())
+                .map_or(Json::Null, |op| format!("{op:?}").into()),
+        ),
+    ])
+}
+
+fn handshake(stream: &mut TcpStream) -> io::Result<()> {
+    let mut key = None;
+    let mut reader = BufReader::new(&*stream);
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("sec-websocket-key") {
+                key = Some(value.trim().to_string());
+            }
+        }
+    }
+    let key = key.ok_or(io::ErrorKind::InvalidData)?;
+    let accept = base64(&sha1(format!("{key}{HANDSHAKE_GUID}").as_bytes()));
+    write!(
+        stream,
+        "HTTP/1.1 101 Switching Protocols\r\n\
+         Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {accept}\r\n\r\n"
+    )
+}
+
+/// a single unmasked text frame
+fn write_frame(stream: &mut TcpStream, text: &str) -> io::Result<()> {
+    let len = text.len();
+    let mut frame = vec![0x81];
+    if len < 126 {
+        frame.push(len as u8);
+    } else if len <= u16::MAX as usize {
+        frame.push(126);
+        frame.extend_from_slice(&(len as u16).to_be_bytes());
+    } else {
+        frame.push(127);
+        frame.extend_from_slice(&(len as u64).to_be_bytes());
+    }
+    frame.extend_from_slice(text.as_bytes());
+    stream.write_all(&frame)
+}
+
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+    for chunk in message.chunks_exact(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in chunk.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes(word.try_into().unwrap());
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (h, v) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(v);
+        }
+    }
+    let mut digest = [0; 20];
+    for (bytes, h) in digest.chunks_exact_mut(4).zip(h) {
+        bytes.copy_from_slice(&h.to_be_bytes());
+    }
+    digest
+}
+
+/// standard base64 with padding, unlike the layout codes
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in data.chunks(3) {
+        let bits = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |acc, (i, byte)| acc | (*byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(bits >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}